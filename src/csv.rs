@@ -0,0 +1,38 @@
+//! Minimal CSV writer for `--csv`: RFC 4180 rows, quoting only the fields
+//! that need it.
+
+/// `value` as a CSV field: quoted, with inner quotes doubled, when it holds a
+/// comma, a quote or a line break.
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One CSV line, line break included.
+pub fn row<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields.iter().map(|f| field(f.as_ref())).collect::<Vec<_>>().join(",");
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_quoting() {
+        assert_eq!(field("plain"), "plain");
+        assert_eq!(field(""), "");
+        assert_eq!(field("/a,/b"), "\"/a,/b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_row() {
+        assert_eq!(row(&["io", "0", "/a,/b", ""]), "io,0,\"/a,/b\",\n");
+    }
+}
@@ -0,0 +1,151 @@
+//! `StressError`, the failures the engine hands back instead of exiting: a
+//! run that cannot start as configured, a worker that cannot do its I/O, or
+//! a run that failed, abandoned a worker or was interrupted. Each variant has
+//! the exit status the binary ends with.
+
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum StressError {
+    /// A setting or combination of settings that cannot be run as given.
+    InvalidConfig(String),
+    /// An I/O failure on `path`: a worker file or directory, the log file, or
+    /// an address to listen on or connect to.
+    Io { path: String, source: io::Error },
+    /// The worker files do not fit on these filesystems, all listed at once.
+    OutOfSpace(Vec<SpaceShortfall>),
+    /// Something this platform or kernel cannot do.
+    Unsupported(String),
+    /// The run was not started: the confirmation prompt was declined or
+    /// `--preflight` failed.
+    Aborted(String),
+    /// The run finished, but a worker or an end-of-run check failed.
+    Failed(String),
+    /// A worker was still running `--grace` after its duration and was left
+    /// behind.
+    Abandoned,
+    /// The run was stopped by Ctrl-C; the results reported are partial.
+    Interrupted,
+}
+
+/// A filesystem where the worker files need `needed` bytes but only
+/// `available` are free; `path` lists the I/O paths on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceShortfall {
+    pub path: String,
+    pub needed: u64,
+    pub available: u64,
+}
+
+impl StressError {
+    pub(crate) fn io(path: &str, source: io::Error) -> Self {
+        StressError::Io { path: path.to_string(), source }
+    }
+
+    /// The exit status the binary ends with for this error. An `Io` error
+    /// only comes back before anything was stressed, so it counts as a
+    /// configuration failure.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StressError::Failed(_) => crate::FAILED_EXIT_CODE,
+            StressError::Abandoned => crate::ABANDONED_EXIT_CODE,
+            StressError::Interrupted => crate::INTERRUPTED_EXIT_CODE,
+            StressError::InvalidConfig(_)
+            | StressError::Io { .. }
+            | StressError::OutOfSpace(_)
+            | StressError::Unsupported(_)
+            | StressError::Aborted(_) => crate::CONFIG_EXIT_CODE,
+        }
+    }
+}
+
+impl fmt::Display for StressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StressError::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            StressError::Io { path, source } => write!(f, "I/O error on {}: {}", path, source),
+            StressError::OutOfSpace(shortfalls) => {
+                let mb = |bytes: u64| bytes / (1024 * 1024);
+                match shortfalls.as_slice() {
+                    [one] => write!(
+                        f,
+                        "Not enough space on {}: need {} MB for the worker files, have {} MB free",
+                        one.path,
+                        mb(one.needed),
+                        mb(one.available)
+                    )?,
+                    _ => {
+                        write!(
+                            f,
+                            "Not enough space for the worker files on {} filesystems:",
+                            shortfalls.len()
+                        )?;
+                        for (i, s) in shortfalls.iter().enumerate() {
+                            let sep = if i == 0 { "" } else { ";" };
+                            write!(
+                                f,
+                                "{} {} needs {} MB, has {} MB free",
+                                sep,
+                                s.path,
+                                mb(s.needed),
+                                mb(s.available)
+                            )?;
+                        }
+                    }
+                }
+                write!(f, " (lower --io-size or --io-workers, or pass --io-allow-overcommit)")
+            }
+            StressError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            StressError::Aborted(msg) => write!(f, "Not starting the run: {}", msg),
+            StressError::Failed(msg) => write!(f, "{}", msg),
+            StressError::Abandoned => write!(
+                f,
+                "One or more workers were abandoned after --grace; see the errors above"
+            ),
+            StressError::Interrupted => write!(f, "Interrupted: the results are partial"),
+        }
+    }
+}
+
+impl std::error::Error for StressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StressError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_display_and_source() {
+        let e = StressError::InvalidConfig("--repeat must be at least 1".into());
+        assert_eq!(e.to_string(), "Invalid configuration: --repeat must be at least 1");
+        assert!(e.source().is_none());
+
+        let e = StressError::io("/mnt/x", io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(e.to_string().starts_with("I/O error on /mnt/x: "));
+        assert!(e.source().is_some());
+
+        let tmp = SpaceShortfall { path: "/tmp".into(), needed: 3 << 20, available: 1 << 20 };
+        let e = StressError::OutOfSpace(vec![tmp.clone()]);
+        assert!(e.to_string().starts_with("Not enough space on /tmp: need 3 MB"));
+        let mnt = SpaceShortfall { path: "/mnt".into(), needed: 5 << 20, available: 0 };
+        let e = StressError::OutOfSpace(vec![tmp, mnt]);
+        assert!(e.to_string().starts_with(
+            "Not enough space for the worker files on 2 filesystems: \
+             /tmp needs 3 MB, has 1 MB free; /mnt needs 5 MB, has 0 MB free"
+        ));
+
+        let e = StressError::Aborted("preflight failed".into());
+        assert_eq!(e.to_string(), "Not starting the run: preflight failed");
+        assert_eq!(e.exit_code(), crate::CONFIG_EXIT_CODE);
+        assert_eq!(StressError::Abandoned.exit_code(), crate::ABANDONED_EXIT_CODE);
+        assert_eq!(StressError::Interrupted.exit_code(), crate::INTERRUPTED_EXIT_CODE);
+    }
+}
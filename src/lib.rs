@@ -0,0 +1,11520 @@
+//! The stress engine behind the `stressr` binary. Build a [`Config`] (or parse
+//! a command line with [`Config::from_args`]) and drive [`stress_cpu`], [`stress_memory`]
+//! or [`run_disk_io`] directly, or hand it to [`run`] for the full CLI flow.
+//! Failures come back as a [`StressError`].
+
+#[macro_use]
+mod log;
+
+mod csv;
+mod error;
+mod json;
+mod rng;
+#[cfg(target_os = "linux")]
+mod uring;
+
+pub use error::{SpaceShortfall, StressError};
+use json::Json;
+pub use log::LogLevel;
+use rng::Rng;
+use std::env;
+use std::fs::{OpenOptions, remove_file};
+use std::io::{BufRead, BufReader, ErrorKind, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Barrier, Mutex, OnceLock, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Set by `--output json`: human-readable result lines are suppressed and the
+/// results are printed as one JSON object at the end instead.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// `println!` for human-readable results; a no-op under `--output json`.
+macro_rules! report {
+    ($($arg:tt)*) => {
+        if !JSON_OUTPUT.load(Ordering::Relaxed) {
+            $crate::log::emit(false, format!($($arg)*));
+        }
+    };
+}
+
+/// Prints the version line, for `--version`.
+pub fn print_version() {
+    println!("stressr v0.1.0");
+}
+
+/// Prints the usage text, for `--help` or a bare command line.
+pub fn print_help() {
+    println!(
+        r#"stressr - A zero-dependency Rust stress testing tool
+
+Usage:
+  stressr [OPTIONS]
+
+Results are written to stdout; progress and diagnostics go to stderr, so
+`stressr ... > results.txt` captures only the results. Use --quiet or -v to
+show less or more on stderr.
+
+General Options:
+  --cpu-percent <N>       CPU load per thread (0–100)
+  --cpu-total             Treat --cpu-percent as total machine load, spread across the threads
+  --memory-percent <N>    Percent of total RAM to allocate (0–100)
+  --memory-use <BASE>     What --memory-percent is relative to: total (default) or available
+  --memory-threads <N>    Split the memory target across N threads that stream over it (default: 1)
+  --memory-hold <TIME>    Hold the allocated memory this long (default: --duration)
+  --memory-free <TIME>    After the hold, free the memory gradually over this long
+  --memory-hugepages      Back the memory blocks with 2 MB huge pages (Linux only)
+  --memory-mlock          Lock the memory blocks into RAM (mlock/VirtualLock) so they
+                          cannot be swapped out
+  --duration <TIME>       Duration for CPU and memory stress (30s, 5m, 2h; bare = seconds)
+  --cpu-workload <KIND>   CPU kernel: int (default), float (f64 multiply-add), sqrt,
+                          matrix (small f64 matrix products), avx512 (wide
+                          vector FMA), cache (pointer chasing through
+                          --cache-size) or primes (an L2-sized prime sieve);
+                          --cpu-method is an alias
+  --cache-size <MB>       Per-thread buffer for the cache workload (default: 64)
+  --cpu-ramp <TIME>       Raise CPU load linearly from 0 to --cpu-percent over this long,
+                          then hold it for the rest of --duration
+  --cpu-system-percent <N>
+                          Hold overall system CPU utilization (from /proc/stat) at N%
+  --cpu-threads <N>       CPU stress threads (default 0: one per core)
+  --cpu-affinity <LIST>   Pin CPU threads to these core IDs (e.g. 0,2,4), cycling
+                          through the list when there are more threads
+  --numa-node <N>         Pin CPU threads to NUMA node N's cores and bind memory to it (Linux)
+  --cpu-trace <FILE>      Write a time series of aggregate CPU busy fraction to FILE
+  --signal-throttle       SIGTSTP halves CPU load and I/O rate (repeatable), SIGCONT restores
+  --signal-adjust         SIGUSR1 raises the CPU target by 10%, SIGUSR2 lowers it by 10%
+  --sequential            Run CPU, then memory, then I/O stress one after another instead
+                          of all at once
+  --benchmark             Find the peak instead: step up CPU threads, then I/O workers and
+                          queue depth, in short probes until throughput stops rising
+
+Disk I/O Options:
+  --io                    Enable disk I/O stress
+  --io-paths <DIR1,...>   Comma-separated list of target directories (default: system temp dir)
+  --io-workers <N>        Number of threads per path
+  --io-size <SIZE>        File size per worker: 512K, 100M, 4G (bare number = MB)
+  --io-size-percent <N>   Size workers to N% of each path's own free space (overrides --io-size)
+  --io-files-per-worker <N>
+                          Split each worker's --io-size over N files and spread its ops
+                          across them (round-robin, or at random with --io-random)
+  --io-allow-overcommit   Run even if the worker files do not fit in the free space
+  --io-duration <TIME>    Duration of I/O stress test (same format as --duration)
+  --warmup <TIME>         Leave the first part of --io-duration out of the I/O results
+  --io-read               Enable disk reads
+  --io-write              Enable disk writes
+  --io-rwmix <N>          Make each op a read with N% probability, otherwise a write,
+                          instead of a write plus a read (implies --io-read --io-write)
+  --io-random             Enable random (seek-based) access
+  --seed <N>              Seed for random offsets and payloads, for reproducible runs (default: 0)
+  --chunk-size <SIZE>     Chunk size per read/write operation (bare number = KB)
+  --io-read-chunk <SIZE>  Chunk size for reads (defaults to --chunk-size)
+  --io-write-chunk <SIZE> Chunk size for writes (defaults to --chunk-size)
+  --io-metadata           Churn small files (create/write/fsync/delete) instead of data I/O
+  --io-metadata-ops       Add renames, hardlink churn and mkdir/rmdir to --io-metadata (implies it)
+  --io-churn              Create, write 4 KB, close and delete one file after another, reporting
+                          files/s instead of data throughput
+  --io-trace <FILE>       Replay "R|W <offset> <size>" lines (bytes) from FILE
+  --io-trace-once         Replay the trace a single time instead of looping for --io-duration
+  --io-processes          Run each I/O worker in its own process instead of a thread
+  --io-pause-below <MB>   Pause I/O while available memory is below this many MB
+  --io-pattern <KIND>     Write payload: random (default), zero or incrementing (each 8-byte
+                          word holds its index in the file)
+  --io-fill <HEX>         Write this byte (e.g. FF, 0xAA) everywhere instead of random data
+  --io-pretrim            Discard (punch out) each worker file's blocks before timed writes
+  --io-keep               Leave the worker files in place after the run
+  --io-reuse              Run against existing worker_<N>.tmp files as they are instead of
+                          creating and sizing them (implies --io-keep)
+  --io-verify             Check every read against the written pattern as the run goes
+  --io-verify-pass        Verify every worker file against the written pattern after the run
+  --io-direct             Bypass the page cache (O_DIRECT, F_NOCACHE or FILE_FLAG_NO_BUFFERING);
+                          chunks and offsets are aligned to 4096 bytes
+  --io-block-align <SIZE> Align offsets and chunk sizes to SIZE bytes (e.g. 4096) in
+                          buffered mode too, so no op straddles a sector boundary
+  --io-fsync              fsync after every write so throughput reflects durable writes
+  --io-fsync-every <N>    fsync after every N writes instead (implies --io-fsync)
+  --io-sync-mode <MODE>   none, fsync, fdatasync (sync after writes, every --io-fsync-every)
+                          or osync (open the files O_SYNC); --io-fsync is the same as fsync
+  --io-queue-depth <N>    Keep N reads/writes in flight per worker with io_uring (Linux;
+                          default: 1, synchronous); each read or write counts as one op
+  --io-target-iops <N>    Pace all workers to N ops/s in total and check the target is reached
+  --io-rate <N>           Throttle all workers to at most N ops/s in total
+  --io-bandwidth <MB/s>   Throttle all workers to at most this many MB/s in total
+  --io-max-latency <MS>   Fail the run if any single I/O op takes longer than this
+  --io-max-latency-soft   Only count ops over --io-max-latency instead of aborting
+  --raise-fd-limit        Raise the open-file soft limit toward the hard limit if needed
+
+Network Options:
+  --net                   Enable network stress (with --net-server or --net-connect)
+  --net-server <BIND>     Accept connections on BIND (e.g. 0.0.0.0:5201) and drain them
+  --net-connect <ADDR>    Send to a --net-server at HOST:PORT (implies --net, like --net-server)
+  --net-conns <N>         Client connections in parallel, one thread each (default: 4)
+  --net-size <SIZE>       Data each client connection sends before reconnecting (bare = MB,
+                          default: 64)
+  --net-duration <TIME>   Duration of network stress (same format as --duration)
+
+Output Options:
+  --label <KEY=VALUE>     Attach a tag to the results (repeatable)
+  --output <FORMAT>       Result format: text (default) or json (one object on stdout)
+  -q, --quiet             Only log errors to stderr
+  -v, --verbose           Also log debug detail, such as the full configuration (-vv works too)
+  --log-file <FILE>       Also append timestamped diagnostics, the config and the start and
+                          end of the run to FILE
+  --csv <FILE>            Append one row per I/O worker plus totals to FILE (header if new)
+  --precision <N>         Decimal places for reported throughput (default 2)
+  --compare-last          Show percent changes against the previous run's results
+  --repeat <N>            Run the whole test N times and report the spread of the results
+  --progress <TIME>       Print a progress snapshot to stderr every interval (JSON lines
+                          under --output json)
+  --stats-addr <ADDR>     Serve live Prometheus metrics at http://ADDR/metrics during the run
+  --tui                   Show a full-screen live dashboard while running (stdout must be a
+                          terminal)
+
+Safety Options:
+  --yes, --force          Skip the confirmation prompt for large or destructive runs
+  --confirm-threshold <MB>
+                          Ask before runs whose I/O files total more than this (default 10240)
+  --preflight             Smoke-test each enabled subsystem for ~1s and abort if any fails
+  --dry-run               Run every check, print the resolved plan and exit without stressing
+  --limit-memory <MB>     Cap the process address space (RLIMIT_AS) before starting
+  --limit-cpu-time <SECS> Cap the process CPU time (RLIMIT_CPU) before starting
+  --grace <TIME>          Give up on workers still running this long after their duration
+                          (e.g. stuck on a dead mount), list them and exit with status 3
+
+Distributed Options:
+  --coordinate <ADDR>     Act as the start barrier for --peers participants, then
+                          print their aggregated results (runs no stress itself)
+  --peers <N>             Number of participants to wait for (default 2)
+  --coordinator <ADDR>    Check in with a coordinator and start on its signal
+  --delay <TIME>          Wait this long before starting the stress
+  --start-at <HH:MM[:SS]> Wait for the next occurrence of this local time before starting
+
+Config Options:
+  --config <SOURCE>       Read `key = value` options from a file, `-` (stdin) or an
+                          http:// URL; keys are flag names (e.g. io_workers = 4),
+                          and command-line flags override them
+
+Environment:
+  Every flag can also be set as STRESSR_<FLAG> in upper snake case, e.g.
+  STRESSR_CPU_PERCENT=50, STRESSR_IO_WORKERS=4, STRESSR_DURATION=5m or
+  STRESSR_IO_RANDOM=true. Command-line flags override --config values, which
+  override environment variables, which override the defaults.
+
+Exit Status:
+  0    The run completed and every worker and check succeeded
+  1    A worker failed (I/O error, panic, worker process died) or an end-of-run
+       check (--io-target-iops, --io-max-latency) failed
+  2    Invalid configuration, unsupported option, not enough free space, a
+       failed pre-run check or an I/O error before the run (log file, listen
+       address, coordinator); nothing was stressed
+  3    A worker did not finish within --grace and was abandoned
+  130  Interrupted by Ctrl-C (partial results are printed)
+
+Help:
+  -h, --help              Show this help message
+  -V, --version           Show the version
+"#
+    );
+}
+
+/// Instruction mix executed during the busy part of each CPU duty cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuWorkload {
+    Int,
+    /// Chained `f64` multiply-add.
+    Float,
+    /// Dependent `f64::sqrt` chain.
+    Sqrt,
+    /// Small fixed-size `f64` matrix products.
+    Matrix,
+    /// 512-bit FMA chains, to trigger AVX-512 frequency licenses.
+    Avx512,
+    /// Dependent loads around a random cycle of cache lines.
+    Cache,
+    /// A sieve of Eratosthenes over a range that fits in L2.
+    Primes,
+}
+
+impl CpuWorkload {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "int" => Some(CpuWorkload::Int),
+            "float" => Some(CpuWorkload::Float),
+            "sqrt" => Some(CpuWorkload::Sqrt),
+            "matrix" => Some(CpuWorkload::Matrix),
+            "avx512" => Some(CpuWorkload::Avx512),
+            "cache" => Some(CpuWorkload::Cache),
+            "primes" => Some(CpuWorkload::Primes),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CpuWorkload::Int => "int",
+            CpuWorkload::Float => "float",
+            CpuWorkload::Sqrt => "sqrt",
+            CpuWorkload::Matrix => "matrix",
+            CpuWorkload::Avx512 => "avx512",
+            CpuWorkload::Cache => "cache",
+            CpuWorkload::Primes => "primes",
+        }
+    }
+}
+
+/// What `--memory-percent` is a percentage of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryBase {
+    Total,
+    /// What the OS reports as available, so other processes' memory is left alone.
+    Available,
+}
+
+impl MemoryBase {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "total" => Some(MemoryBase::Total),
+            "available" => Some(MemoryBase::Available),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MemoryBase::Total => "total",
+            MemoryBase::Available => "available",
+        }
+    }
+}
+
+/// How results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Data written by I/O workers, for testing how storage reacts to entropy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPattern {
+    /// Incompressible PRNG bytes.
+    Random,
+    /// All zeros, as compressible as data gets.
+    Zero,
+    /// Each 8-byte word holds its own word index in the file.
+    Incrementing,
+}
+
+impl IoPattern {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "random" => Some(IoPattern::Random),
+            "zero" => Some(IoPattern::Zero),
+            "incrementing" => Some(IoPattern::Incrementing),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            IoPattern::Random => "random",
+            IoPattern::Zero => "zero",
+            IoPattern::Incrementing => "incrementing",
+        }
+    }
+}
+
+/// How written data is made durable, to compare the cost of each primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoSyncMode {
+    /// Writes land in the page cache.
+    None,
+    /// `fsync` (data and metadata) after every `io_fsync_every` writes.
+    Fsync,
+    /// `fdatasync` (data, and only the metadata needed to read it back) instead.
+    Fdatasync,
+    /// The files are opened with `O_SYNC`, so every write is synchronous.
+    Osync,
+}
+
+impl IoSyncMode {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(IoSyncMode::None),
+            "fsync" => Some(IoSyncMode::Fsync),
+            "fdatasync" => Some(IoSyncMode::Fdatasync),
+            "osync" => Some(IoSyncMode::Osync),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            IoSyncMode::None => "none",
+            IoSyncMode::Fsync => "fsync",
+            IoSyncMode::Fdatasync => "fdatasync",
+            IoSyncMode::Osync => "osync",
+        }
+    }
+
+    /// Whether workers make a sync call after writes, as opposed to none or
+    /// one built into every write.
+    fn syncs_after_writes(&self) -> bool {
+        matches!(self, IoSyncMode::Fsync | IoSyncMode::Fdatasync)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub cpu_percent: u64,
+    /// `cpu_percent` is a share of all cores rather than a per-thread load.
+    pub cpu_total: bool,
+    pub cpu_workload: CpuWorkload,
+    /// Per-thread buffer chased by the `cache` workload, in MiB.
+    pub cache_size_mb: u64,
+    pub cpu_trace: Option<String>,
+    pub cpu_affinity: Vec<usize>,
+    /// NUMA node whose cores and memory the CPU and memory stress use.
+    pub numa_node: Option<usize>,
+    /// Worker threads; 0 means one per available core.
+    pub cpu_threads: usize,
+    pub cpu_system_percent: Option<u64>,
+    /// Seconds over which the load rises linearly from zero to target.
+    pub cpu_ramp_secs: u64,
+    pub signal_throttle: bool,
+    /// SIGUSR1 raises and SIGUSR2 lowers the CPU target while the run is going.
+    pub signal_adjust: bool,
+    /// Run the enabled stressors one after another rather than together.
+    pub sequential: bool,
+    /// Search for the peak CPU and I/O throughput instead of a normal run.
+    pub benchmark: bool,
+    pub memory_percent: u64,
+    pub memory_use: MemoryBase,
+    /// Threads sharing the memory target; more than one streams over it.
+    pub memory_threads: usize,
+    /// How long memory is held once allocated; `duration_secs` when unset.
+    pub memory_hold_secs: Option<u64>,
+    /// Release memory in steps over this long after the hold, rather than
+    /// all at once.
+    pub memory_free_secs: Option<u64>,
+    /// Back the memory blocks with huge pages (Linux only).
+    pub memory_hugepages: bool,
+    /// Lock the allocated blocks into RAM so they are never swapped out.
+    pub memory_mlock: bool,
+    pub duration_secs: u64,
+    pub io_enabled: bool,
+    pub io_paths: Vec<String>,
+    pub io_workers: usize,
+    pub io_size_bytes: u64,
+    pub io_size_percent: Option<u64>,
+    /// Skip the free-space check and let the worker files be sparse.
+    pub io_allow_overcommit: bool,
+    pub io_duration_secs: u64,
+    /// Leading part of `io_duration_secs` excluded from the I/O results.
+    pub warmup_secs: u64,
+    pub io_random: bool,
+    /// Seed for offsets and payloads; each worker derives its own stream.
+    pub seed: u64,
+    pub io_read: bool,
+    pub io_write: bool,
+    /// Percent of ops that are reads, picked per op, instead of every op
+    /// writing and then reading.
+    pub io_rwmix: Option<u64>,
+    pub chunk_size_bytes: usize,
+    pub io_read_chunk_bytes: Option<usize>,
+    pub io_write_chunk_bytes: Option<usize>,
+    pub io_metadata: bool,
+    pub io_metadata_ops: bool,
+    /// Create, write, close and delete one small file after another.
+    pub io_churn: bool,
+    pub io_trace: Option<String>,
+    pub io_trace_once: bool,
+    pub io_processes: bool,
+    pub io_worker_child: Option<(usize, String)>,
+    pub io_pause_below_mb: Option<u64>,
+    pub io_verify: bool,
+    pub io_verify_pass: bool,
+    /// Open worker files for unbuffered I/O that bypasses the page cache.
+    pub io_direct: bool,
+    /// Round offsets down and chunk sizes up to this many bytes, buffered or not.
+    pub io_block_align: Option<u64>,
+    pub io_sync_mode: IoSyncMode,
+    /// Writes between syncs under `IoSyncMode::Fsync` or `Fdatasync`.
+    pub io_fsync_every: u64,
+    /// Reads and writes each worker keeps in flight; above 1 uses io_uring.
+    pub io_queue_depth: usize,
+    /// Files each worker splits its `io_size_bytes` over.
+    pub io_files_per_worker: usize,
+    pub io_pretrim: bool,
+    /// Leave the worker files behind after the run.
+    pub io_keep: bool,
+    /// Use the worker files already in place instead of creating them.
+    pub io_reuse: bool,
+    pub io_pattern: IoPattern,
+    pub io_fill: Option<u8>,
+    pub io_max_latency_ms: Option<u64>,
+    pub io_target_iops: Option<u64>,
+    /// `--io-rate` / `--io-bandwidth` caps, shared by all workers.
+    pub io_rate: Option<u64>,
+    pub io_bandwidth_mbps: Option<f64>,
+    pub io_max_latency_soft: bool,
+    pub raise_fd_limit: bool,
+    pub net_enabled: bool,
+    /// `--net` roles: address to listen on, or server to send to.
+    pub net_server: Option<String>,
+    pub net_connect: Option<String>,
+    pub net_conns: usize,
+    pub net_size_bytes: u64,
+    pub net_duration_secs: u64,
+    pub labels: Vec<(String, String)>,
+    pub output: OutputFormat,
+    /// Least important stderr diagnostic that is still shown.
+    pub log_level: LogLevel,
+    /// File that diagnostics are appended to as well as stderr.
+    pub log_file: Option<String>,
+    /// File that per-worker result rows are appended to.
+    pub csv: Option<String>,
+    pub precision: usize,
+    pub assume_yes: bool,
+    pub confirm_threshold_mb: u64,
+    pub limit_memory_mb: Option<u64>,
+    pub limit_cpu_secs: Option<u64>,
+    /// How long after its duration a worker may keep running before it is
+    /// abandoned; None waits for ever.
+    pub grace_secs: Option<u64>,
+    pub preflight: bool,
+    /// Validate and print the plan, then exit without stressing anything.
+    pub dry_run: bool,
+    pub coordinate_bind: Option<String>,
+    pub peers: usize,
+    pub coordinator: Option<String>,
+    /// Wait before starting the stress.
+    pub delay_secs: u64,
+    /// Local time of day, in seconds after midnight, to start the stress at.
+    pub start_at: Option<u32>,
+    pub compare_last: bool,
+    /// Interval between `--progress` snapshots.
+    pub progress_secs: Option<u64>,
+    /// Address of the `/metrics` endpoint served while the run lasts.
+    pub stats_addr: Option<String>,
+    /// Full-screen live dashboard instead of scrolling output during the run.
+    pub tui: bool,
+    /// Times to run the whole test; more than one reports the spread.
+    pub repeat: u64,
+}
+
+/// The platform temp directory (`/tmp`, `%TEMP%`, ...) used when no
+/// `--io-paths` are given.
+fn default_io_path() -> String {
+    env::temp_dir().to_string_lossy().into_owned()
+}
+
+/// Creates and removes a probe file in `dir`, failing with the reason it cannot.
+fn probe_writable_dir(dir: &str) -> std::io::Result<()> {
+    let probe = PathBuf::from(dir).join(format!(".stressr_probe_{}", std::process::id()));
+    OpenOptions::new().create_new(true).write(true).open(&probe)?;
+    let _ = remove_file(&probe);
+    Ok(())
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            cpu_percent: 0,
+            cpu_total: false,
+            cpu_workload: CpuWorkload::Int,
+            cache_size_mb: 64,
+            cpu_trace: None,
+            cpu_affinity: Vec::new(),
+            numa_node: None,
+            cpu_threads: 0,
+            cpu_system_percent: None,
+            cpu_ramp_secs: 0,
+            signal_throttle: false,
+            signal_adjust: false,
+            sequential: false,
+            benchmark: false,
+            memory_percent: 0,
+            memory_use: MemoryBase::Total,
+            memory_threads: 1,
+            memory_hold_secs: None,
+            memory_free_secs: None,
+            memory_hugepages: false,
+            memory_mlock: false,
+            duration_secs: 30,
+            io_enabled: false,
+            io_paths: vec![default_io_path()],
+            io_workers: 2,
+            io_size_bytes: 100 * 1024 * 1024,
+            io_size_percent: None,
+            io_allow_overcommit: false,
+            io_duration_secs: 30,
+            warmup_secs: 0,
+            io_random: false,
+            seed: 0,
+            io_read: false,
+            io_write: false,
+            io_rwmix: None,
+            chunk_size_bytes: 64 * 1024,
+            io_read_chunk_bytes: None,
+            io_write_chunk_bytes: None,
+            io_metadata: false,
+            io_metadata_ops: false,
+            io_churn: false,
+            io_trace: None,
+            io_trace_once: false,
+            io_processes: false,
+            io_worker_child: None,
+            io_pause_below_mb: None,
+            io_verify: false,
+            io_verify_pass: false,
+            io_direct: false,
+            io_block_align: None,
+            io_sync_mode: IoSyncMode::None,
+            io_fsync_every: 1,
+            io_queue_depth: 1,
+            io_files_per_worker: 1,
+            io_pretrim: false,
+            io_keep: false,
+            io_reuse: false,
+            io_pattern: IoPattern::Random,
+            io_fill: None,
+            io_max_latency_ms: None,
+            io_target_iops: None,
+            io_rate: None,
+            io_bandwidth_mbps: None,
+            io_max_latency_soft: false,
+            raise_fd_limit: false,
+            net_enabled: false,
+            net_server: None,
+            net_connect: None,
+            net_conns: 4,
+            net_size_bytes: 64 * 1024 * 1024,
+            net_duration_secs: 30,
+            labels: Vec::new(),
+            output: OutputFormat::Text,
+            log_level: LogLevel::Info,
+            log_file: None,
+            csv: None,
+            precision: 2,
+            assume_yes: false,
+            confirm_threshold_mb: 10 * 1024,
+            limit_memory_mb: None,
+            limit_cpu_secs: None,
+            grace_secs: None,
+            preflight: false,
+            dry_run: false,
+            coordinate_bind: None,
+            peers: 2,
+            coordinator: None,
+            delay_secs: 0,
+            start_at: None,
+            compare_last: false,
+            progress_secs: None,
+            stats_addr: None,
+            tui: false,
+            repeat: 1,
+        }
+    }
+}
+
+/// What a command line asks for: a run, or only the help text or version,
+/// which the caller prints with [`print_help`] or [`print_version`].
+#[derive(Debug)]
+pub enum Invocation {
+    Run(Box<Config>),
+    Help,
+    Version,
+}
+
+impl Config {
+    fn read_chunk_bytes(&self) -> usize {
+        self.io_read_chunk_bytes.unwrap_or(self.chunk_size_bytes)
+    }
+
+    fn write_chunk_bytes(&self) -> usize {
+        self.io_write_chunk_bytes.unwrap_or(self.chunk_size_bytes)
+    }
+
+    /// The effective configuration, for `--output json`.
+    fn to_json(&self) -> Json {
+        let labels = self.labels.iter().map(|(k, v)| (k.clone(), Json::from(v))).collect();
+        Json::object(vec![
+            ("cpu_percent", self.cpu_percent.into()),
+            ("cpu_total", self.cpu_total.into()),
+            ("cpu_workload", self.cpu_workload.name().into()),
+            ("cache_size_mb", self.cache_size_mb.into()),
+            ("cpu_trace", self.cpu_trace.clone().into()),
+            ("cpu_affinity", self.cpu_affinity.clone().into()),
+            ("numa_node", self.numa_node.into()),
+            ("cpu_threads", self.cpu_threads.into()),
+            ("cpu_system_percent", self.cpu_system_percent.into()),
+            ("cpu_ramp_secs", self.cpu_ramp_secs.into()),
+            ("signal_throttle", self.signal_throttle.into()),
+            ("signal_adjust", self.signal_adjust.into()),
+            ("sequential", self.sequential.into()),
+            ("benchmark", self.benchmark.into()),
+            ("memory_percent", self.memory_percent.into()),
+            ("memory_use", self.memory_use.name().into()),
+            ("memory_threads", self.memory_threads.into()),
+            ("memory_hold_secs", self.memory_hold_secs.into()),
+            ("memory_free_secs", self.memory_free_secs.into()),
+            ("memory_hugepages", self.memory_hugepages.into()),
+            ("memory_mlock", self.memory_mlock.into()),
+            ("duration_secs", self.duration_secs.into()),
+            ("io_enabled", self.io_enabled.into()),
+            ("io_paths", self.io_paths.clone().into()),
+            ("io_workers", self.io_workers.into()),
+            ("io_size_bytes", self.io_size_bytes.into()),
+            ("io_size_percent", self.io_size_percent.into()),
+            ("io_allow_overcommit", self.io_allow_overcommit.into()),
+            ("io_duration_secs", self.io_duration_secs.into()),
+            ("warmup_secs", self.warmup_secs.into()),
+            ("io_random", self.io_random.into()),
+            ("seed", self.seed.into()),
+            ("io_read", self.io_read.into()),
+            ("io_write", self.io_write.into()),
+            ("io_rwmix", self.io_rwmix.into()),
+            ("io_read_chunk_bytes", self.read_chunk_bytes().into()),
+            ("io_write_chunk_bytes", self.write_chunk_bytes().into()),
+            ("io_metadata", self.io_metadata.into()),
+            ("io_metadata_ops", self.io_metadata_ops.into()),
+            ("io_churn", self.io_churn.into()),
+            ("io_trace", self.io_trace.clone().into()),
+            ("io_trace_once", self.io_trace_once.into()),
+            ("io_processes", self.io_processes.into()),
+            ("io_pause_below_mb", self.io_pause_below_mb.into()),
+            ("io_verify", self.io_verify.into()),
+            ("io_verify_pass", self.io_verify_pass.into()),
+            ("io_direct", self.io_direct.into()),
+            ("io_block_align", self.io_block_align.into()),
+            ("io_sync_mode", self.io_sync_mode.name().into()),
+            ("io_fsync_every", self.io_fsync_every.into()),
+            ("io_queue_depth", self.io_queue_depth.into()),
+            ("io_files_per_worker", self.io_files_per_worker.into()),
+            ("io_pretrim", self.io_pretrim.into()),
+            ("io_keep", self.io_keep.into()),
+            ("io_reuse", self.io_reuse.into()),
+            ("io_pattern", self.io_pattern.name().into()),
+            ("io_fill", self.io_fill.into()),
+            ("io_max_latency_ms", self.io_max_latency_ms.into()),
+            ("io_max_latency_soft", self.io_max_latency_soft.into()),
+            ("io_target_iops", self.io_target_iops.into()),
+            ("io_rate", self.io_rate.into()),
+            ("io_bandwidth_mbps", self.io_bandwidth_mbps.into()),
+            ("net_enabled", self.net_enabled.into()),
+            ("net_server", self.net_server.clone().into()),
+            ("net_connect", self.net_connect.clone().into()),
+            ("net_conns", self.net_conns.into()),
+            ("net_size_bytes", self.net_size_bytes.into()),
+            ("net_duration_secs", self.net_duration_secs.into()),
+            ("labels", Json::Object(labels)),
+            ("log_level", self.log_level.name().into()),
+            ("log_file", self.log_file.clone().into()),
+            ("limit_memory_mb", self.limit_memory_mb.into()),
+            ("limit_cpu_secs", self.limit_cpu_secs.into()),
+            ("grace_secs", self.grace_secs.into()),
+            ("coordinator", self.coordinator.clone().into()),
+            ("delay_secs", self.delay_secs.into()),
+            ("start_at", self.start_at.map(format_clock_time).into()),
+            ("progress_secs", self.progress_secs.into()),
+            ("stats_addr", self.stats_addr.clone().into()),
+            ("tui", self.tui.into()),
+            ("repeat", self.repeat.into()),
+        ])
+    }
+
+    /// Options that rebuild this configuration through `apply_args` on top
+    /// of the defaults: every setting that differs from them, with sizes in
+    /// exact bytes. Worker processes are launched with these. The hidden
+    /// worker flag is left to the caller, and a `log_level` of `Warn`, which
+    /// no flag sets, comes back as the default.
+    fn to_args(&self) -> Vec<String> {
+        let d = Config::default();
+        let bytes = |n: u64| format!("{}B", n);
+        let cores: Vec<String> = self.cpu_affinity.iter().map(|c| c.to_string()).collect();
+        let switches = [
+            ("--cpu-total", self.cpu_total),
+            ("--signal-throttle", self.signal_throttle),
+            ("--signal-adjust", self.signal_adjust),
+            ("--sequential", self.sequential),
+            ("--benchmark", self.benchmark),
+            ("--memory-hugepages", self.memory_hugepages),
+            ("--memory-mlock", self.memory_mlock),
+            ("--io", self.io_enabled),
+            ("--io-allow-overcommit", self.io_allow_overcommit),
+            ("--io-random", self.io_random),
+            ("--io-read", self.io_read),
+            ("--io-write", self.io_write),
+            ("--io-metadata", self.io_metadata),
+            ("--io-metadata-ops", self.io_metadata_ops),
+            ("--io-churn", self.io_churn),
+            ("--io-trace-once", self.io_trace_once),
+            ("--io-processes", self.io_processes),
+            ("--io-verify", self.io_verify),
+            ("--io-verify-pass", self.io_verify_pass),
+            ("--io-direct", self.io_direct),
+            ("--io-pretrim", self.io_pretrim),
+            ("--io-keep", self.io_keep),
+            ("--io-reuse", self.io_reuse),
+            ("--io-max-latency-soft", self.io_max_latency_soft),
+            ("--raise-fd-limit", self.raise_fd_limit),
+            ("--net", self.net_enabled),
+            ("--quiet", self.log_level == LogLevel::Error),
+            ("--verbose", self.log_level == LogLevel::Debug),
+            ("--yes", self.assume_yes),
+            ("--preflight", self.preflight),
+            ("--dry-run", self.dry_run),
+            ("--compare-last", self.compare_last),
+            ("--tui", self.tui),
+        ];
+        // `--io-fsync-every` implies fsync, so the sync mode comes after it.
+        let sync_differs =
+            self.io_sync_mode != d.io_sync_mode || self.io_fsync_every != d.io_fsync_every;
+        let values = [
+            ("--cpu-percent", differs(&self.cpu_percent, &d.cpu_percent)),
+            ("--cpu-workload", differs(&self.cpu_workload.name(), &d.cpu_workload.name())),
+            ("--cache-size", differs(&self.cache_size_mb, &d.cache_size_mb)),
+            ("--cpu-trace", self.cpu_trace.clone()),
+            ("--cpu-affinity", (!self.cpu_affinity.is_empty()).then(|| cores.join(","))),
+            ("--numa-node", self.numa_node.map(|n| n.to_string())),
+            ("--cpu-threads", differs(&self.cpu_threads, &d.cpu_threads)),
+            ("--cpu-system-percent", self.cpu_system_percent.map(|p| p.to_string())),
+            ("--cpu-ramp", differs(&self.cpu_ramp_secs, &d.cpu_ramp_secs)),
+            ("--memory-percent", differs(&self.memory_percent, &d.memory_percent)),
+            ("--memory-use", differs(&self.memory_use.name(), &d.memory_use.name())),
+            ("--memory-threads", differs(&self.memory_threads, &d.memory_threads)),
+            ("--memory-hold", self.memory_hold_secs.map(|s| s.to_string())),
+            ("--memory-free", self.memory_free_secs.map(|s| s.to_string())),
+            ("--duration", differs(&self.duration_secs, &d.duration_secs)),
+            ("--io-paths", differs(&self.io_paths.join(","), &d.io_paths.join(","))),
+            ("--io-workers", differs(&self.io_workers, &d.io_workers)),
+            ("--io-size", differs(&bytes(self.io_size_bytes), &bytes(d.io_size_bytes))),
+            ("--io-size-percent", self.io_size_percent.map(|p| p.to_string())),
+            ("--io-duration", differs(&self.io_duration_secs, &d.io_duration_secs)),
+            ("--warmup", differs(&self.warmup_secs, &d.warmup_secs)),
+            ("--seed", differs(&self.seed, &d.seed)),
+            ("--io-rwmix", self.io_rwmix.map(|p| p.to_string())),
+            (
+                "--chunk-size",
+                differs(&bytes(self.chunk_size_bytes as u64), &bytes(d.chunk_size_bytes as u64)),
+            ),
+            ("--io-read-chunk", self.io_read_chunk_bytes.map(|b| bytes(b as u64))),
+            ("--io-write-chunk", self.io_write_chunk_bytes.map(|b| bytes(b as u64))),
+            ("--io-trace", self.io_trace.clone()),
+            ("--io-pause-below", self.io_pause_below_mb.map(|mb| mb.to_string())),
+            ("--io-block-align", self.io_block_align.map(bytes)),
+            ("--io-fsync-every", differs(&self.io_fsync_every, &d.io_fsync_every)),
+            ("--io-sync-mode", sync_differs.then(|| self.io_sync_mode.name().to_string())),
+            ("--io-queue-depth", differs(&self.io_queue_depth, &d.io_queue_depth)),
+            ("--io-files-per-worker", differs(&self.io_files_per_worker, &d.io_files_per_worker)),
+            ("--io-pattern", differs(&self.io_pattern.name(), &d.io_pattern.name())),
+            ("--io-fill", self.io_fill.map(|b| format!("{:02X}", b))),
+            ("--io-max-latency", self.io_max_latency_ms.map(|ms| ms.to_string())),
+            ("--io-target-iops", self.io_target_iops.map(|n| n.to_string())),
+            ("--io-rate", self.io_rate.map(|n| n.to_string())),
+            ("--io-bandwidth", self.io_bandwidth_mbps.map(|mbps| mbps.to_string())),
+            ("--net-server", self.net_server.clone()),
+            ("--net-connect", self.net_connect.clone()),
+            ("--net-conns", differs(&self.net_conns, &d.net_conns)),
+            ("--net-size", differs(&bytes(self.net_size_bytes), &bytes(d.net_size_bytes))),
+            ("--net-duration", differs(&self.net_duration_secs, &d.net_duration_secs)),
+            ("--output", (self.output == OutputFormat::Json).then(|| "json".to_string())),
+            ("--log-file", self.log_file.clone()),
+            ("--csv", self.csv.clone()),
+            ("--precision", differs(&self.precision, &d.precision)),
+            ("--confirm-threshold", differs(&self.confirm_threshold_mb, &d.confirm_threshold_mb)),
+            ("--limit-memory", self.limit_memory_mb.map(|mb| mb.to_string())),
+            ("--limit-cpu-time", self.limit_cpu_secs.map(|s| s.to_string())),
+            ("--grace", self.grace_secs.map(|s| s.to_string())),
+            ("--coordinate", self.coordinate_bind.clone()),
+            ("--peers", differs(&self.peers, &d.peers)),
+            ("--coordinator", self.coordinator.clone()),
+            ("--delay", differs(&self.delay_secs, &d.delay_secs)),
+            ("--start-at", self.start_at.map(format_clock_time)),
+            ("--progress", self.progress_secs.map(|s| s.to_string())),
+            ("--stats-addr", self.stats_addr.clone()),
+            ("--repeat", differs(&self.repeat, &d.repeat)),
+        ];
+
+        let mut args: Vec<String> =
+            switches.iter().filter(|(_, on)| *on).map(|(flag, _)| flag.to_string()).collect();
+        for (flag, value) in values {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value]);
+            }
+        }
+        for (key, value) in &self.labels {
+            args.extend(["--label".to_string(), format!("{}={}", key, value)]);
+        }
+        args
+    }
+
+    /// Parses a full command line, program name first (as from `env::args`),
+    /// into a run or a request for the help text or version.
+    pub fn from_args(args: &[String]) -> Result<Invocation, StressError> {
+        let args = args.get(1..).unwrap_or_default();
+
+        if args.iter().any(|a| a == "--version" || a == "-V") {
+            return Ok(Invocation::Version);
+        }
+
+        // Without arguments, show help unless STRESSR_* variables configure the run.
+        let env_args = env_to_args(env::vars());
+        let bare = args.is_empty() && env_args.is_empty();
+        if bare || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            return Ok(Invocation::Help);
+        }
+
+        let mut cfg = Config::default();
+
+        for flag in cfg.apply_args(&env_args) {
+            let name = flag.trim_start_matches('-').replace('-', "_").to_uppercase();
+            warn!("Ignoring unknown environment variable {}{}", ENV_PREFIX, name);
+        }
+
+        if let Some(source) = config_source(args) {
+            let file_args = load_config(source).map_err(|e| {
+                StressError::InvalidConfig(format!("cannot load {}: {}", source, e))
+            })?;
+            for flag in cfg.apply_args(&file_args) {
+                let key = flag.trim_start_matches('-').replace('-', "_");
+                warn!("Ignoring unknown config key {:?} in {}", key, source);
+            }
+        }
+
+        let unknown = cfg.apply_args(args);
+        log::set_level(cfg.log_level);
+        for arg in unknown {
+            warn!("Ignoring unknown option {:?}", arg);
+        }
+        cfg.validate()?;
+        Ok(Invocation::Run(Box::new(cfg)))
+    }
+
+    /// Bytes that I/O offsets and chunk sizes are multiples of: the
+    /// `--io-block-align`, at least `DIRECT_IO_ALIGN` under `--io-direct`.
+    fn io_align(&self) -> usize {
+        let direct = if self.io_direct { DIRECT_IO_ALIGN } else { 1 };
+        (self.io_block_align.unwrap_or(1) as usize).max(direct)
+    }
+
+    /// Workers only read the `--io-reuse` files already in place, so those
+    /// are opened read-only and may sit on read-only storage.
+    fn io_read_only(&self) -> bool {
+        self.io_reuse && !self.io_write && self.io_trace.is_none()
+    }
+
+    /// Seconds the memory stressor runs: the hold plus any gradual release.
+    pub fn memory_duration_secs(&self) -> u64 {
+        self.memory_hold_secs.unwrap_or(self.duration_secs) + self.memory_free_secs.unwrap_or(0)
+    }
+
+    /// Rejects settings that cannot be run as given.
+    pub fn validate(&self) -> Result<(), StressError> {
+        self.check_settings().map_err(StressError::InvalidConfig)
+    }
+
+    fn check_settings(&self) -> Result<(), String> {
+        if self.cpu_percent > 100 {
+            return Err(format!("--cpu-percent must be 0-100, got {}", self.cpu_percent));
+        }
+        if self.signal_adjust && self.cpu_percent == 0 && self.cpu_system_percent.is_none() {
+            return Err("--signal-adjust needs --cpu-percent or --cpu-system-percent".to_string());
+        }
+        if self.cpu_ramp_secs > self.duration_secs {
+            return Err(format!(
+                "--cpu-ramp ({}s) must not be longer than --duration ({}s)",
+                self.cpu_ramp_secs, self.duration_secs
+            ));
+        }
+        if self.cache_size_mb == 0 {
+            return Err("--cache-size must be at least 1 MB".to_string());
+        }
+        if self.memory_percent > 100 {
+            return Err(format!("--memory-percent must be 0-100, got {}", self.memory_percent));
+        }
+        if let Some(mix) = self.io_rwmix.filter(|&mix| mix > 100) {
+            return Err(format!("--io-rwmix must be 0-100, got {}", mix));
+        }
+        if self.io_direct {
+            if self.io_trace.is_some() {
+                return Err("--io-direct cannot replay --io-trace, whose offsets and sizes \
+                            are not block-aligned"
+                    .to_string());
+            }
+            if !self.io_size_bytes.is_multiple_of(DIRECT_IO_ALIGN as u64) {
+                return Err(format!(
+                    "--io-direct needs --io-size to be a multiple of {} bytes, got {}",
+                    DIRECT_IO_ALIGN, self.io_size_bytes
+                ));
+            }
+        }
+        if let Some(align) = self.io_block_align {
+            if align == 0 {
+                return Err("--io-block-align must be at least 1 byte".to_string());
+            }
+            if self.io_direct && !align.is_multiple_of(DIRECT_IO_ALIGN as u64) {
+                return Err(format!(
+                    "--io-block-align must be a multiple of {} bytes with --io-direct, got {}",
+                    DIRECT_IO_ALIGN, align
+                ));
+            }
+            if self.io_trace.is_some() {
+                return Err("--io-block-align cannot move the offsets of --io-trace".to_string());
+            }
+        }
+        if !(1..=IO_QUEUE_DEPTH_MAX).contains(&self.io_queue_depth) {
+            return Err(format!(
+                "--io-queue-depth must be 1-{}, got {}",
+                IO_QUEUE_DEPTH_MAX, self.io_queue_depth
+            ));
+        }
+        if self.io_queue_depth > 1 {
+            if self.io_verify {
+                return Err("--io-queue-depth cannot check reads as they complete; \
+                            use --io-verify-pass instead of --io-verify"
+                    .to_string());
+            }
+            if self.io_sync_mode.syncs_after_writes() {
+                return Err(format!(
+                    "--io-queue-depth cannot be combined with --io-sync-mode {}",
+                    self.io_sync_mode.name()
+                ));
+            }
+            if self.io_trace.is_some() {
+                return Err("--io-queue-depth does not apply to --io-trace replay".to_string());
+            }
+        }
+        if self.io_sync_mode == IoSyncMode::Osync && self.io_fsync_every > 1 {
+            return Err("--io-fsync-every does not apply to --io-sync-mode osync, \
+                        where every write is synchronous"
+                .to_string());
+        }
+        if self.io_churn {
+            if self.io_metadata_ops {
+                return Err("--io-churn cannot be combined with --io-metadata-ops".to_string());
+            }
+            if self.io_trace.is_some() {
+                return Err("--io-churn cannot be combined with --io-trace".to_string());
+            }
+        }
+        if self.io_files_per_worker == 0 {
+            return Err("--io-files-per-worker must be at least 1".to_string());
+        }
+        if self.io_files_per_worker > 1 {
+            let clash = if self.io_queue_depth > 1 {
+                Some("--io-queue-depth")
+            } else if self.io_trace.is_some() {
+                Some("--io-trace")
+            } else if self.io_churn {
+                Some("--io-churn")
+            } else if self.io_metadata {
+                Some("--io-metadata")
+            } else {
+                None
+            };
+            if let Some(flag) = clash {
+                return Err(format!("--io-files-per-worker cannot be combined with {}", flag));
+            }
+        }
+        if self.io_reuse && self.io_pretrim {
+            return Err("--io-pretrim would discard the data of the --io-reuse files".to_string());
+        }
+        if self.io_pretrim && (self.io_verify || self.io_verify_pass) {
+            return Err("--io-pretrim would discard the pattern written for --io-verify \
+                        and --io-verify-pass"
+                .to_string());
+        }
+        if self.net_enabled {
+            match (&self.net_server, &self.net_connect) {
+                (None, None) => {
+                    return Err("--net needs --net-server <BIND> or --net-connect <HOST:PORT>"
+                        .to_string());
+                }
+                (Some(_), Some(_)) => {
+                    return Err("--net-server and --net-connect are separate roles; \
+                                run one of each"
+                        .to_string());
+                }
+                _ => {}
+            }
+            if self.net_connect.is_some() && (self.net_conns == 0 || self.net_size_bytes == 0) {
+                return Err("--net-conns and --net-size must be at least 1".to_string());
+            }
+        }
+        if self.repeat == 0 {
+            return Err("--repeat must be at least 1".to_string());
+        }
+        if self.io_enabled && self.warmup_secs > 0 && self.warmup_secs >= self.io_duration_secs {
+            return Err(format!(
+                "--warmup ({}s) must be shorter than --io-duration ({}s)",
+                self.warmup_secs, self.io_duration_secs
+            ));
+        }
+        Ok(())
+    }
+
+    /// Applies command-line style options on top of the current values and
+    /// returns the option names it did not recognize.
+    fn apply_args(&mut self, args: &[String]) -> Vec<String> {
+        let mut unknown = Vec::new();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--cpu-percent" => {
+                    i += 1;
+                    self.cpu_percent = number_arg("--cpu-percent", args.get(i), 100);
+                }
+                "--cpu-total" => self.cpu_total = true,
+                "--sequential" => self.sequential = true,
+                "--benchmark" => self.benchmark = true,
+                "--cpu-ramp" => {
+                    i += 1;
+                    self.cpu_ramp_secs = duration_arg("--cpu-ramp", args.get(i), 0);
+                }
+                "--cpu-system-percent" => {
+                    i += 1;
+                    self.cpu_system_percent =
+                        args.get(i).and_then(|v| v.parse().ok()).map(|p: u64| p.min(100));
+                }
+                "--signal-throttle" => self.signal_throttle = true,
+                "--signal-adjust" => self.signal_adjust = true,
+                "--cpu-workload" | "--cpu-method" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| CpuWorkload::parse(v)) {
+                        Some(workload) => self.cpu_workload = workload,
+                        None => warn!(
+                            "Ignoring unknown --cpu-workload {:?} \
+                             (expected int, float, sqrt, matrix, avx512, cache or primes)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--cache-size" => {
+                    i += 1;
+                    self.cache_size_mb = number_arg("--cache-size", args.get(i), 64);
+                }
+                "--cpu-trace" => {
+                    i += 1;
+                    self.cpu_trace = args.get(i).cloned();
+                }
+                "--cpu-threads" => {
+                    i += 1;
+                    self.cpu_threads = number_arg("--cpu-threads", args.get(i), 0);
+                }
+                "--cpu-affinity" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| parse_core_list(v)) {
+                        Some(cores) => self.cpu_affinity = cores,
+                        None => warn!(
+                            "Ignoring invalid --cpu-affinity {:?} (expected core IDs like 0,2,4)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--numa-node" => {
+                    i += 1;
+                    self.numa_node = Some(number_arg("--numa-node", args.get(i), 0));
+                }
+                "--memory-percent" => {
+                    i += 1;
+                    self.memory_percent = number_arg("--memory-percent", args.get(i), 100);
+                }
+                "--memory-threads" => {
+                    i += 1;
+                    self.memory_threads = number_arg("--memory-threads", args.get(i), 1);
+                }
+                "--memory-hold" => {
+                    i += 1;
+                    self.memory_hold_secs = Some(duration_arg("--memory-hold", args.get(i), 30));
+                }
+                "--memory-free" => {
+                    i += 1;
+                    self.memory_free_secs = Some(duration_arg("--memory-free", args.get(i), 30));
+                }
+                "--memory-hugepages" => self.memory_hugepages = true,
+                "--memory-mlock" => self.memory_mlock = true,
+                "--memory-use" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| MemoryBase::parse(v)) {
+                        Some(base) => self.memory_use = base,
+                        None => warn!(
+                            "Ignoring unknown --memory-use {:?} (expected total or available)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--duration" => {
+                    i += 1;
+                    self.duration_secs = duration_arg("--duration", args.get(i), 30);
+                }
+                "--io" => self.io_enabled = true,
+                "--io-paths" => {
+                    i += 1;
+                    self.io_paths = args
+                        .get(i)
+                        .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+                        .unwrap_or(vec![default_io_path()]);
+                }
+                "--io-workers" => {
+                    i += 1;
+                    self.io_workers = number_arg("--io-workers", args.get(i), 2);
+                }
+                "--io-size" => {
+                    i += 1;
+                    self.io_size_bytes = size_arg("--io-size", args.get(i), 1024 * 1024, 100);
+                }
+                "--io-size-percent" => {
+                    i += 1;
+                    self.io_size_percent = args.get(i).and_then(|v| v.parse().ok());
+                }
+                "--io-allow-overcommit" => self.io_allow_overcommit = true,
+                "--io-duration" => {
+                    i += 1;
+                    self.io_duration_secs = duration_arg("--io-duration", args.get(i), 30);
+                }
+                "--warmup" => {
+                    i += 1;
+                    self.warmup_secs = duration_arg("--warmup", args.get(i), 0);
+                }
+                "--io-random" => self.io_random = true,
+                "--seed" => {
+                    i += 1;
+                    self.seed = number_arg("--seed", args.get(i), 0);
+                }
+                "--io-metadata" => self.io_metadata = true,
+                "--io-metadata-ops" => {
+                    self.io_metadata = true;
+                    self.io_metadata_ops = true;
+                }
+                "--io-churn" => {
+                    self.io_metadata = true;
+                    self.io_churn = true;
+                }
+                "--io-trace" => {
+                    i += 1;
+                    self.io_trace = args.get(i).cloned();
+                }
+                "--io-trace-once" => self.io_trace_once = true,
+                "--io-processes" => self.io_processes = true,
+                "--io-verify" => self.io_verify = true,
+                "--io-verify-pass" => self.io_verify_pass = true,
+                "--io-direct" => self.io_direct = true,
+                "--io-block-align" => {
+                    i += 1;
+                    self.io_block_align = Some(size_arg("--io-block-align", args.get(i), 1, 4096));
+                }
+                "--io-fsync" => self.io_sync_mode = IoSyncMode::Fsync,
+                "--io-fsync-every" => {
+                    i += 1;
+                    if self.io_sync_mode == IoSyncMode::None {
+                        self.io_sync_mode = IoSyncMode::Fsync;
+                    }
+                    self.io_fsync_every =
+                        args.get(i).and_then(|v| v.parse().ok()).filter(|&n| n > 0).unwrap_or(1);
+                }
+                "--io-queue-depth" => {
+                    i += 1;
+                    self.io_queue_depth = number_arg("--io-queue-depth", args.get(i), 1);
+                }
+                "--io-files-per-worker" => {
+                    i += 1;
+                    self.io_files_per_worker = number_arg("--io-files-per-worker", args.get(i), 1);
+                }
+                "--io-pretrim" => self.io_pretrim = true,
+                "--io-keep" => self.io_keep = true,
+                "--io-reuse" => self.io_reuse = true,
+                "--io-max-latency" => {
+                    i += 1;
+                    self.io_max_latency_ms = args.get(i).and_then(|v| v.parse().ok());
+                }
+                "--io-max-latency-soft" => self.io_max_latency_soft = true,
+                "--io-target-iops" => {
+                    i += 1;
+                    self.io_target_iops =
+                        args.get(i).and_then(|v| v.parse().ok()).filter(|&n| n > 0);
+                }
+                "--io-rate" => {
+                    i += 1;
+                    self.io_rate = args.get(i).and_then(|v| v.parse().ok()).filter(|&n| n > 0);
+                }
+                "--io-bandwidth" => {
+                    i += 1;
+                    self.io_bandwidth_mbps =
+                        args.get(i).and_then(|v| v.parse().ok()).filter(|&n: &f64| n > 0.0);
+                }
+                "--io-sync-mode" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| IoSyncMode::parse(v)) {
+                        Some(mode) => self.io_sync_mode = mode,
+                        None => warn!(
+                            "Ignoring unknown --io-sync-mode {:?} \
+                             (expected none, fsync, fdatasync or osync)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--io-pattern" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| IoPattern::parse(v)) {
+                        Some(pattern) => self.io_pattern = pattern,
+                        None => warn!(
+                            "Ignoring unknown --io-pattern {:?} \
+                             (expected random, zero or incrementing)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--io-fill" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| parse_fill_byte(v)) {
+                        Some(byte) => self.io_fill = Some(byte),
+                        None => warn!(
+                            "Ignoring invalid --io-fill {:?} (expected a hex byte such as FF)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--raise-fd-limit" => self.raise_fd_limit = true,
+                "--net" => self.net_enabled = true,
+                "--net-server" => {
+                    i += 1;
+                    self.net_enabled = true;
+                    self.net_server = args.get(i).cloned();
+                }
+                "--net-connect" => {
+                    i += 1;
+                    self.net_enabled = true;
+                    self.net_connect = args.get(i).cloned();
+                }
+                "--net-conns" => {
+                    i += 1;
+                    self.net_conns = number_arg("--net-conns", args.get(i), 4);
+                }
+                "--net-size" => {
+                    i += 1;
+                    self.net_size_bytes = size_arg("--net-size", args.get(i), 1024 * 1024, 64);
+                }
+                "--net-duration" => {
+                    i += 1;
+                    self.net_duration_secs = duration_arg("--net-duration", args.get(i), 30);
+                }
+                "--limit-memory" => {
+                    i += 1;
+                    self.limit_memory_mb = args.get(i).and_then(|v| v.parse().ok());
+                }
+                "--preflight" => self.preflight = true,
+                "--dry-run" => self.dry_run = true,
+                "--limit-cpu-time" => {
+                    i += 1;
+                    self.limit_cpu_secs = args.get(i).and_then(|v| v.parse().ok());
+                }
+                "--grace" => {
+                    i += 1;
+                    self.grace_secs = Some(duration_arg("--grace", args.get(i), 30));
+                }
+                "--io-pause-below" => {
+                    i += 1;
+                    self.io_pause_below_mb = args.get(i).and_then(|v| v.parse().ok());
+                }
+                // Hidden: set by the parent when it spawns a worker process.
+                WORKER_CHILD_FLAG => {
+                    let id = args.get(i + 1).and_then(|v| v.parse().ok());
+                    let path = args.get(i + 2).cloned();
+                    i += 2;
+                    if let (Some(id), Some(path)) = (id, path) {
+                        self.io_worker_child = Some((id, path));
+                    }
+                }
+                "--io-read" => self.io_read = true,
+                "--io-write" => self.io_write = true,
+                "--io-rwmix" => {
+                    i += 1;
+                    self.io_rwmix = Some(number_arg("--io-rwmix", args.get(i), 50));
+                    self.io_read = true;
+                    self.io_write = true;
+                }
+                "--chunk-size" => {
+                    i += 1;
+                    self.chunk_size_bytes =
+                        size_arg("--chunk-size", args.get(i), 1024, 64) as usize;
+                }
+                "--io-read-chunk" => {
+                    i += 1;
+                    self.io_read_chunk_bytes =
+                        args.get(i).and_then(|v| parse_size(v, 1024)).map(|b| b as usize);
+                }
+                "--io-write-chunk" => {
+                    i += 1;
+                    self.io_write_chunk_bytes =
+                        args.get(i).and_then(|v| parse_size(v, 1024)).map(|b| b as usize);
+                }
+                "--precision" => {
+                    i += 1;
+                    self.precision = number_arg("--precision", args.get(i), 2);
+                }
+                // Loaded up front by from_args; only skip its value here.
+                "--config" => i += 1,
+                "--compare-last" => self.compare_last = true,
+                "--repeat" => {
+                    i += 1;
+                    self.repeat = number_arg("--repeat", args.get(i), 1);
+                }
+                "--progress" => {
+                    i += 1;
+                    self.progress_secs =
+                        Some(duration_arg("--progress", args.get(i), 10)).filter(|&s| s > 0);
+                }
+                "--stats-addr" => {
+                    i += 1;
+                    self.stats_addr = args.get(i).cloned();
+                }
+                "--tui" => self.tui = true,
+                "--output" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| OutputFormat::parse(v)) {
+                        Some(output) => self.output = output,
+                        None => warn!(
+                            "Ignoring unknown --output {:?} (expected text or json)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--csv" => {
+                    i += 1;
+                    self.csv = args.get(i).cloned();
+                }
+                "--log-file" => {
+                    i += 1;
+                    self.log_file = args.get(i).cloned();
+                }
+                "--quiet" | "-q" => self.log_level = LogLevel::Error,
+                "--verbose" | "-v" | "-vv" => self.log_level = LogLevel::Debug,
+                "--yes" | "--force" => self.assume_yes = true,
+                "--coordinate" => {
+                    i += 1;
+                    self.coordinate_bind = args.get(i).cloned();
+                }
+                "--peers" => {
+                    i += 1;
+                    self.peers = number_arg("--peers", args.get(i), 2);
+                }
+                "--coordinator" => {
+                    i += 1;
+                    self.coordinator = args.get(i).cloned();
+                }
+                "--delay" => {
+                    i += 1;
+                    self.delay_secs = duration_arg("--delay", args.get(i), 0);
+                }
+                "--start-at" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| parse_clock_time(v)) {
+                        Some(time) => self.start_at = Some(time),
+                        None => warn!(
+                            "Ignoring invalid --start-at {:?} (expected HH:MM or HH:MM:SS)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                "--confirm-threshold" => {
+                    i += 1;
+                    self.confirm_threshold_mb =
+                        number_arg("--confirm-threshold", args.get(i), 10 * 1024);
+                }
+                "--label" => {
+                    i += 1;
+                    match args.get(i).and_then(|v| parse_label(v)) {
+                        Some(label) => self.labels.push(label),
+                        None => warn!(
+                            "Ignoring malformed --label {:?} (expected KEY=VALUE)",
+                            args.get(i).map(String::as_str).unwrap_or("")
+                        ),
+                    }
+                }
+                other => {
+                    unknown.push(other.to_string());
+                    // Skip what looks like the unknown option's value.
+                    if other.starts_with("--")
+                        && args.get(i + 1).is_some_and(|next| !next.starts_with("--"))
+                    {
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+        }
+        unknown
+    }
+}
+
+/// The value of the last `--config` flag, if any.
+fn config_source(args: &[String]) -> Option<&str> {
+    args.iter()
+        .rposition(|a| a == "--config")
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Reads a config source (`-` for stdin, an `http://` URL, or a file path) and
+/// translates it into command-line style arguments.
+fn load_config(source: &str) -> Result<Vec<String>, String> {
+    let text = if source == "-" {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("reading stdin: {}", e))?;
+        text
+    } else if source.starts_with("http://") {
+        http_get(source)?
+    } else if source.contains("://") {
+        return Err("only plain http:// URLs are supported".into());
+    } else {
+        std::fs::read_to_string(source).map_err(|e| e.to_string())?
+    };
+
+    config_to_args(&text)
+}
+
+/// Appends the flag for one `key = value` option: `io_workers`, `4` becomes
+/// `--io-workers 4`, `io_read`, `true` becomes `--io-read`, and `false`
+/// drops the flag.
+fn push_option(args: &mut Vec<String>, key: &str, value: &str) {
+    let flag = format!("--{}", key.replace('_', "-"));
+    match value {
+        "true" => args.push(flag),
+        "false" => {}
+        _ => {
+            args.push(flag);
+            args.push(value.to_string());
+        }
+    }
+}
+
+/// Prefix of environment variables read as options: `STRESSR_IO_WORKERS=4`
+/// is `--io-workers 4`.
+const ENV_PREFIX: &str = "STRESSR_";
+
+/// Translates `STRESSR_*` variables into flags, in name order so repeated
+/// runs apply them the same way.
+fn env_to_args(vars: impl IntoIterator<Item = (String, String)>) -> Vec<String> {
+    let mut vars: Vec<(String, String)> =
+        vars.into_iter().filter(|(name, _)| name.starts_with(ENV_PREFIX)).collect();
+    vars.sort();
+    let mut args = Vec::new();
+    for (name, value) in vars {
+        let key = name[ENV_PREFIX.len()..].to_lowercase();
+        push_option(&mut args, &key, value.trim());
+    }
+    args
+}
+
+/// Translates `key = value` lines into flags with `push_option`. Blank lines
+/// and `#` comments are ignored; keys may repeat.
+fn config_to_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for (n, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `key = value`, got {:?}", n + 1, raw))?;
+        let key = key.trim();
+        let value = value.trim().trim_matches('"');
+        if key.is_empty() {
+            return Err(format!("line {}: missing key", n + 1));
+        }
+
+        push_option(&mut args, key, value);
+    }
+
+    Ok(args)
+}
+
+/// Minimal HTTP/1.0 GET returning the response body of a 200 reply.
+fn http_get(url: &str) -> Result<String, String> {
+    use std::net::{TcpStream, ToSocketAddrs};
+
+    let rest = url.strip_prefix("http://").ok_or("not an http:// URL")?;
+    let (host_port, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((h, p)) => (h, p.parse::<u16>().map_err(|_| format!("bad port in {}", url))?),
+        None => (host_port, 80),
+    };
+
+    let timeout = Duration::from_secs(10);
+    let addr = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("resolving {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("{} did not resolve", host))?;
+    let mut stream =
+        TcpStream::connect_timeout(&addr, timeout).map_err(|e| format!("connecting: {}", e))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: stressr\r\nConnection: close\r\n\r\n",
+        path, host_port
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("sending request: {}", e))?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|e| format!("reading response: {}", e))?;
+    let response = String::from_utf8(response).map_err(|_| "response is not UTF-8")?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("malformed HTTP response")?;
+    let status = head.lines().next().unwrap_or("");
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(format!("server replied {:?}", status));
+    }
+    Ok(body.to_string())
+}
+
+/// The value of a numeric flag, or `default` with a note on stderr if the
+/// value is missing or malformed.
+fn number_arg<T: std::str::FromStr + std::fmt::Display>(
+    flag: &str,
+    value: Option<&String>,
+    default: T,
+) -> T {
+    match value.map(|v| (v, v.parse())) {
+        Some((_, Ok(n))) => n,
+        Some((v, Err(_))) => {
+            warn!("Ignoring invalid {} {:?}, using {}", flag, v, default);
+            default
+        }
+        None => {
+            warn!("Note: {} has no value, using {}", flag, default);
+            default
+        }
+    }
+}
+
+/// Parses a duration such as `30s`, `5m` or `2h`; a bare number is seconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (digits, unit_secs) = match value.as_bytes().last()? {
+        b's' => (&value[..value.len() - 1], 1),
+        b'm' => (&value[..value.len() - 1], 60),
+        b'h' => (&value[..value.len() - 1], 60 * 60),
+        _ => (value, 1),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let secs = digits.parse::<u64>().ok()?.checked_mul(unit_secs)?;
+    Some(Duration::from_secs(secs))
+}
+
+/// Seconds for a duration flag, or `default` with a warning if it is malformed.
+fn duration_arg(flag: &str, value: Option<&String>, default: u64) -> u64 {
+    match value.and_then(|v| parse_duration(v)) {
+        Some(duration) => duration.as_secs(),
+        None => {
+            warn!(
+                "Ignoring invalid {} {:?} (expected e.g. 30, 30s, 5m or 2h), using {}s",
+                flag,
+                value.map(String::as_str).unwrap_or(""),
+                default
+            );
+            default
+        }
+    }
+}
+
+/// Parses a size in bytes such as `4096B`, `512K`, `100M` or `4G`
+/// (case-insensitive). A bare number is a count of `bare_unit` bytes.
+fn parse_size(value: &str, bare_unit: u64) -> Option<u64> {
+    let value = value.trim();
+    let (digits, unit) = match value.as_bytes().last()?.to_ascii_uppercase() {
+        b'B' => (&value[..value.len() - 1], 1),
+        b'K' => (&value[..value.len() - 1], 1024),
+        b'M' => (&value[..value.len() - 1], 1024 * 1024),
+        b'G' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, bare_unit),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// Bytes for a size flag, or `default` bare units with a warning if malformed.
+fn size_arg(flag: &str, value: Option<&String>, bare_unit: u64, default: u64) -> u64 {
+    match value.and_then(|v| parse_size(v, bare_unit)) {
+        Some(bytes) => bytes,
+        None => {
+            warn!(
+                "Ignoring invalid {} {:?} (expected e.g. 512K, 100M or 4G), using {}",
+                flag,
+                value.map(String::as_str).unwrap_or(""),
+                default
+            );
+            default * bare_unit
+        }
+    }
+}
+
+/// `value` as an option value, or None where it is the default.
+fn differs<T: PartialEq + ToString + ?Sized>(value: &T, default: &T) -> Option<String> {
+    (value != default).then(|| value.to_string())
+}
+
+fn parse_label(s: &str) -> Option<(String, String)> {
+    let (key, value) = s.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    Some((key.to_string(), value.to_string()))
+}
+
+fn format_labels(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `labels` as a Prometheus label set, `{key="value",...}`, or empty when
+/// there are none. Characters a label name cannot hold become `_`, and
+/// values have `\`, `"` and line breaks escaped.
+fn prometheus_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| {
+            let mut name: String =
+                k.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+            if name.starts_with(|c: char| c.is_ascii_digit()) {
+                name.insert(0, '_');
+            }
+            let value = v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Formats a throughput given in MB/s, dropping to KB/s or B/s for values that
+/// would otherwise print as zero at the requested precision.
+fn format_rate(mbps: f64, precision: usize) -> String {
+    if mbps >= 1.0 || mbps == 0.0 {
+        format!("{:.*} MB/s", precision, mbps)
+    } else if mbps * 1024.0 >= 1.0 {
+        format!("{:.*} KB/s", precision, mbps * 1024.0)
+    } else {
+        format!("{:.*} B/s", precision, mbps * 1024.0 * 1024.0)
+    }
+}
+
+fn avx512_supported() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        std::arch::is_x86_feature_detected!("avx512f")
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        false
+    }
+}
+
+/// A short burst of independent 512-bit FMA chains, wide enough to keep the
+/// vector units busy and trigger the AVX-512 frequency license.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+fn avx512_burst() {
+    use std::arch::x86_64::*;
+
+    let b = _mm512_set1_pd(0.999_999_9);
+    let c = _mm512_set1_pd(1e-9);
+    let mut acc = [_mm512_set1_pd(1.0); 8];
+    for _ in 0..64 {
+        for a in acc.iter_mut() {
+            *a = _mm512_fmadd_pd(*a, b, c);
+        }
+    }
+    std::hint::black_box(acc);
+}
+
+/// Side of the square matrices multiplied by the `matrix` workload.
+const MATRIX_N: usize = 4;
+
+fn float_burst() {
+    let mut x = std::hint::black_box(1.0f64);
+    for _ in 0..64 {
+        x = x * 0.999_999_9 + 1e-9;
+    }
+    std::hint::black_box(x);
+}
+
+fn sqrt_burst() {
+    let mut x = std::hint::black_box(2.0f64);
+    for _ in 0..64 {
+        x = (x + 1.0).sqrt();
+    }
+    std::hint::black_box(x);
+}
+
+fn matrix_burst() {
+    let a = std::hint::black_box([[1.000_001f64; MATRIX_N]; MATRIX_N]);
+    let b = std::hint::black_box([[0.999_999f64; MATRIX_N]; MATRIX_N]);
+    let mut c = [[0.0f64; MATRIX_N]; MATRIX_N];
+    for (i, row) in c.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..MATRIX_N).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    std::hint::black_box(c);
+}
+
+/// Odd numbers covered by one `primes` sieve pass, a byte each: 128 KiB,
+/// which stays in L2 on current cores.
+const PRIME_SIEVE_ODDS: usize = 128 * 1024;
+
+/// One sieve of Eratosthenes pass over the integers below
+/// `2 * PRIME_SIEVE_ODDS`, returning how many primes there are. Unlike the
+/// other kernels it mixes data-dependent branches with strided stores.
+fn primes_burst() -> usize {
+    // Entry i stands for 2i + 1; 2 is counted up front.
+    let mut composite = [false; PRIME_SIEVE_ODDS];
+    std::hint::black_box(&mut composite);
+    let mut count = 1;
+    for i in 1..PRIME_SIEVE_ODDS {
+        if composite[i] {
+            continue;
+        }
+        count += 1;
+        let p = 2 * i + 1;
+        for j in (p * p / 2..PRIME_SIEVE_ODDS).step_by(p) {
+            composite[j] = true;
+        }
+    }
+    std::hint::black_box(count)
+}
+
+/// Bytes per `cache` workload slot: one cache line on current x86 and ARM.
+const CACHE_LINE: usize = 64;
+
+/// Dependent loads per `cache` kernel iteration.
+const CACHE_CHASE_STEPS: u64 = 64;
+
+/// The `cache` workload's buffer: one slot per cache line, each holding the
+/// index of the next slot on a single random cycle through all of them, so
+/// every load depends on the previous one and the prefetcher cannot guess it.
+struct CacheChain {
+    slots: Vec<usize>,
+    pos: usize,
+}
+
+impl CacheChain {
+    /// Only the first word of each cache line is used; the rest is padding.
+    const STRIDE: usize = CACHE_LINE / std::mem::size_of::<usize>();
+
+    /// A chain over `bytes` (at least two lines), shuffled with Sattolo's
+    /// algorithm from the `stream` generator of `seed`.
+    fn new(bytes: u64, seed: u64, stream: u64) -> Self {
+        let lines = (bytes as usize / CACHE_LINE).max(2);
+        let mut order: Vec<usize> = (0..lines).collect();
+        let mut rng = Rng::for_stream(seed, stream);
+        for i in (1..lines).rev() {
+            let j = rng.below(i as u64) as usize;
+            order.swap(i, j);
+        }
+        let mut slots = vec![0usize; lines * Self::STRIDE];
+        for i in 0..lines {
+            slots[order[i] * Self::STRIDE] = order[(i + 1) % lines] * Self::STRIDE;
+        }
+        CacheChain { slots, pos: 0 }
+    }
+
+    /// The chain CPU thread `thread` needs for `workload`: `size_mb` MiB
+    /// for the `cache` workload, empty otherwise.
+    fn for_thread(workload: CpuWorkload, size_mb: u64, seed: u64, thread: usize) -> Self {
+        if workload == CpuWorkload::Cache {
+            CacheChain::new(size_mb * 1024 * 1024, seed, thread as u64)
+        } else {
+            CacheChain::none()
+        }
+    }
+
+    /// An empty chain for workloads that do not chase one.
+    fn none() -> Self {
+        CacheChain { slots: Vec::new(), pos: 0 }
+    }
+
+    fn chase(&mut self) {
+        let mut pos = self.pos;
+        for _ in 0..CACHE_CHASE_STEPS {
+            pos = std::hint::black_box(self.slots[pos]);
+        }
+        self.pos = pos;
+    }
+}
+
+fn run_cpu_kernel(workload: CpuWorkload, chain: &mut CacheChain) {
+    match workload {
+        CpuWorkload::Int => {
+            std::hint::black_box(1 + 1);
+        }
+        CpuWorkload::Float => float_burst(),
+        CpuWorkload::Sqrt => sqrt_burst(),
+        CpuWorkload::Matrix => matrix_burst(),
+        CpuWorkload::Avx512 => {
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: stress_cpu only selects Avx512 after avx512_supported().
+            unsafe {
+                avx512_burst()
+            }
+        }
+        CpuWorkload::Cache => chain.chase(),
+        CpuWorkload::Primes => {
+            primes_burst();
+        }
+    }
+}
+
+/// Root of the RAPL powercap tree; each top-level `intel-rapl:N` zone is a
+/// CPU package (also exposed under this name on AMD).
+const RAPL_ROOT: &str = "/sys/class/powercap";
+
+/// (energy_uj, max_energy_range_uj) for every RAPL package zone, or None when
+/// RAPL is not exposed or not readable.
+fn read_rapl_energy() -> Option<Vec<(u64, u64)>> {
+    let mut zones: Vec<PathBuf> = std::fs::read_dir(RAPL_ROOT)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("intel-rapl:") && n.matches(':').count() == 1)
+        })
+        .collect();
+    zones.sort();
+
+    let read = |path: PathBuf| std::fs::read_to_string(path).ok()?.trim().parse::<u64>().ok();
+    let energy: Option<Vec<(u64, u64)>> = zones
+        .into_iter()
+        .map(|zone| Some((read(zone.join("energy_uj"))?, read(zone.join("max_energy_range_uj"))?)))
+        .collect();
+    energy.filter(|zones| !zones.is_empty())
+}
+
+/// Microjoules consumed between two samples of one counter, allowing for one
+/// wraparound at `max_range`.
+fn energy_delta_uj(before: u64, after: u64, max_range: u64) -> u64 {
+    if after >= before {
+        after - before
+    } else {
+        max_range - before + after
+    }
+}
+
+/// Total package energy in joules between two `read_rapl_energy` samples.
+fn rapl_joules(before: &[(u64, u64)], after: &[(u64, u64)]) -> f64 {
+    before
+        .iter()
+        .zip(after)
+        .map(|(&(b, max), &(a, _))| energy_delta_uj(b, a, max))
+        .sum::<u64>() as f64
+        / 1e6
+}
+
+/// Average current core frequency in MHz, as reported by the kernel.
+fn read_cpu_mhz() -> Option<f64> {
+    let contents = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    let mhz: Vec<f64> = contents
+        .lines()
+        .filter(|l| l.starts_with("cpu MHz"))
+        .filter_map(|l| l.split(':').nth(1)?.trim().parse().ok())
+        .collect();
+    if mhz.is_empty() {
+        None
+    } else {
+        Some(mhz.iter().sum::<f64>() / mhz.len() as f64)
+    }
+}
+
+/// Cumulative (busy, total) jiffies across all CPUs from the `cpu` line of
+/// `/proc/stat`; idle and iowait count as not busy.
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let contents = std::fs::read_to_string("/proc/stat").ok()?;
+    let line = contents.lines().find(|l| l.starts_with("cpu "))?;
+    let fields: Vec<u64> = line.split_whitespace().skip(1).filter_map(|f| f.parse().ok()).collect();
+    if fields.len() < 5 {
+        return None;
+    }
+    let total: u64 = fields.iter().sum();
+    Some((total - fields[3] - fields[4], total))
+}
+
+/// Utilization in percent between two `read_cpu_times` samples.
+fn cpu_utilization(before: (u64, u64), after: (u64, u64)) -> f64 {
+    let total = after.1.saturating_sub(before.1);
+    if total == 0 {
+        return 0.0;
+    }
+    after.0.saturating_sub(before.0) as f64 / total as f64 * 100.0
+}
+
+/// Length of one busy/idle cycle of the CPU stressor.
+const CPU_CYCLE: Duration = Duration::from_millis(100);
+/// How often `--cpu-system-percent` measures and corrects the duty cycle.
+const CPU_CONTROL_INTERVAL: Duration = Duration::from_millis(500);
+/// Fraction of the utilization error applied to the duty cycle per interval.
+const CPU_CONTROL_GAIN: f64 = 0.5;
+
+/// Closed-loop controller for `--cpu-system-percent`: every
+/// `CPU_CONTROL_INTERVAL` compares measured system utilization with
+/// `CPU_TARGET_PERCENT` and nudges `duty_micros` (busy time per `CPU_CYCLE`)
+/// toward it.
+fn run_cpu_controller(duty_micros: Arc<AtomicU64>, stop: Arc<AtomicBool>) {
+    let Some(mut last) = read_cpu_times() else {
+        return;
+    };
+    let cycle = CPU_CYCLE.as_micros() as f64;
+    let mut duty = duty_micros.load(Ordering::Relaxed) as f64;
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(CPU_CONTROL_INTERVAL);
+        let Some(now) = read_cpu_times() else {
+            return;
+        };
+        let target = CPU_TARGET_PERCENT.load(Ordering::Relaxed);
+        let error = target as f64 - cpu_utilization(last, now);
+        duty = (duty + error / 100.0 * cycle * CPU_CONTROL_GAIN).clamp(0.0, cycle);
+        duty_micros.store(duty as u64, Ordering::Relaxed);
+        last = now;
+    }
+}
+
+/// How often `--cpu-trace` samples the aggregate busy fraction.
+const CPU_TRACE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Samples `busy_nanos` (summed busy time of all CPU threads) every
+/// `CPU_TRACE_INTERVAL` and appends `timestamp_ms,elapsed_ms,busy_fraction`
+/// rows to `path` until `stop` is set.
+fn run_cpu_trace(
+    path: &str,
+    threads: usize,
+    busy_nanos: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+) -> std::io::Result<()> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    writeln!(out, "timestamp_ms,elapsed_ms,busy_fraction")?;
+
+    let start = Instant::now();
+    let mut last_busy = busy_nanos.load(Ordering::Relaxed);
+    let mut last = start;
+
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(CPU_TRACE_INTERVAL);
+        let now = Instant::now();
+        let busy = busy_nanos.load(Ordering::Relaxed);
+        let window = now.duration_since(last).as_nanos() as f64 * threads as f64;
+        let fraction = ((busy - last_busy) as f64 / window).min(1.0);
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        writeln!(
+            out,
+            "{},{},{:.4}",
+            timestamp_ms,
+            now.duration_since(start).as_millis(),
+            fraction
+        )?;
+        last_busy = busy;
+        last = now;
+    }
+
+    out.flush()
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn sched_setaffinity(
+        pid: std::ffi::c_int,
+        cpusetsize: usize,
+        mask: *const u64,
+    ) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "macos")]
+unsafe extern "C" {
+    fn pthread_self() -> usize;
+    fn pthread_mach_thread_np(thread: usize) -> u32;
+    fn thread_policy_set(
+        thread: u32,
+        flavor: std::ffi::c_int,
+        policy_info: *const std::ffi::c_int,
+        count: u32,
+    ) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "macos")]
+const THREAD_AFFINITY_POLICY: std::ffi::c_int = 4;
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GetCurrentThread() -> *mut std::ffi::c_void;
+    fn SetThreadAffinityMask(thread: *mut std::ffi::c_void, mask: usize) -> usize;
+}
+
+/// Pins the calling thread to `core`. On macOS this is only an affinity tag:
+/// threads with the same tag share a core, but the kernel picks which one.
+fn pin_current_thread(core: usize) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        // A cpu_set_t: 1024 bits.
+        let mut mask = [0u64; 16];
+        if core >= mask.len() * 64 {
+            return Err(format!("core {} is out of range", core));
+        }
+        mask[core / 64] |= 1 << (core % 64);
+        // SAFETY: mask is a full cpu_set_t; pid 0 means the calling thread.
+        if unsafe { sched_setaffinity(0, size_of_val(&mask), mask.as_ptr()) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error().to_string())
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // Tag 0 means "no affinity", so shift the core IDs up by one.
+        let tag = (core + 1) as std::ffi::c_int;
+        // SAFETY: the policy is a single integer_t affinity tag.
+        let ret = unsafe {
+            let thread = pthread_mach_thread_np(pthread_self());
+            thread_policy_set(thread, THREAD_AFFINITY_POLICY, &tag, 1)
+        };
+        if ret == 0 { Ok(()) } else { Err(format!("thread_policy_set failed ({})", ret)) }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        if core >= usize::BITS as usize {
+            return Err(format!("core {} is out of range", core));
+        }
+        // SAFETY: GetCurrentThread returns a pseudo-handle that needs no closing.
+        if unsafe { SetThreadAffinityMask(GetCurrentThread(), 1 << core) } != 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error().to_string())
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = core;
+        Err("CPU affinity is not supported on this platform".to_string())
+    }
+}
+
+/// Parses a `--cpu-affinity` core list such as `0,2,4`.
+fn parse_core_list(value: &str) -> Option<Vec<usize>> {
+    let cores: Option<Vec<usize>> = value.split(',').map(|s| s.trim().parse().ok()).collect();
+    cores.filter(|c| !c.is_empty())
+}
+
+/// Per-node sysfs directories, each with a `cpulist` and a `meminfo`.
+const NUMA_NODE_DIR: &str = "/sys/devices/system/node";
+
+/// Parses a kernel CPU list such as `0-3,8-11`, the sysfs `cpulist` format.
+fn parse_cpu_list(value: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for part in value.trim().split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last): (usize, usize) = (first.parse().ok()?, last.parse().ok()?);
+                cores.extend(first..=last);
+            }
+            None => cores.push(part.parse().ok()?),
+        }
+    }
+    Some(cores)
+}
+
+/// The cores of NUMA node `node` in `cpulist` form and expanded.
+fn numa_node_cpus(node: usize) -> Result<(String, Vec<usize>), String> {
+    let path = format!("{}/node{}/cpulist", NUMA_NODE_DIR, node);
+    let list = std::fs::read_to_string(&path).map_err(|_| format!("node {} not found", node))?;
+    let list = list.trim().to_string();
+    match parse_cpu_list(&list) {
+        Some(cores) if !cores.is_empty() => Ok((list, cores)),
+        Some(_) => Err(format!("node {} has no cores", node)),
+        None => Err(format!("cannot parse {} ({:?})", path, list)),
+    }
+}
+
+/// Memory in use on NUMA node `node` in MB, from its sysfs `meminfo`.
+fn numa_node_used_mb(node: usize) -> Option<u64> {
+    let contents =
+        std::fs::read_to_string(format!("{}/node{}/meminfo", NUMA_NODE_DIR, node)).ok()?;
+    // Lines look like "Node 0 MemUsed:   1631952 kB".
+    let line = contents.lines().find(|l| l.contains(" MemUsed:"))?;
+    let kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kb / 1024)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn syscall(number: std::ffi::c_long, ...) -> std::ffi::c_long;
+}
+
+/// glibc has no set_mempolicy wrapper (libnuma does), so it goes through
+/// syscall(2) with the per-architecture number.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const SYS_SET_MEMPOLICY: Option<std::ffi::c_long> = Some(238);
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const SYS_SET_MEMPOLICY: Option<std::ffi::c_long> = Some(237);
+#[cfg(all(target_os = "linux", not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+const SYS_SET_MEMPOLICY: Option<std::ffi::c_long> = None;
+
+#[cfg(target_os = "linux")]
+const MPOL_BIND: std::ffi::c_int = 2;
+
+/// Restricts the calling thread's future allocations to NUMA node `node`.
+fn bind_memory_to_node(node: usize) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        let number = SYS_SET_MEMPOLICY.ok_or("set_mempolicy is not wired up on this CPU")?;
+        // A nodemask of 1024 bits, the largest MAX_NUMNODES in use.
+        let mut mask = [0u64; 16];
+        if node >= mask.len() * 64 {
+            return Err(format!("node {} is out of range", node));
+        }
+        mask[node / 64] |= 1 << (node % 64);
+        // The kernel drops one from maxnode, so pass one more than the bits.
+        let maxnode = mask.len() * 64 + 1;
+        // SAFETY: set_mempolicy reads `maxnode - 1` bits from `mask`, which
+        // holds that many, and only changes this thread's policy.
+        if unsafe { syscall(number, MPOL_BIND, mask.as_ptr(), maxnode) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error().to_string())
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = node;
+        Err("NUMA binding is not supported on this platform".to_string())
+    }
+}
+
+/// Resolves `--numa-node` before the run: the CPU threads are pinned to the
+/// node's cores (one per core unless `--cpu-threads` says otherwise) unless
+/// `--cpu-affinity` names cores itself. A node that cannot be found is
+/// dropped with a warning and the run goes ahead unbound.
+fn apply_numa_node(cfg: &mut Config) {
+    let Some(node) = cfg.numa_node else {
+        return;
+    };
+    let cores = if cfg!(target_os = "linux") {
+        numa_node_cpus(node)
+    } else {
+        Err("NUMA binding is not supported on this platform".to_string())
+    };
+    let (list, cores) = match cores {
+        Ok(found) => found,
+        Err(e) => {
+            warn!("NUMA: {}; running unbound", e);
+            cfg.numa_node = None;
+            return;
+        }
+    };
+    info!("NUMA: binding to node {} (cores {})", node, list);
+    if !cfg.cpu_affinity.is_empty() {
+        warn!("NUMA: --cpu-affinity given, not pinning CPU threads to node {}", node);
+        return;
+    }
+    if cfg.cpu_threads == 0 {
+        cfg.cpu_threads = cores.len();
+    }
+    cfg.cpu_affinity = cores;
+}
+
+/// Upper bound for `--cpu-threads`; beyond this the run measures the
+/// scheduler rather than the CPU.
+const MAX_CPU_THREADS: usize = 1024;
+
+/// Where the cgroup v2 `cpu.max` and v1 CFS quota files sit inside a
+/// container (the cgroup namespace root is the container's own group).
+const CGROUP_CPU_MAX: &str = "/sys/fs/cgroup/cpu.max";
+const CGROUP_V1_CPU_DIR: &str = "/sys/fs/cgroup/cpu";
+
+/// CPUs a CFS quota of `quota` per `period` microseconds amounts to, rounded
+/// up so a fractional share still gets a thread. None for no quota.
+fn cpus_for_quota(quota: i64, period: i64) -> Option<usize> {
+    if quota <= 0 || period <= 0 {
+        return None;
+    }
+    Some((quota as u64).div_ceil(period as u64).max(1) as usize)
+}
+
+/// Effective CPUs from a cgroup v2 `cpu.max` ("<quota> <period>" or
+/// "max <period>").
+fn parse_cgroup_cpu_max(text: &str) -> Option<usize> {
+    let mut fields = text.split_whitespace();
+    let quota = fields.next()?.parse().ok()?;
+    let period = fields.next().map_or(Some(100_000), |p| p.parse().ok())?;
+    cpus_for_quota(quota, period)
+}
+
+/// Effective CPUs from cgroup v1 `cpu.cfs_quota_us` and `cpu.cfs_period_us`;
+/// a quota of -1 means unlimited.
+fn parse_cgroup_cfs_quota(quota: &str, period: &str) -> Option<usize> {
+    cpus_for_quota(quota.trim().parse().ok()?, period.trim().parse().ok()?)
+}
+
+/// CPUs the cgroup quota allows this process, if one is set.
+fn cgroup_cpu_limit() -> Option<usize> {
+    if let Ok(text) = std::fs::read_to_string(CGROUP_CPU_MAX) {
+        return parse_cgroup_cpu_max(&text);
+    }
+    let quota = std::fs::read_to_string(format!("{}/cpu.cfs_quota_us", CGROUP_V1_CPU_DIR)).ok()?;
+    let period = std::fs::read_to_string(format!("{}/cpu.cfs_period_us", CGROUP_V1_CPU_DIR)).ok()?;
+    parse_cgroup_cfs_quota(&quota, &period)
+}
+
+/// CPUs available to this process: `available_parallelism`, lowered to the
+/// cgroup CPU quota on Linux when one is set, since `available_parallelism`
+/// can report the host count inside containers.
+fn detect_cpu_count() -> usize {
+    static COUNT: OnceLock<usize> = OnceLock::new();
+    *COUNT.get_or_init(|| {
+        let quota = if cfg!(target_os = "linux") { cgroup_cpu_limit() } else { None };
+        cpus_within_quota(quota, thread::available_parallelism().map(|n| n.get()).unwrap_or(4))
+    })
+}
+
+/// `available` CPUs capped by a cgroup `quota`, which can only lower the count:
+/// a quota above the CPUs the process may run on buys it no more of them.
+fn cpus_within_quota(quota: Option<usize>, available: usize) -> usize {
+    quota.map_or(available, |quota| quota.min(available))
+}
+
+/// Number of CPU stress threads: `cpu_threads`, or one per core when 0.
+fn cpu_thread_count(cfg: &Config) -> usize {
+    if cfg.cpu_threads == 0 {
+        return detect_cpu_count();
+    }
+    if cfg.cpu_threads > MAX_CPU_THREADS {
+        warn!("CPU: clamping --cpu-threads {} to {}", cfg.cpu_threads, MAX_CPU_THREADS);
+    }
+    cfg.cpu_threads.min(MAX_CPU_THREADS)
+}
+
+/// What the CPU stressor got done: kernel iterations over the run.
+#[derive(Debug, Default)]
+pub struct CpuStats {
+    pub threads: usize,
+    pub target_percent: u64,
+    pub ops: u64,
+    pub elapsed: Duration,
+    /// Time all threads together spent running the kernel.
+    pub busy: Duration,
+    /// CPU time the stress threads used per thread and wall-clock second, in
+    /// percent, where the platform reports it.
+    pub achieved_percent: Option<f64>,
+}
+
+impl CpuStats {
+    pub fn mops(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.ops as f64 / secs / 1e6 } else { 0.0 }
+    }
+
+    /// Measured average load of one stress thread, in percent.
+    pub fn load_percent(&self) -> f64 {
+        let window = self.elapsed.as_secs_f64() * self.threads as f64;
+        if window > 0.0 { self.busy.as_secs_f64() / window * 100.0 } else { 0.0 }
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("threads", self.threads.into()),
+            ("target_percent", self.target_percent.into()),
+            ("ops", self.ops.into()),
+            ("mops", self.mops().into()),
+            ("load_percent", self.load_percent().into()),
+            ("achieved_percent", self.achieved_percent.into()),
+            ("elapsed_secs", self.elapsed.as_secs_f64().into()),
+        ])
+    }
+}
+
+/// Busy time per `CPU_CYCLE` for each of `threads` threads so that together
+/// they keep `percent`% of `cores` cores busy (`--cpu-total`). Capped at a
+/// full cycle when there are too few threads to reach the total.
+fn total_load_duty_micros(percent: u64, cores: usize, threads: usize) -> u64 {
+    let cycle = CPU_CYCLE.as_micros() as u64;
+    let busy = percent * cores as u64 * cycle / 100 / threads.max(1) as u64;
+    busy.min(cycle)
+}
+
+/// Share of the target load applied `elapsed` into a `--cpu-ramp` of
+/// `ramp`: rising linearly from 0 to 1, then 1 for the rest of the run.
+fn ramp_fraction(elapsed: Duration, ramp: Duration) -> f64 {
+    if elapsed >= ramp { 1.0 } else { elapsed.as_secs_f64() / ramp.as_secs_f64() }
+}
+
+/// Average share of the target load over a run of `duration` that starts
+/// with a `ramp`, for comparing against the measured load.
+fn ramp_average(ramp: Duration, duration: Duration) -> f64 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    let ramp = ramp.min(duration).as_secs_f64();
+    1.0 - ramp / 2.0 / duration.as_secs_f64()
+}
+
+/// How long each CPU thread times the kernel to size its first busy period.
+const CPU_CALIBRATION: Duration = Duration::from_millis(20);
+
+/// Weight of the latest cycle when re-estimating the kernel rate, so the
+/// busy period follows frequency changes (turbo, thermal throttling).
+const CPU_RATE_SMOOTHING: f64 = 0.2;
+
+/// Kernel iterations per microsecond on the calling thread. The clock is
+/// read once per doubling batch so its cost does not skew the estimate.
+fn calibrate_kernel(workload: CpuWorkload, chain: &mut CacheChain) -> f64 {
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    let mut batch = 1u64;
+    while start.elapsed() < CPU_CALIBRATION {
+        for _ in 0..batch {
+            run_cpu_kernel(workload, chain);
+        }
+        iterations += batch;
+        batch = batch.saturating_mul(2);
+    }
+    iterations as f64 / start.elapsed().as_secs_f64() / 1e6
+}
+
+/// Spawns the duty-cycle threads behind `stress_cpu`. Each one runs until
+/// `duration_secs` is up or the run is interrupted. A cycle runs as many
+/// kernel iterations as fit the busy time at the calibrated rate, without
+/// polling the clock, then sleeps until the next cycle is due so sleep
+/// overshoot does not accumulate. Each thread returns the CPU time it used,
+/// where the platform reports it.
+fn spawn_cpu_threads(
+    cfg: &Config,
+    threads: usize,
+    workload: CpuWorkload,
+    duty_micros: Arc<AtomicU64>,
+    busy_nanos: Arc<AtomicU64>,
+    ops: Arc<AtomicU64>,
+) -> Vec<thread::JoinHandle<Option<Duration>>> {
+    let duration = Duration::from_secs(cfg.duration_secs);
+    let ramp = Duration::from_secs(cfg.cpu_ramp_secs);
+    let mut handles = vec![];
+    for t in 0..threads {
+        let busy_nanos = busy_nanos.clone();
+        let duty_micros = duty_micros.clone();
+        let ops = ops.clone();
+        // Threads cycle through the listed cores; only the first thread on
+        // each core reports a failure to pin.
+        let affinity = &cfg.cpu_affinity;
+        let core = (!affinity.is_empty())
+            .then(|| (affinity[t % affinity.len()], t < affinity.len()));
+        let (cache_size_mb, seed) = (cfg.cache_size_mb, cfg.seed);
+        handles.push(spawn_named(format!("stressr-cpu-{}", t), move || {
+            let cpu_start = thread_cpu_time();
+            if let Some((core, first)) = core
+                && let Err(e) = pin_current_thread(core)
+                && first
+            {
+                warn!("CPU: cannot pin to core {} ({}), running unpinned", core, e);
+            }
+            // Built on the thread itself so its pages are first touched there.
+            let mut chain = CacheChain::for_thread(workload, cache_size_mb, seed, t);
+            let mut per_micro = calibrate_kernel(workload, &mut chain);
+            let start = Instant::now();
+            let mut next_cycle = start;
+            while start.elapsed() < duration && running() {
+                let throttle = THROTTLE_LEVEL.load(Ordering::Relaxed);
+                let duty = duty_micros.load(Ordering::Relaxed) as f64;
+                let busy_micros = (duty * ramp_fraction(start.elapsed(), ramp)) as u64 >> throttle;
+                let iterations = (busy_micros as f64 * per_micro) as u64;
+                let t0 = Instant::now();
+                for _ in 0..iterations {
+                    run_cpu_kernel(workload, &mut chain);
+                }
+                let spun = t0.elapsed();
+                if iterations > 0 && !spun.is_zero() {
+                    let rate = iterations as f64 / spun.as_secs_f64() / 1e6;
+                    per_micro += (rate - per_micro) * CPU_RATE_SMOOTHING;
+                }
+                busy_nanos.fetch_add(spun.as_nanos() as u64, Ordering::Relaxed);
+                PROGRESS_CPU_BUSY_NANOS.fetch_add(spun.as_nanos() as u64, Ordering::Relaxed);
+                ops.fetch_add(iterations, Ordering::Relaxed);
+
+                next_cycle += CPU_CYCLE;
+                let now = Instant::now();
+                if next_cycle > now {
+                    thread::sleep(next_cycle - now);
+                } else {
+                    // Overran the cycle; start afresh rather than skipping idle time.
+                    next_cycle = now;
+                }
+            }
+            cpu_start.zip(thread_cpu_time()).map(|(before, after)| after.saturating_sub(before))
+        }));
+    }
+    handles
+}
+
+/// Loads every core at `cpu_percent` for `duration_secs`.
+pub fn stress_cpu(cfg: &Config) -> CpuStats {
+    let mut percent = cfg.cpu_percent;
+    let mut system_target = cfg.cpu_system_percent;
+    if let Some(target) = system_target {
+        percent = target;
+        if read_cpu_times().is_none() {
+            warn!("CPU: /proc/stat is not available, running a fixed {}% duty cycle", target);
+            system_target = None;
+        }
+    }
+    let duration = Duration::from_secs(cfg.duration_secs);
+    let threads = cpu_thread_count(cfg);
+    let mut duty = percent * CPU_CYCLE.as_micros() as u64 / 100;
+    let mut total = String::new();
+    if cfg.cpu_total && system_target.is_none() {
+        let cores = detect_cpu_count();
+        duty = total_load_duty_micros(percent, cores, threads);
+        let reachable = threads as u64 * 100 / cores as u64;
+        if percent > reachable {
+            warn!(
+                "CPU: {} threads can load at most {}% of {} cores, not {}%",
+                threads, reachable, cores, percent
+            );
+        }
+        total = format!(", {}% total of {} cores", percent, cores);
+    }
+    let duty_micros = Arc::new(AtomicU64::new(duty));
+
+    let mut workload = cfg.cpu_workload;
+    if workload == CpuWorkload::Avx512 && !avx512_supported() {
+        warn!("CPU: AVX-512 is not supported on this CPU, falling back to the int workload");
+        workload = CpuWorkload::Int;
+    }
+
+    let pinned = if cfg.cpu_affinity.is_empty() {
+        String::new()
+    } else {
+        let cores: Vec<String> = cfg.cpu_affinity.iter().map(|c| c.to_string()).collect();
+        format!(", pinned to cores {}", cores.join(","))
+    };
+    match system_target {
+        Some(target) => info!(
+            "CPU: {} threads targeting {}% system utilization ({} workload{})",
+            threads, target, workload.name(), pinned
+        ),
+        None => info!(
+            "CPU: {} threads @ {:.1}% ({} workload{}{})",
+            threads,
+            duty as f64 / CPU_CYCLE.as_micros() as f64 * 100.0,
+            workload.name(),
+            pinned,
+            total
+        ),
+    }
+    let ramp = Duration::from_secs(cfg.cpu_ramp_secs);
+    if !ramp.is_zero() {
+        info!(
+            "CPU: ramping from 0% to {}% over {}s, then holding for {}s",
+            percent,
+            ramp.as_secs(),
+            duration.saturating_sub(ramp).as_secs()
+        );
+    }
+
+    let idle_mhz = read_cpu_mhz();
+    let busy_nanos = Arc::new(AtomicU64::new(0));
+    let stop_trace = Arc::new(AtomicBool::new(false));
+
+    let trace = cfg.cpu_trace.clone().map(|path| {
+        let busy_nanos = busy_nanos.clone();
+        let stop = stop_trace.clone();
+        spawn_named("stressr-cpu-trace".to_string(), move || {
+            if let Err(e) = run_cpu_trace(&path, threads, busy_nanos, stop) {
+                error!("CPU: failed to write trace to {}: {}", path, e);
+            }
+        })
+    });
+
+    let system_start = read_cpu_times();
+    CPU_TARGET_PERCENT.store(percent, Ordering::Relaxed);
+    let controller = system_target.map(|_| {
+        let duty_micros = duty_micros.clone();
+        let stop = stop_trace.clone();
+        spawn_named("stressr-cpu-ctl".to_string(), move || {
+            run_cpu_controller(duty_micros, stop)
+        })
+    });
+    // The adjuster returns the time-weighted average target and duty, so
+    // the summary compares against the load that was actually asked for.
+    let adjuster = cfg.signal_adjust.then(|| {
+        let duty_micros = duty_micros.clone();
+        let stop = stop_trace.clone();
+        let (system, cpu_total) = (system_target.is_some(), cfg.cpu_total);
+        let cores = detect_cpu_count();
+        spawn_named("stressr-cpu-adjust".to_string(), move || {
+            let mut current = percent;
+            let (mut weighted_percent, mut weighted_duty) = (0.0, 0.0);
+            let (begin, mut since) = (Instant::now(), Instant::now());
+            let mut add_span = |percent: u64, since: Instant| {
+                let secs = since.elapsed().as_secs_f64();
+                weighted_percent += percent as f64 * secs;
+                weighted_duty += duty_micros.load(Ordering::Relaxed) as f64 * secs;
+            };
+            while !stop.load(Ordering::Relaxed) {
+                thread::sleep(CPU_ADJUST_POLL);
+                let target = CPU_TARGET_PERCENT.load(Ordering::Relaxed);
+                if target == current {
+                    continue;
+                }
+                add_span(current, since);
+                since = Instant::now();
+                current = target;
+                // The controller picks up a new system target by itself.
+                if !system {
+                    let duty = if cpu_total {
+                        total_load_duty_micros(target, cores, threads)
+                    } else {
+                        target * CPU_CYCLE.as_micros() as u64 / 100
+                    };
+                    duty_micros.store(duty, Ordering::Relaxed);
+                }
+                info!("CPU: target adjusted to {}%", target);
+            }
+            add_span(current, since);
+            let secs = begin.elapsed().as_secs_f64();
+            (secs > 0.0).then(|| (weighted_percent / secs, weighted_duty / secs))
+        })
+    });
+
+    let start = Instant::now();
+    let ops = Arc::new(AtomicU64::new(0));
+    let handles = spawn_cpu_threads(
+        cfg,
+        threads,
+        workload,
+        duty_micros.clone(),
+        busy_nanos.clone(),
+        ops.clone(),
+    );
+    PROGRESS_CPU_THREADS.fetch_add(threads as u64, Ordering::Relaxed);
+
+    if workload == CpuWorkload::Avx512 {
+        // Sample mid-run, once the frequency license has had time to settle.
+        sleep_while_running(duration / 2);
+        match (idle_mhz, read_cpu_mhz()) {
+            (Some(before), Some(during)) => report!(
+                "CPU: observed frequency {:.0} MHz under load (was {:.0} MHz before start)",
+                during, before
+            ),
+            _ => warn!("CPU: core frequency is not available on this platform"),
+        }
+    }
+
+    let cpu_times: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    PROGRESS_CPU_THREADS.fetch_sub(threads as u64, Ordering::Relaxed);
+    let elapsed = start.elapsed();
+    let window = elapsed.as_secs_f64() * threads as f64;
+    let stats = CpuStats {
+        threads,
+        target_percent: percent,
+        ops: ops.load(Ordering::Relaxed),
+        elapsed,
+        busy: Duration::from_nanos(busy_nanos.load(Ordering::Relaxed)),
+        achieved_percent: cpu_times
+            .into_iter()
+            .sum::<Option<Duration>>()
+            .filter(|_| window > 0.0)
+            .map(|used| used.as_secs_f64() / window * 100.0),
+    };
+
+    stop_trace.store(true, Ordering::Relaxed);
+    if let Some(trace) = trace {
+        trace.join().unwrap();
+    }
+    if let Some(controller) = controller {
+        controller.join().unwrap();
+    }
+    let adjusted = adjuster.and_then(|adjuster| adjuster.join().unwrap());
+    let adjusted_note = if adjusted.is_some() { " averaged over the adjustments" } else { "" };
+
+    let system_end = read_cpu_times();
+    if let (Some(target), Some(before), Some(after)) = (system_target, system_start, system_end) {
+        report!(
+            "CPU: achieved {:.1}% system utilization (target {:.1}%{})",
+            cpu_utilization(before, after),
+            adjusted.map_or(target as f64, |(percent, _)| percent),
+            adjusted_note
+        );
+    }
+
+    report!(
+        "CPU: {:.2} Mops/s ({} kernel iterations, {} workload)",
+        stats.mops(),
+        stats.ops,
+        workload.name()
+    );
+    if workload == CpuWorkload::Cache && stats.ops > 0 {
+        let accesses = stats.ops * CACHE_CHASE_STEPS;
+        report!(
+            "CPU: {:.2} M accesses/s over {} MB per thread, {:.1} ns per access",
+            accesses as f64 / stats.elapsed.as_secs_f64() / 1e6,
+            cfg.cache_size_mb,
+            stats.busy.as_nanos() as f64 / accesses as f64
+        );
+    }
+    if workload == CpuWorkload::Primes && stats.ops > 0 {
+        let passes = stats.ops as f64 / stats.elapsed.as_secs_f64();
+        report!(
+            "CPU: {:.1} sieve passes/s over the integers below {} ({:.1} M integers/s)",
+            passes,
+            2 * PRIME_SIEVE_ODDS,
+            passes * (2 * PRIME_SIEVE_ODDS) as f64 / 1e6
+        );
+    }
+    let duty = adjusted.map_or(duty as f64, |(_, duty)| duty);
+    let target = duty / CPU_CYCLE.as_micros() as f64 * 100.0 * ramp_average(ramp, duration);
+    let averaged = match (ramp.is_zero(), adjusted.is_some()) {
+        (true, _) => adjusted_note,
+        (false, false) => " averaged over the ramp",
+        (false, true) => " averaged over the ramp and the adjustments",
+    };
+    match system_target {
+        Some(_) => report!("CPU: measured {:.1}% load per thread", stats.load_percent()),
+        None => report!(
+            "CPU: measured {:.1}% load per thread (target {:.1}%{})",
+            stats.load_percent(),
+            target,
+            averaged
+        ),
+    }
+    if system_target.is_none()
+        && let Some(achieved) = stats.achieved_percent
+    {
+        report!("CPU achieved: {:.1}% (target {:.1}%{})", achieved, target, averaged);
+    }
+    stats
+}
+
+#[cfg(target_os = "windows")]
+#[allow(non_snake_case, dead_code)]
+#[derive(Default)]
+#[repr(C)]
+struct MEMORYSTATUSEX {
+    dwLength: u32,
+    dwMemoryLoad: u32,
+    ullTotalPhys: u64,
+    ullAvailPhys: u64,
+    ullTotalPageFile: u64,
+    ullAvailPageFile: u64,
+    ullTotalVirtual: u64,
+    ullAvailVirtual: u64,
+    ullAvailExtendedVirtual: u64,
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GlobalMemoryStatusEx(lpBuffer: *mut MEMORYSTATUSEX) -> i32;
+}
+
+/// `GlobalMemoryStatusEx`, or None if the call fails.
+#[cfg(target_os = "windows")]
+fn memory_status() -> Option<MEMORYSTATUSEX> {
+    let mut status = MEMORYSTATUSEX {
+        dwLength: std::mem::size_of::<MEMORYSTATUSEX>() as u32,
+        ..Default::default()
+    };
+    // SAFETY: `status` is a writable MEMORYSTATUSEX with dwLength set as the API requires.
+    let ok = unsafe { GlobalMemoryStatusEx(&mut status) };
+    (ok != 0).then_some(status)
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GetDiskFreeSpaceExW(
+        lpDirectoryName: *const u16,
+        lpFreeBytesAvailableToCaller: *mut u64,
+        lpTotalNumberOfBytes: *mut u64,
+        lpTotalNumberOfFreeBytes: *mut u64,
+    ) -> i32;
+}
+
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct Statvfs {
+    f_bsize: std::ffi::c_ulong,
+    f_frsize: std::ffi::c_ulong,
+    f_blocks: std::ffi::c_ulong,
+    f_bfree: std::ffi::c_ulong,
+    f_bavail: std::ffi::c_ulong,
+    f_files: std::ffi::c_ulong,
+    f_ffree: std::ffi::c_ulong,
+    f_favail: std::ffi::c_ulong,
+    f_fsid: std::ffi::c_ulong,
+    f_flag: std::ffi::c_ulong,
+    f_namemax: std::ffi::c_ulong,
+    __f_spare: [std::ffi::c_int; 6],
+}
+
+#[cfg(target_os = "macos")]
+#[repr(C)]
+struct Statvfs {
+    f_bsize: std::ffi::c_ulong,
+    f_frsize: std::ffi::c_ulong,
+    f_blocks: std::ffi::c_uint,
+    f_bfree: std::ffi::c_uint,
+    f_bavail: std::ffi::c_uint,
+    f_files: std::ffi::c_uint,
+    f_ffree: std::ffi::c_uint,
+    f_favail: std::ffi::c_uint,
+    f_fsid: std::ffi::c_ulong,
+    f_flag: std::ffi::c_ulong,
+    f_namemax: std::ffi::c_ulong,
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+unsafe extern "C" {
+    fn statvfs(path: *const std::ffi::c_char, buf: *mut Statvfs) -> std::ffi::c_int;
+}
+
+#[cfg(unix)]
+#[repr(C)]
+struct Rlimit {
+    rlim_cur: u64,
+    rlim_max: u64,
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn getrlimit(resource: std::ffi::c_int, rlim: *mut Rlimit) -> std::ffi::c_int;
+    fn setrlimit(resource: std::ffi::c_int, rlim: *const Rlimit) -> std::ffi::c_int;
+}
+
+#[cfg(unix)]
+const RLIMIT_CPU: std::ffi::c_int = 0;
+#[cfg(target_os = "linux")]
+const RLIMIT_NOFILE: std::ffi::c_int = 7;
+#[cfg(all(unix, not(target_os = "linux")))]
+const RLIMIT_NOFILE: std::ffi::c_int = 8;
+#[cfg(target_os = "linux")]
+const RLIMIT_AS: std::ffi::c_int = 9;
+#[cfg(all(unix, not(target_os = "linux")))]
+const RLIMIT_AS: std::ffi::c_int = 5;
+#[cfg(target_os = "linux")]
+const RLIMIT_MEMLOCK: std::ffi::c_int = 8;
+#[cfg(all(unix, not(target_os = "linux")))]
+const RLIMIT_MEMLOCK: std::ffi::c_int = 6;
+
+/// Current (soft, hard) values of a resource limit.
+#[cfg(unix)]
+fn get_rlimit(resource: std::ffi::c_int) -> Option<(u64, u64)> {
+    let mut rlim = Rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: rlim is a valid, writable rlimit struct.
+    if unsafe { getrlimit(resource, &mut rlim) } == 0 {
+        return Some((rlim.rlim_cur, rlim.rlim_max));
+    }
+    None
+}
+
+#[cfg(unix)]
+fn set_rlimit(resource: std::ffi::c_int, soft: u64, hard: u64) -> bool {
+    let rlim = Rlimit {
+        rlim_cur: soft,
+        rlim_max: hard,
+    };
+    // SAFETY: rlim is a valid rlimit struct.
+    unsafe { setrlimit(resource, &rlim) == 0 }
+}
+
+/// Current (soft, hard) open file descriptor limits.
+fn fd_limits() -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        get_rlimit(RLIMIT_NOFILE)
+    }
+
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Raises the open file soft limit to `target` (capped at the hard limit).
+fn raise_fd_limit(target: u64) -> bool {
+    #[cfg(unix)]
+    {
+        let Some((_, hard)) = fd_limits() else {
+            return false;
+        };
+        set_rlimit(RLIMIT_NOFILE, target.min(hard), hard)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = target;
+        false
+    }
+}
+
+/// Applies `--limit-memory` and `--limit-cpu-time` as kernel-enforced soft and
+/// hard limits, so a misbehaving stressor is stopped by the kernel rather than
+/// relying on the tool's own accounting. Worker processes inherit them.
+/// Returns a description of each applied limit, or why one could not be set.
+fn apply_resource_limits(cfg: &Config) -> Result<Vec<String>, String> {
+    let mut applied = Vec::new();
+
+    #[cfg(unix)]
+    {
+        let limits = [
+            (RLIMIT_AS, cfg.limit_memory_mb.map(|mb| mb * 1024 * 1024), "address space"),
+            (RLIMIT_CPU, cfg.limit_cpu_secs, "CPU time"),
+        ];
+        for (resource, value, name) in limits {
+            let Some(value) = value else {
+                continue;
+            };
+            let (_, hard) =
+                get_rlimit(resource).ok_or_else(|| format!("cannot read the {} limit", name))?;
+            if value > hard {
+                return Err(format!("{} limit exceeds the current hard limit", name));
+            }
+            // RLIMIT_CPU sends SIGXCPU at the soft limit; keep a second of
+            // headroom before the hard limit's SIGKILL.
+            let hard = if resource == RLIMIT_CPU { (value + 1).min(hard) } else { value };
+            if !set_rlimit(resource, value, hard) {
+                return Err(format!("setrlimit failed for the {} limit", name));
+            }
+            applied.push(match resource {
+                RLIMIT_AS => format!("address space {} MB", value / (1024 * 1024)),
+                _ => format!("CPU time {} s", value),
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        if cfg.limit_memory_mb.is_some() || cfg.limit_cpu_secs.is_some() {
+            return Err("resource limits are only supported on Unix".to_string());
+        }
+    }
+
+    Ok(applied)
+}
+
+#[cfg(unix)]
+#[repr(C)]
+#[derive(Default)]
+struct Timeval {
+    tv_sec: std::ffi::c_long,
+    #[cfg(target_os = "linux")]
+    tv_usec: std::ffi::c_long,
+    // suseconds_t is 32-bit outside Linux, padded to 16 bytes.
+    #[cfg(not(target_os = "linux"))]
+    tv_usec: i32,
+    #[cfg(not(target_os = "linux"))]
+    _pad: i32,
+}
+
+#[cfg(unix)]
+impl Timeval {
+    fn to_duration(&self) -> Duration {
+        Duration::from_secs(self.tv_sec as u64) + Duration::from_micros(self.tv_usec as u64)
+    }
+}
+
+#[cfg(unix)]
+#[repr(C)]
+#[derive(Default)]
+struct Rusage {
+    ru_utime: Timeval,
+    ru_stime: Timeval,
+    ru_maxrss: std::ffi::c_long,
+    _ru_shared: [std::ffi::c_long; 3],
+    ru_minflt: std::ffi::c_long,
+    ru_majflt: std::ffi::c_long,
+    _ru_rest: [std::ffi::c_long; 8],
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn getrusage(who: std::ffi::c_int, usage: *mut Rusage) -> std::ffi::c_int;
+}
+
+#[cfg(unix)]
+const RUSAGE_SELF: std::ffi::c_int = 0;
+#[cfg(unix)]
+const RUSAGE_CHILDREN: std::ffi::c_int = -1;
+
+/// What a process (or its reaped children) consumed over the run.
+#[derive(Debug)]
+struct ResourceUsage {
+    user: Duration,
+    system: Duration,
+    max_rss_kb: u64,
+    minor_faults: u64,
+    major_faults: u64,
+}
+
+impl ResourceUsage {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("user_secs", self.user.as_secs_f64().into()),
+            ("system_secs", self.system.as_secs_f64().into()),
+            ("max_rss_kb", self.max_rss_kb.into()),
+            ("minor_faults", self.minor_faults.into()),
+            ("major_faults", self.major_faults.into()),
+        ])
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "user {:.2}s | system {:.2}s | max RSS {:.1} MB | page faults {} minor, {} major",
+            self.user.as_secs_f64(),
+            self.system.as_secs_f64(),
+            self.max_rss_kb as f64 / 1024.0,
+            self.minor_faults,
+            self.major_faults
+        )
+    }
+}
+
+/// getrusage for this process (`children == false`) or its waited-for children.
+fn resource_usage(children: bool) -> Option<ResourceUsage> {
+    #[cfg(unix)]
+    {
+        let who = if children { RUSAGE_CHILDREN } else { RUSAGE_SELF };
+        let mut usage = Rusage::default();
+        // SAFETY: usage is a valid, writable rusage struct.
+        if unsafe { getrusage(who, &mut usage) } != 0 {
+            return None;
+        }
+        // ru_maxrss is in kilobytes on Linux but bytes on macOS.
+        let max_rss_kb = if cfg!(target_os = "macos") {
+            usage.ru_maxrss as u64 / 1024
+        } else {
+            usage.ru_maxrss as u64
+        };
+        Some(ResourceUsage {
+            user: usage.ru_utime.to_duration(),
+            system: usage.ru_stime.to_duration(),
+            max_rss_kb,
+            minor_faults: usage.ru_minflt as u64,
+            major_faults: usage.ru_majflt as u64,
+        })
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = children;
+        None
+    }
+}
+
+/// `struct timespec`, whose fields are both C longs on the platforms that
+/// have `CLOCK_THREAD_CPUTIME_ID`.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[repr(C)]
+#[derive(Default)]
+struct Timespec {
+    tv_sec: std::ffi::c_long,
+    tv_nsec: std::ffi::c_long,
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+unsafe extern "C" {
+    fn clock_gettime(clock: std::ffi::c_int, tp: *mut Timespec) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "linux")]
+const CLOCK_THREAD_CPUTIME_ID: std::ffi::c_int = 3;
+#[cfg(target_os = "macos")]
+const CLOCK_THREAD_CPUTIME_ID: std::ffi::c_int = 16;
+
+#[cfg(target_os = "windows")]
+#[repr(C)]
+#[derive(Default)]
+struct FileTime {
+    low: u32,
+    high: u32,
+}
+
+#[cfg(target_os = "windows")]
+impl FileTime {
+    /// FILETIME durations count 100ns ticks.
+    fn to_duration(&self) -> Duration {
+        Duration::from_nanos((((self.high as u64) << 32) | self.low as u64) * 100)
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn GetCurrentProcess() -> *mut std::ffi::c_void;
+    fn GetCurrentThread() -> *mut std::ffi::c_void;
+    fn GetThreadTimes(
+        thread: *mut std::ffi::c_void,
+        creation: *mut FileTime,
+        exit: *mut FileTime,
+        kernel: *mut FileTime,
+        user: *mut FileTime,
+    ) -> i32;
+}
+
+/// User plus system CPU time the calling thread has used so far.
+fn thread_cpu_time() -> Option<Duration> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let mut ts = Timespec::default();
+        // SAFETY: `ts` is a writable timespec.
+        let ok = unsafe { clock_gettime(CLOCK_THREAD_CPUTIME_ID, &mut ts) } == 0;
+        ok.then(|| Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let (mut creation, mut exit) = (FileTime::default(), FileTime::default());
+        let (mut kernel, mut user) = (FileTime::default(), FileTime::default());
+        // SAFETY: GetCurrentThread returns a pseudo-handle needing no
+        // closing, and each out-pointer is a writable FILETIME.
+        let ok = unsafe {
+            GetThreadTimes(GetCurrentThread(), &mut creation, &mut exit, &mut kernel, &mut user)
+        };
+        (ok != 0).then(|| kernel.to_duration() + user.to_duration())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Descriptors the process needs besides the I/O workers' own files.
+const FD_BASE_OVERHEAD: u64 = 16;
+
+/// Open file descriptors this process will hold at peak for the configured I/O.
+fn projected_fd_usage(cfg: &Config) -> u64 {
+    if !cfg.io_enabled {
+        return FD_BASE_OVERHEAD;
+    }
+    // Threads hold all their worker files; process mode holds one pipe per child.
+    let per_worker = if cfg.io_processes { 1 } else { cfg.io_files_per_worker.max(1) };
+    FD_BASE_OVERHEAD + (cfg.io_paths.len() * cfg.io_workers * per_worker) as u64
+}
+
+/// Checks the projected descriptor usage against RLIMIT_NOFILE, raising the
+/// soft limit if allowed. Fails if the run would run out of fds.
+fn check_fd_limit(cfg: &Config) -> Result<(), StressError> {
+    let Some((soft, hard)) = fd_limits() else {
+        return Ok(());
+    };
+    let needed = projected_fd_usage(cfg);
+    let mut soft = soft;
+
+    if needed > soft && cfg.raise_fd_limit && raise_fd_limit(needed) {
+        soft = fd_limits().map(|(s, _)| s).unwrap_or(soft);
+    }
+
+    info!(
+        "Open file limit: {} (hard {}), projected use {}",
+        soft, hard, needed
+    );
+
+    if needed > soft {
+        return Err(StressError::InvalidConfig(format!(
+            "projected open files ({}) exceed the limit ({}); raise it with `ulimit -n`{}",
+            needed,
+            soft,
+            if cfg.raise_fd_limit || needed > hard {
+                ""
+            } else {
+                " or pass --raise-fd-limit"
+            }
+        )));
+    }
+    Ok(())
+}
+
+/// Device id of the filesystem holding `path`, where the platform has one.
+fn filesystem_id(path: &str) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        std::fs::metadata(path).ok().map(|m| m.dev())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// The I/O paths grouped by filesystem, with the bytes their worker files
+/// will take there in total. Paths on one filesystem share its free space;
+/// a path whose filesystem is unknown is a group of its own.
+fn io_space_needs(cfg: &Config) -> Vec<(Vec<String>, u64)> {
+    let mut groups: Vec<(Option<u64>, Vec<String>, u64)> = Vec::new();
+    let per_path = cfg.io_size_bytes.saturating_mul(cfg.io_workers as u64);
+    for path in &cfg.io_paths {
+        let fs = filesystem_id(path);
+        let group = groups.iter_mut().find(|(id, paths, _)| match (fs, id) {
+            (Some(fs), Some(id)) => fs == *id,
+            _ => paths.contains(path),
+        });
+        match group {
+            Some((_, paths, needed)) => {
+                paths.push(path.clone());
+                *needed = needed.saturating_add(per_path);
+            }
+            None => groups.push((fs, vec![path.clone()], per_path)),
+        }
+    }
+    groups.into_iter().map(|(_, paths, needed)| (paths, needed)).collect()
+}
+
+/// Checks every I/O path before any worker starts: each must be a directory
+/// that takes a probe file, or, under `--io-reuse` where the worker files are
+/// already in place, one that can be listed. Reports all bad paths at once.
+fn check_io_paths(cfg: &Config) -> Result<(), StressError> {
+    let mut problems = Vec::new();
+    for path in cfg.io_paths.iter().filter(|p| !is_raw_device(p)) {
+        let problem = match std::fs::metadata(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => "does not exist".to_string(),
+            Err(e) => format!("cannot be accessed ({})", e),
+            Ok(meta) if !meta.is_dir() => "is not a directory".to_string(),
+            Ok(_) if cfg.io_reuse => match std::fs::read_dir(path) {
+                Ok(_) => continue,
+                Err(e) => format!("is not readable ({})", e),
+            },
+            Ok(_) => match probe_writable_dir(path) {
+                Ok(()) => continue,
+                Err(e) => format!("is not writable ({})", e),
+            },
+        };
+        problems.push(format!("{} {}", path, problem));
+    }
+    let hint = if cfg.io_paths == [default_io_path()] { "; pass --io-paths <DIR>" } else { "" };
+    match problems.as_slice() {
+        [] => Ok(()),
+        [problem] => Err(StressError::InvalidConfig(format!("I/O path {}{}", problem, hint))),
+        _ => Err(StressError::InvalidConfig(format!(
+            "{} I/O paths cannot be used: {}",
+            problems.len(),
+            problems.join("; ")
+        ))),
+    }
+}
+
+/// Checks that the worker files fit in the free space of each filesystem
+/// before any are created; `set_len` makes sparse files that only run out of
+/// space partway through the writes. Fails listing every filesystem they do
+/// not fit on.
+fn check_io_space(cfg: &Config) -> Result<(), StressError> {
+    // Sized from free space, laid down already, or not sized by --io-size.
+    if cfg.io_allow_overcommit
+        || cfg.io_size_percent.is_some()
+        || cfg.io_reuse
+        || cfg.io_metadata
+        || cfg.io_trace.is_some()
+    {
+        return Ok(());
+    }
+
+    let mut shortfalls = Vec::new();
+    for (paths, needed) in io_space_needs(cfg) {
+        let Some(available) = available_space_bytes(&paths[0]) else {
+            continue;
+        };
+        if needed > available {
+            shortfalls.push(SpaceShortfall { path: paths.join(", "), needed, available });
+        }
+    }
+    if shortfalls.is_empty() { Ok(()) } else { Err(StressError::OutOfSpace(shortfalls)) }
+}
+
+/// Bytes available to unprivileged writers on the filesystem holding `path`.
+fn available_space_bytes(path: &str) -> Option<u64> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    {
+        let c_path = std::ffi::CString::new(path).ok()?;
+        let mut buf = std::mem::MaybeUninit::<Statvfs>::zeroed();
+        // SAFETY: c_path is NUL-terminated and buf is a properly sized statvfs struct.
+        let rc = unsafe { statvfs(c_path.as_ptr(), buf.as_mut_ptr()) };
+        if rc != 0 {
+            return None;
+        }
+        // SAFETY: statvfs succeeded and filled the struct.
+        let st = unsafe { buf.assume_init() };
+        // fsblkcnt_t is 32-bit on macOS; the casts are only no-ops on Linux.
+        #[allow(clippy::unnecessary_cast)]
+        let available = st.f_bavail as u64 * st.f_frsize as u64;
+        Some(available)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::ffi::OsStrExt;
+
+        let wide: Vec<u16> = std::ffi::OsStr::new(path).encode_wide().chain(Some(0)).collect();
+        let mut available = 0u64;
+        // SAFETY: wide is NUL-terminated; the out pointers are valid or null.
+        let ok = unsafe {
+            GetDiskFreeSpaceExW(
+                wide.as_ptr(),
+                &mut available,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if ok != 0 { Some(available) } else { None }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+#[allow(dead_code)]
+fn read_total_memory_kb() -> u64 {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(contents) = std::fs::read_to_string("/proc/meminfo") {
+            for line in contents.lines() {
+                if line.starts_with("MemTotal:")
+                    && let Some(kb_str) = line.split_whitespace().nth(1)
+                    && let Ok(kb) = kb_str.parse::<u64>()
+                {
+                    return kb;
+                }
+            }
+        }
+        warn!("Failed to read /proc/meminfo");
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(output) = Command::new("sysctl")
+            .arg("-n")
+            .arg("hw.memsize")
+            .output()
+        {
+            if output.status.success() {
+                if let Ok(value) = String::from_utf8(output.stdout) {
+                    if let Ok(bytes) = value.trim().parse::<u64>() {
+                        return bytes / 1024; // Convert to KB
+                    } else {
+                        warn!("Failed to parse memory size as integer: '{}'", value.trim());
+                    }
+                } else {
+                    warn!("Non-UTF8 output from sysctl hw.memsize");
+                }
+            } else {
+                warn!("sysctl call failed with status: {:?}", output.status);
+            }
+        } else {
+            warn!("Failed to run sysctl command");
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        match memory_status() {
+            Some(status) if status.ullTotalPhys > 0 => {
+                return status.ullTotalPhys / 1024; // Convert to KB
+            }
+            Some(_) => warn!("GlobalMemoryStatusEx reported 0 bytes of physical memory"),
+            None => warn!("GlobalMemoryStatusEx failed: {}", std::io::Error::last_os_error()),
+        }
+    }
+
+    warn!("Unable to detect total memory, using fallback 1GB");
+    1024 * 1024 // Fallback: 1 GB in KB
+}
+
+/// Memory the OS reports as available for new allocations, in KB.
+fn read_available_memory_kb() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let contents = std::fs::read_to_string("/proc/meminfo").ok()?;
+        for line in contents.lines() {
+            if line.starts_with("MemAvailable:") {
+                return line.split_whitespace().nth(1)?.parse().ok();
+            }
+        }
+        None
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = Command::new("sysctl")
+            .arg("-n")
+            .arg("vm.page_free_count")
+            .arg("hw.pagesize")
+            .output()
+            .ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        let mut values = text.lines().map(|l| l.trim().parse::<u64>());
+        let pages = values.next()?.ok()?;
+        let page_size = values.next()?.ok()?;
+        Some(pages * page_size / 1024)
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        memory_status().map(|status| status.ullAvailPhys / 1024)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Set while available memory is below the `--io-pause-below` threshold.
+/// I/O workers consult it before every op and back off while it is set.
+static MEMORY_PRESSURE: AtomicBool = AtomicBool::new(false);
+
+/// Polls available memory and drives `MEMORY_PRESSURE` until dropped.
+struct MemoryPressureMonitor {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl MemoryPressureMonitor {
+    fn start(threshold_mb: u64) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let threshold_kb = threshold_mb * 1024;
+        // Resume only once we are 10% above the threshold to avoid flapping.
+        let resume_kb = threshold_kb + threshold_kb / 10;
+
+        let handle = spawn_named("stressr-mem-monitor".to_string(), move || {
+            while !stop_flag.load(Ordering::Relaxed) {
+                if let Some(avail_kb) = read_available_memory_kb() {
+                    let paused = MEMORY_PRESSURE.load(Ordering::Relaxed);
+                    if !paused && avail_kb < threshold_kb {
+                        warn!("Memory pressure: {} MB available, pausing I/O", avail_kb / 1024);
+                        MEMORY_PRESSURE.store(true, Ordering::Relaxed);
+                    } else if paused && avail_kb >= resume_kb {
+                        info!(
+                            "Memory recovered: {} MB available, resuming I/O",
+                            avail_kb / 1024
+                        );
+                        MEMORY_PRESSURE.store(false, Ordering::Relaxed);
+                    }
+                }
+                thread::sleep(Duration::from_millis(250));
+            }
+            MEMORY_PRESSURE.store(false, Ordering::Relaxed);
+        });
+
+        MemoryPressureMonitor {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for MemoryPressureMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Live counters for `--progress`, `--stats-addr` and `--tui`, bumped by the
+/// stressors with relaxed atomics. Under `--io-processes` the I/O counters
+/// are fed by the workers' `ProgressForwarder` lines.
+static PROGRESS_IO_BYTES: AtomicU64 = AtomicU64::new(0);
+static PROGRESS_IO_OPS: AtomicU64 = AtomicU64::new(0);
+static PROGRESS_CPU_THREADS: AtomicU64 = AtomicU64::new(0);
+static PROGRESS_CPU_BUSY_NANOS: AtomicU64 = AtomicU64::new(0);
+static PROGRESS_MEMORY_MB: AtomicU64 = AtomicU64::new(0);
+
+/// Prints a snapshot of the live counters every interval until dropped.
+struct ProgressReporter {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    fn start(interval: Duration, total: Duration, precision: usize) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+
+        let handle = spawn_named("stressr-progress".to_string(), move || {
+            let start = Instant::now();
+            let mut last = start;
+            let mut last_bytes = PROGRESS_IO_BYTES.load(Ordering::Relaxed);
+            while !stop_flag.load(Ordering::Relaxed) && running() {
+                thread::sleep(Duration::from_millis(100));
+                if last.elapsed() < interval {
+                    continue;
+                }
+                let bytes = PROGRESS_IO_BYTES.load(Ordering::Relaxed);
+                let secs = last.elapsed().as_secs_f64();
+                let mbps = (bytes - last_bytes) as f64 / secs / 1024.0 / 1024.0;
+                last = Instant::now();
+                last_bytes = bytes;
+                info!("{}", progress_line(start.elapsed(), total, mbps, precision));
+            }
+        });
+
+        ProgressReporter {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Length of the window the `/metrics` I/O rates are taken over.
+const STATS_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// The live I/O counters sampled every `STATS_RATE_WINDOW`, and the MB/s and
+/// ops/s over the last complete window.
+struct IoWindow {
+    since: Instant,
+    bytes: u64,
+    ops: u64,
+    rates: (f64, f64),
+}
+
+impl IoWindow {
+    fn start() -> Self {
+        IoWindow {
+            since: Instant::now(),
+            bytes: PROGRESS_IO_BYTES.load(Ordering::Relaxed),
+            ops: PROGRESS_IO_OPS.load(Ordering::Relaxed),
+            rates: (0.0, 0.0),
+        }
+    }
+
+    /// Closes the window once it is `STATS_RATE_WINDOW` long and opens the next.
+    fn advance(&mut self) {
+        let secs = self.since.elapsed().as_secs_f64();
+        if secs < STATS_RATE_WINDOW.as_secs_f64() {
+            return;
+        }
+        let next = IoWindow::start();
+        self.rates = (
+            next.bytes.saturating_sub(self.bytes) as f64 / secs / 1024.0 / 1024.0,
+            next.ops.saturating_sub(self.ops) as f64 / secs,
+        );
+        *self = IoWindow { rates: self.rates, ..next };
+    }
+}
+
+/// Serves the live counters in the Prometheus text format at `/metrics`
+/// until dropped or interrupted.
+struct StatsServer {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl StatsServer {
+    /// Every sample carries `labels`, the run's `--label` tags.
+    fn start(addr: &str, labels: &[(String, String)]) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        info!("Stats: serving metrics on http://{}/metrics", listener.local_addr()?);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let labels = prometheus_labels(labels);
+        let handle = spawn_named("stressr-stats".to_string(), move || {
+            let start = Instant::now();
+            let mut window = IoWindow::start();
+            while !stop_flag.load(Ordering::Relaxed) && running() {
+                window.advance();
+                match listener.accept() {
+                    Ok((stream, addr)) => {
+                        let sample = (start.elapsed(), window.rates);
+                        if let Err(e) = serve_stats_request(stream, sample, &labels) {
+                            debug!("Stats: request from {} failed: {}", addr, e);
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(NET_POLL),
+                    Err(e) => {
+                        warn!("Stats: accept failed: {}", e);
+                        thread::sleep(NET_POLL);
+                    }
+                }
+            }
+        });
+
+        Ok(StatsServer {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for StatsServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Answers one HTTP request: the metrics for `GET /metrics`, 404 otherwise.
+/// `sample` is the time since the server started and the current I/O rates.
+fn serve_stats_request(
+    stream: std::net::TcpStream,
+    sample: (Duration, (f64, f64)),
+    labels: &str,
+) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers so the client sees a clean close.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics_text(sample.0, sample.1, labels)),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.0 {}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    (&stream).write_all(response.as_bytes())
+}
+
+/// The live counters in the Prometheus text exposition format, each sample
+/// with the label set `labels`. `rates` are the I/O MB/s and ops/s over the
+/// last `STATS_RATE_WINDOW`.
+fn metrics_text(elapsed: Duration, rates: (f64, f64), labels: &str) -> String {
+    let secs = elapsed.as_secs_f64();
+    let bytes = PROGRESS_IO_BYTES.load(Ordering::Relaxed);
+    let ops = PROGRESS_IO_OPS.load(Ordering::Relaxed);
+    let (mbps, ops_per_sec) = rates;
+    let metrics: [(&str, &str, &str, String); 7] = [
+        (
+            "stressr_io_bytes_total",
+            "counter",
+            "Bytes read and written by I/O workers",
+            bytes.to_string(),
+        ),
+        (
+            "stressr_io_ops_total",
+            "counter",
+            "Read and write operations by I/O workers",
+            ops.to_string(),
+        ),
+        (
+            "stressr_io_mbps",
+            "gauge",
+            "Aggregate I/O throughput over the last second in MB/s",
+            mbps.to_string(),
+        ),
+        (
+            "stressr_io_ops_per_second",
+            "gauge",
+            "Aggregate I/O operations per second over the last second",
+            ops_per_sec.to_string(),
+        ),
+        (
+            "stressr_memory_allocated_bytes",
+            "gauge",
+            "Memory currently held by the memory stressor",
+            (PROGRESS_MEMORY_MB.load(Ordering::Relaxed) * 1024 * 1024).to_string(),
+        ),
+        (
+            "stressr_cpu_threads",
+            "gauge",
+            "Active CPU stress threads",
+            PROGRESS_CPU_THREADS.load(Ordering::Relaxed).to_string(),
+        ),
+        (
+            "stressr_elapsed_seconds",
+            "gauge",
+            "Seconds since the stats server started",
+            secs.to_string(),
+        ),
+    ];
+
+    let mut text = String::new();
+    for (name, kind, help, value) in metrics {
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+        text.push_str(&format!("{}{} {}\n", name, labels, value));
+    }
+    text
+}
+
+/// `--tui` terminal control: switch to the alternate screen and hide the
+/// cursor, and the reverse.
+const TUI_ENTER: &str = "\x1b[?1049h\x1b[?25l";
+const TUI_LEAVE: &str = "\x1b[?25h\x1b[?1049l";
+
+/// Set while the dashboard owns the screen, so a second Ctrl-C can restore
+/// the terminal before exiting.
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+const TUI_BAR_WIDTH: usize = 40;
+/// Seconds of I/O throughput in the sparkline.
+const TUI_HISTORY: usize = 60;
+/// Recent output lines shown under the gauges.
+const TUI_MESSAGES: usize = 10;
+
+/// Full-screen `--tui` dashboard, redrawn every second from the live
+/// counters until dropped. Output printed meanwhile is held, shown in the
+/// dashboard, and printed for real once the terminal is restored.
+struct TuiDashboard {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl TuiDashboard {
+    fn start(precision: usize) -> Self {
+        log::hold();
+        TUI_ACTIVE.store(true, Ordering::Relaxed);
+        let mut out = std::io::stdout();
+        let _ = write!(out, "{}", TUI_ENTER).and_then(|_| out.flush());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = spawn_named("stressr-tui".to_string(), move || {
+            let total_memory_mb = read_total_memory_kb() / 1024;
+            let start = Instant::now();
+            let mut last = start;
+            let mut last_bytes = PROGRESS_IO_BYTES.load(Ordering::Relaxed);
+            let mut last_busy = PROGRESS_CPU_BUSY_NANOS.load(Ordering::Relaxed);
+            let mut last_times = read_cpu_times();
+            let mut history = Vec::new();
+            while !stop_flag.load(Ordering::Relaxed) && running() {
+                thread::sleep(Duration::from_millis(100));
+                if last.elapsed() < Duration::from_secs(1) {
+                    continue;
+                }
+                let secs = last.elapsed().as_secs_f64();
+                last = Instant::now();
+
+                let bytes = PROGRESS_IO_BYTES.load(Ordering::Relaxed);
+                let busy = PROGRESS_CPU_BUSY_NANOS.load(Ordering::Relaxed);
+                let times = read_cpu_times();
+                let cpu_threads = PROGRESS_CPU_THREADS.load(Ordering::Relaxed);
+                let sample = TuiSample {
+                    elapsed: start.elapsed(),
+                    cpu_threads,
+                    cpu_percent: if cpu_threads > 0 {
+                        (busy - last_busy) as f64 / 1e9 / secs / cpu_threads as f64 * 100.0
+                    } else {
+                        0.0
+                    },
+                    system_cpu_percent: last_times.zip(times).map(|(a, b)| cpu_utilization(a, b)),
+                    memory_mb: PROGRESS_MEMORY_MB.load(Ordering::Relaxed),
+                    total_memory_mb,
+                    io_mbps: (bytes - last_bytes) as f64 / secs / 1024.0 / 1024.0,
+                };
+                (last_bytes, last_busy, last_times) = (bytes, busy, times);
+                if history.len() == TUI_HISTORY {
+                    history.remove(0);
+                }
+                history.push(sample.io_mbps);
+
+                let frame = tui_frame(&sample, &history, &log::recent(TUI_MESSAGES), precision);
+                let mut out = std::io::stdout().lock();
+                let _ = out.write_all(frame.as_bytes()).and_then(|_| out.flush());
+            }
+        });
+
+        TuiDashboard {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for TuiDashboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        let mut out = std::io::stdout();
+        let _ = write!(out, "{}", TUI_LEAVE).and_then(|_| out.flush());
+        TUI_ACTIVE.store(false, Ordering::Relaxed);
+        log::release();
+    }
+}
+
+/// One second of the live counters, as the dashboard shows them.
+struct TuiSample {
+    elapsed: Duration,
+    cpu_threads: u64,
+    /// Average load of the CPU stress threads.
+    cpu_percent: f64,
+    /// Whole-machine utilization, where `/proc/stat` is available.
+    system_cpu_percent: Option<f64>,
+    memory_mb: u64,
+    total_memory_mb: u64,
+    io_mbps: f64,
+}
+
+/// `[████░░░░]` filled to `fraction` (clamped to 0..=1).
+fn tui_gauge(fraction: f64) -> String {
+    let filled = (fraction.clamp(0.0, 1.0) * TUI_BAR_WIDTH as f64).round() as usize;
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(TUI_BAR_WIDTH - filled))
+}
+
+/// One block character per value, scaled to the largest.
+fn tui_sparkline(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|&v| if max > 0.0 { BLOCKS[((v / max) * 7.0).round() as usize] } else { BLOCKS[0] })
+        .collect()
+}
+
+/// A whole dashboard screen: cursor home, each line cleared to its end, and
+/// the rest of the screen cleared below.
+fn tui_frame(sample: &TuiSample, history: &[f64], messages: &[String], precision: usize) -> String {
+    let mut lines = vec![
+        format!(" stressr  {}s elapsed  (Ctrl-C to stop)", sample.elapsed.as_secs()),
+        String::new(),
+        format!(
+            " CPU threads  {} {:5.1}%  {} threads",
+            tui_gauge(sample.cpu_percent / 100.0),
+            sample.cpu_percent,
+            sample.cpu_threads
+        ),
+    ];
+    if let Some(system) = sample.system_cpu_percent {
+        lines.push(format!(" System CPU   {} {:5.1}%", tui_gauge(system / 100.0), system));
+    }
+    let memory = sample.memory_mb as f64 / sample.total_memory_mb.max(1) as f64;
+    lines.push(format!(
+        " Memory       {} {} MB of {} MB",
+        tui_gauge(memory),
+        sample.memory_mb,
+        sample.total_memory_mb
+    ));
+    lines.push(format!(
+        " I/O          {}  {}",
+        format_rate(sample.io_mbps, precision),
+        tui_sparkline(history)
+    ));
+    lines.push(String::new());
+    lines.extend(messages.iter().map(|m| format!(" {}", m)));
+
+    let mut frame = String::from("\x1b[H");
+    for line in lines {
+        frame.push_str(&line);
+        frame.push_str("\x1b[K\r\n");
+    }
+    frame.push_str("\x1b[J");
+    frame
+}
+
+/// One `--progress` snapshot, as text or as a JSON line under `--output json`.
+fn progress_line(elapsed: Duration, total: Duration, mbps: f64, precision: usize) -> String {
+    let elapsed = elapsed.as_secs_f64().round() as u64;
+    let remaining = total.as_secs().saturating_sub(elapsed);
+    let cpu_threads = PROGRESS_CPU_THREADS.load(Ordering::Relaxed);
+    let memory_mb = PROGRESS_MEMORY_MB.load(Ordering::Relaxed);
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        Json::object(vec![
+            ("elapsed_secs", elapsed.into()),
+            ("remaining_secs", remaining.into()),
+            ("io_mbps", mbps.into()),
+            ("cpu_threads", cpu_threads.into()),
+            ("memory_mb", memory_mb.into()),
+        ])
+        .to_string()
+    } else {
+        format!(
+            "[Progress] {}s elapsed, {}s remaining | I/O {} | CPU {} threads | memory {} MB",
+            elapsed,
+            remaining,
+            format_rate(mbps, precision),
+            cpu_threads,
+            memory_mb
+        )
+    }
+}
+
+/// Cleared by the first Ctrl-C; every stressor loop checks it so workers wind
+/// down and still print their (partial) results.
+static RUNNING: AtomicBool = AtomicBool::new(true);
+
+fn running() -> bool {
+    RUNNING.load(Ordering::Relaxed)
+}
+
+/// Sleeps for `duration`, returning early once the run is interrupted.
+fn sleep_while_running(duration: Duration) {
+    let start = Instant::now();
+    while running() {
+        let left = duration.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(left.min(Duration::from_millis(100)));
+    }
+}
+
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Parses a `--start-at` time of day, `HH:MM` or `HH:MM:SS`, into seconds
+/// after midnight.
+fn parse_clock_time(value: &str) -> Option<u32> {
+    let fields: Vec<&str> = value.split(':').collect();
+    if !(2..=3).contains(&fields.len()) {
+        return None;
+    }
+    let mut secs = 0;
+    for (field, limit) in fields.iter().zip([24, 60, 60]) {
+        let n: u32 = field.parse().ok().filter(|&n| n < limit)?;
+        secs = secs * 60 + n;
+    }
+    Some(if fields.len() == 2 { secs * 60 } else { secs })
+}
+
+/// Seconds after midnight as `HH:MM:SS`.
+fn format_clock_time(secs: u32) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+#[cfg(unix)]
+#[repr(C)]
+struct Tm {
+    // tm_sec through tm_isdst.
+    fields: [std::ffi::c_int; 9],
+    tm_gmtoff: std::ffi::c_long,
+    tm_zone: *const std::ffi::c_char,
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn localtime_r(time: *const std::ffi::c_long, result: *mut Tm) -> *mut Tm;
+}
+
+/// Offset of local time from UTC at `unix_secs`, in seconds east of UTC.
+/// None where the platform's local time zone cannot be read.
+fn local_utc_offset(unix_secs: u64) -> Option<i64> {
+    #[cfg(unix)]
+    {
+        let time = unix_secs as std::ffi::c_long;
+        let mut tm = Tm {
+            fields: [0; 9],
+            tm_gmtoff: 0,
+            tm_zone: std::ptr::null(),
+        };
+        // SAFETY: localtime_r only reads `time` and fills in `tm`.
+        let filled = unsafe { localtime_r(&time, &mut tm) };
+        (!filled.is_null()).then_some(tm.tm_gmtoff as i64)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = unix_secs;
+        None
+    }
+}
+
+/// Seconds from `unix_secs` until the next time local clocks `offset`
+/// seconds east of UTC show `start_at`; 0 if they show it now.
+fn secs_until_clock_time(unix_secs: u64, offset: i64, start_at: u32) -> u64 {
+    let day = SECS_PER_DAY as i64;
+    let now = (unix_secs as i64 + offset).rem_euclid(day);
+    (start_at as i64 - now).rem_euclid(day) as u64
+}
+
+/// Sleeps out `--delay`, then until `--start-at`, logging a countdown.
+/// Returns early if the run is interrupted meanwhile.
+fn wait_for_start(cfg: &Config) {
+    if cfg.delay_secs > 0 {
+        info!("Start: delaying {}s", cfg.delay_secs);
+        countdown(Duration::from_secs(cfg.delay_secs));
+    }
+    let Some(start_at) = cfg.start_at else {
+        return;
+    };
+    if !running() {
+        return;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let offset = local_utc_offset(now.as_secs()).unwrap_or_else(|| {
+        warn!("Start: local time zone unknown, taking --start-at as UTC");
+        0
+    });
+    // Aim at the whole second, not at the fraction already elapsed.
+    let wait = Duration::from_secs(secs_until_clock_time(now.as_secs(), offset, start_at))
+        .saturating_sub(Duration::from_nanos(now.subsec_nanos() as u64));
+    info!(
+        "Start: at {} local time, in {}s",
+        format_clock_time(start_at),
+        wait.as_secs_f64().ceil() as u64
+    );
+    countdown(wait);
+}
+
+/// Sleeps for `duration`, logging the time left every 10s and each of the
+/// last 5s.
+fn countdown(duration: Duration) {
+    let end = Instant::now() + duration;
+    while running() {
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        let secs = left.as_secs_f64().ceil() as u64;
+        let step = left - Duration::from_secs(secs - 1);
+        if secs < duration.as_secs() && (secs <= 5 || secs.is_multiple_of(10)) {
+            info!("Start: {}s", secs);
+        }
+        sleep_while_running(step);
+    }
+}
+
+/// Exit status when a worker or an end-of-run check failed.
+pub const FAILED_EXIT_CODE: i32 = 1;
+
+/// Exit status for an invalid configuration, a failed pre-run check or an
+/// I/O error before the run, when nothing has been stressed yet.
+pub const CONFIG_EXIT_CODE: i32 = 2;
+
+/// Exit status when a worker was abandoned after `--grace`.
+pub const ABANDONED_EXIT_CODE: i32 = 3;
+
+/// Exit status of a run stopped by Ctrl-C (128 + SIGINT).
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Set when any worker fails: an I/O error, a panic, or a worker process that
+/// exits unsuccessfully. The run still reports the others, then returns
+/// `StressError::Failed`.
+static WORKER_FAILED: AtomicBool = AtomicBool::new(false);
+
+/// Joins a stressor thread, recording a panic as a failed worker rather than
+/// propagating it.
+fn join_worker<T>(handle: thread::JoinHandle<T>) -> Option<T> {
+    handle.join().map_err(|_| WORKER_FAILED.store(true, Ordering::Relaxed)).ok()
+}
+
+/// Set when a worker is still running `--grace` after its duration and is
+/// left behind. The run reports the others, then returns
+/// `StressError::Abandoned`.
+static WORKER_ABANDONED: AtomicBool = AtomicBool::new(false);
+
+/// A stressor thread that can be waited for with a deadline, which
+/// `JoinHandle::join` cannot do. The thread holds the sending half of
+/// `done` and hangs it up when it returns or panics.
+struct Worker<T> {
+    label: String,
+    handle: thread::JoinHandle<T>,
+    done: mpsc::Receiver<()>,
+}
+
+impl<T: Send + 'static> Worker<T> {
+    /// `spawn_named` for a worker described as `label` in diagnostics.
+    fn spawn(name: String, label: String, f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (tx, done) = mpsc::channel();
+        let handle = spawn_named(name, move || {
+            let _done: mpsc::Sender<()> = tx;
+            f()
+        });
+        Worker { label, handle, done }
+    }
+
+    /// Waits until `deadline` for the worker to finish; false if it is still
+    /// running then.
+    fn finished_by(&self, deadline: Instant) -> bool {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        !matches!(self.done.recv_timeout(wait), Err(mpsc::RecvTimeoutError::Timeout))
+    }
+
+    /// `join_worker`, giving up at `deadline` on a worker that has not
+    /// finished, which is reported and left running.
+    fn join_by(self, deadline: Option<Instant>) -> Option<T> {
+        if let Some(deadline) = deadline
+            && !self.finished_by(deadline)
+        {
+            error!("{} did not finish within --grace, abandoning it", self.label);
+            WORKER_ABANDONED.store(true, Ordering::Relaxed);
+            return None;
+        }
+        join_worker(self.handle)
+    }
+}
+
+/// When a worker that should run for `secs` from now is abandoned under
+/// `--grace`, or None to wait for ever.
+fn grace_deadline(cfg: &Config, secs: u64) -> Option<Instant> {
+    cfg.grace_secs.map(|grace| Instant::now() + Duration::from_secs(secs + grace))
+}
+
+/// `thread::spawn` under `name`, so the thread is identifiable in panics,
+/// `top -H` and profilers.
+fn spawn_named<T, F>(name: String, f: F) -> thread::JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let os_name = os_thread_name(&name);
+    thread::Builder::new()
+        .name(name)
+        .spawn(move || {
+            set_os_thread_name(&os_name);
+            f()
+        })
+        .expect("failed to spawn thread")
+}
+
+/// Longest thread name Linux keeps (`TASK_COMM_LEN` less the NUL).
+const OS_THREAD_NAME_MAX: usize = 15;
+
+/// `name` cut to fit `OS_THREAD_NAME_MAX`: the `stressr-` prefix goes first,
+/// then the middle, so a trailing worker id survives.
+fn os_thread_name(name: &str) -> String {
+    if name.len() <= OS_THREAD_NAME_MAX {
+        return name.to_string();
+    }
+    let name = name.strip_prefix("stressr-").unwrap_or(name);
+    if name.len() <= OS_THREAD_NAME_MAX {
+        return name.to_string();
+    }
+    let tail = name.rfind('-').map_or("", |i| &name[i..]);
+    let mut head = OS_THREAD_NAME_MAX.saturating_sub(tail.len());
+    while !name.is_char_boundary(head) {
+        head -= 1;
+    }
+    format!("{}{}", &name[..head], tail)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn prctl(option: std::ffi::c_int, ...) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "linux")]
+const PR_SET_NAME: std::ffi::c_int = 15;
+
+/// Names the calling thread system-wide. std already does this for names
+/// that fit; this keeps the worker id of longer ones visible.
+fn set_os_thread_name(name: &str) {
+    #[cfg(target_os = "linux")]
+    if let Ok(name) = std::ffi::CString::new(name) {
+        // SAFETY: PR_SET_NAME reads a NUL-terminated string, which `name` is
+        // and outlives the call; the kernel truncates it to 15 bytes.
+        unsafe {
+            prctl(PR_SET_NAME, name.as_ptr());
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = name;
+}
+
+#[cfg(unix)]
+const SIGINT: std::ffi::c_int = 2;
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn _exit(status: std::ffi::c_int) -> !;
+    fn write(fd: std::ffi::c_int, buf: *const std::ffi::c_void, count: usize) -> isize;
+}
+
+/// First Ctrl-C asks workers to stop; a second one exits immediately, after
+/// restoring the terminal if `--tui` has it. Async-signal-safe: only atomics,
+/// `write` and `_exit`.
+#[cfg(unix)]
+extern "C" fn on_interrupt_signal(_: std::ffi::c_int) {
+    if !RUNNING.swap(false, Ordering::Relaxed) {
+        if TUI_ACTIVE.load(Ordering::Relaxed) {
+            // SAFETY: write(2) is async-signal-safe and reads only the
+            // static escape sequence.
+            unsafe {
+                write(1, TUI_LEAVE.as_ptr().cast(), TUI_LEAVE.len());
+            }
+        }
+        // SAFETY: _exit is async-signal-safe.
+        unsafe { _exit(INTERRUPTED_EXIT_CODE) }
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+}
+
+/// Console control handler; Windows runs it on its own thread.
+#[cfg(target_os = "windows")]
+extern "system" fn on_console_ctrl(_: u32) -> i32 {
+    if !RUNNING.swap(false, Ordering::Relaxed) {
+        if TUI_ACTIVE.load(Ordering::Relaxed) {
+            print!("{}", TUI_LEAVE);
+        }
+        std::process::exit(INTERRUPTED_EXIT_CODE);
+    }
+    1
+}
+
+/// Installs the Ctrl-C handler. Returns false if the platform has none.
+fn install_interrupt_handler() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: the handler is async-signal-safe.
+        unsafe { signal(SIGINT, on_interrupt_signal) != usize::MAX }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // SAFETY: the handler only touches an atomic or exits.
+        unsafe { SetConsoleCtrlHandler(on_console_ctrl, 1) != 0 }
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        false
+    }
+}
+
+/// How many times `--signal-throttle` has halved the load; 0 is full intensity.
+/// CPU workers shift their busy time by it and I/O workers stretch each op.
+static THROTTLE_LEVEL: AtomicU64 = AtomicU64::new(0);
+
+/// Deepest throttle: 1/64 of the configured load.
+const MAX_THROTTLE_LEVEL: u64 = 6;
+
+#[cfg(target_os = "linux")]
+const SIGTSTP: std::ffi::c_int = 20;
+#[cfg(target_os = "linux")]
+const SIGCONT: std::ffi::c_int = 18;
+#[cfg(all(unix, not(target_os = "linux")))]
+const SIGTSTP: std::ffi::c_int = 18;
+#[cfg(all(unix, not(target_os = "linux")))]
+const SIGCONT: std::ffi::c_int = 19;
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn signal(signum: std::ffi::c_int, handler: extern "C" fn(std::ffi::c_int)) -> usize;
+}
+
+/// The throttle level after one more halving, or back at full intensity
+/// when `restore`d, within 0-`MAX_THROTTLE_LEVEL`.
+fn next_throttle_level(level: u64, restore: bool) -> u64 {
+    if restore { 0 } else { (level + 1).min(MAX_THROTTLE_LEVEL) }
+}
+
+/// Async-signal-safe: only touches an atomic.
+#[cfg(unix)]
+extern "C" fn on_throttle_signal(sig: std::ffi::c_int) {
+    let _ = THROTTLE_LEVEL.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |level| {
+        Some(next_throttle_level(level, sig == SIGCONT))
+    });
+}
+
+/// Installs the SIGTSTP (halve) and SIGCONT (restore) handlers. Catching
+/// SIGTSTP means Ctrl-Z no longer suspends the process, hence opt-in.
+fn install_throttle_signals() -> bool {
+    #[cfg(unix)]
+    {
+        // SIG_ERR is (sighandler_t)-1.
+        // SAFETY: the handler is async-signal-safe.
+        unsafe {
+            signal(SIGTSTP, on_throttle_signal) != usize::MAX
+                && signal(SIGCONT, on_throttle_signal) != usize::MAX
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Live CPU target in percent: set from `--cpu-percent` (or
+/// `--cpu-system-percent`) when the CPU stressor starts and moved by
+/// `--signal-adjust`.
+static CPU_TARGET_PERCENT: AtomicU64 = AtomicU64::new(0);
+
+/// Percentage points one SIGUSR1 or SIGUSR2 moves the CPU target by.
+const CPU_ADJUST_STEP: u64 = 10;
+
+/// How often the CPU stressor checks whether the target was adjusted.
+const CPU_ADJUST_POLL: Duration = Duration::from_millis(100);
+
+#[cfg(target_os = "linux")]
+const SIGUSR1: std::ffi::c_int = 10;
+#[cfg(target_os = "linux")]
+const SIGUSR2: std::ffi::c_int = 12;
+#[cfg(all(unix, not(target_os = "linux")))]
+const SIGUSR1: std::ffi::c_int = 30;
+#[cfg(all(unix, not(target_os = "linux")))]
+const SIGUSR2: std::ffi::c_int = 31;
+
+/// The CPU target one step up (`raise`) or down from `percent`, within 0-100.
+fn adjusted_cpu_percent(percent: u64, raise: bool) -> u64 {
+    if raise {
+        (percent + CPU_ADJUST_STEP).min(100)
+    } else {
+        percent.saturating_sub(CPU_ADJUST_STEP)
+    }
+}
+
+/// Async-signal-safe: only touches an atomic.
+#[cfg(unix)]
+extern "C" fn on_adjust_signal(sig: std::ffi::c_int) {
+    let _ = CPU_TARGET_PERCENT.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |percent| {
+        Some(adjusted_cpu_percent(percent, sig == SIGUSR1))
+    });
+}
+
+/// Installs the SIGUSR1 (raise) and SIGUSR2 (lower) handlers. Both signals
+/// terminate the process by default, hence opt-in.
+fn install_adjust_signals() -> bool {
+    #[cfg(unix)]
+    {
+        // SAFETY: the handler is async-signal-safe.
+        unsafe {
+            signal(SIGUSR1, on_adjust_signal) != usize::MAX
+                && signal(SIGUSR2, on_adjust_signal) != usize::MAX
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+/// Sleeps long enough after an I/O op that took `op_time` to divide the
+/// worker's rate by the current throttle factor.
+fn throttle_io(op_time: Duration) {
+    let level = THROTTLE_LEVEL.load(Ordering::Relaxed);
+    if level > 0 {
+        thread::sleep(op_time * ((1u32 << level) - 1));
+    }
+}
+
+
+/// What the memory stressor held: the target, what it allocated and how much
+/// of that the OS reports as resident.
+#[derive(Debug, Default)]
+pub struct MemoryStats {
+    pub threads: usize,
+    pub requested_mb: u64,
+    pub allocated_mb: u64,
+    /// Resident set size after the touch pass, where the platform reports it.
+    pub rss_mb: Option<u64>,
+    /// Bytes read plus written by `--memory-threads` streaming.
+    pub streamed_bytes: u64,
+    pub elapsed: Duration,
+    /// Memory backed by huge pages under `--memory-hugepages`, where known.
+    pub huge_pages_mb: Option<u64>,
+    /// The `--numa-node` the blocks were bound to.
+    pub numa_node: Option<usize>,
+    /// Memory in use on that node after allocation, all processes included.
+    pub numa_used_mb: Option<u64>,
+    /// Bytes locked into RAM under `--memory-mlock`.
+    pub locked_bytes: Option<u64>,
+}
+
+impl MemoryStats {
+    /// Streaming bandwidth of one thread, in GB/s.
+    pub fn gbps_per_thread(&self) -> f64 {
+        let window = self.elapsed.as_secs_f64() * self.threads as f64;
+        if window > 0.0 { self.streamed_bytes as f64 / window / 1e9 } else { 0.0 }
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("threads", self.threads.into()),
+            ("requested_mb", self.requested_mb.into()),
+            ("allocated_mb", self.allocated_mb.into()),
+            ("rss_mb", self.rss_mb.into()),
+            ("streamed_bytes", self.streamed_bytes.into()),
+            ("gbps_per_thread", self.gbps_per_thread().into()),
+            ("huge_pages_mb", self.huge_pages_mb.into()),
+            ("numa_node", self.numa_node.into()),
+            ("numa_used_mb", self.numa_used_mb.into()),
+            ("locked_bytes", self.locked_bytes.into()),
+        ])
+    }
+}
+
+/// Stride of the re-touch pass: one write per page keeps every page resident.
+const MEMORY_PAGE: usize = 4096;
+
+/// How often held blocks are re-touched so the kernel cannot reclaim them.
+const MEMORY_RETOUCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Fills a block with a non-zero pattern. Zeroed allocations map the shared
+/// zero page and never fault in, so they would not raise RSS.
+fn fill_memory_block(block: &mut [u8], rng: &mut Rng) {
+    for word in block.chunks_exact_mut(8) {
+        word.copy_from_slice(&(rng.next_u64() | 1).to_le_bytes());
+    }
+}
+
+/// Writes one byte per page of every block.
+fn touch_memory_blocks(blocks: &mut [MemoryBlock]) {
+    for block in blocks.iter_mut() {
+        for byte in block.iter_mut().step_by(MEMORY_PAGE) {
+            *byte = byte.wrapping_add(1);
+        }
+        std::hint::black_box(&mut *block);
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn sysconf(name: std::ffi::c_int) -> std::ffi::c_long;
+}
+
+#[cfg(target_os = "linux")]
+const _SC_PAGESIZE: std::ffi::c_int = 30;
+
+/// Resident set size of this process in KB, from `/proc/self/statm`.
+fn read_rss_kb() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let contents = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = contents.split_whitespace().nth(1)?.parse().ok()?;
+        // SAFETY: sysconf only reads a system constant.
+        let page_size = unsafe { sysconf(_SC_PAGESIZE) };
+        (page_size > 0).then(|| pages * page_size as u64 / 1024)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// The `--memory-percent` target in KB, taken from total or available RAM
+/// per `--memory-use`. Falls back to total RAM when available is unknown.
+fn memory_target_kb(cfg: &Config) -> u64 {
+    let base_kb = match cfg.memory_use {
+        MemoryBase::Total => read_total_memory_kb(),
+        MemoryBase::Available => read_available_memory_kb().unwrap_or_else(|| {
+            warn!("Memory: available memory unknown, sizing against total RAM");
+            read_total_memory_kb()
+        }),
+    };
+    base_kb * cfg.memory_percent / 100
+}
+
+const MEMORY_BLOCK: usize = 1024 * 1024;
+
+/// Size of the blocks under `--memory-hugepages`: one x86-64/arm64 huge page.
+const HUGE_PAGE: usize = 2 * 1024 * 1024;
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn mmap(
+        addr: *mut std::ffi::c_void,
+        len: usize,
+        prot: std::ffi::c_int,
+        flags: std::ffi::c_int,
+        fd: std::ffi::c_int,
+        offset: i64,
+    ) -> *mut std::ffi::c_void;
+    fn munmap(addr: *mut std::ffi::c_void, len: usize) -> std::ffi::c_int;
+    fn madvise(addr: *mut std::ffi::c_void, len: usize, advice: std::ffi::c_int)
+    -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "linux")]
+const PROT_READ_WRITE: std::ffi::c_int = 0x1 | 0x2;
+#[cfg(target_os = "linux")]
+const MAP_PRIVATE_ANONYMOUS: std::ffi::c_int = 0x02 | 0x20;
+#[cfg(target_os = "linux")]
+const MAP_HUGETLB: std::ffi::c_int = 0x40000;
+#[cfg(target_os = "linux")]
+const MADV_HUGEPAGE: std::ffi::c_int = 14;
+
+/// An anonymous `MAP_HUGETLB` mapping, unmapped on drop.
+#[cfg(target_os = "linux")]
+struct HugeMapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+#[cfg(target_os = "linux")]
+impl HugeMapping {
+    fn new(len: usize) -> std::io::Result<Self> {
+        // SAFETY: an anonymous private mapping touches no existing memory;
+        // the result is checked against MAP_FAILED before use.
+        let ptr = unsafe {
+            mmap(
+                std::ptr::null_mut(),
+                len,
+                PROT_READ_WRITE,
+                MAP_PRIVATE_ANONYMOUS | MAP_HUGETLB,
+                -1,
+                0,
+            )
+        };
+        if ptr as isize == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(HugeMapping { ptr: ptr.cast(), len })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for HugeMapping {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` are exactly what mmap returned and asked for.
+        unsafe {
+            munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// A huge-page-sized buffer aligned to a huge page, so transparent huge
+/// pages can back it with exactly one.
+#[cfg(target_os = "linux")]
+#[repr(C, align(2097152))]
+struct HugePageBuf([u8; HUGE_PAGE]);
+
+/// Allocates a zeroed `HugePageBuf` advised with `MADV_HUGEPAGE` before its
+/// pages are first touched. The advice is best effort: THP may be disabled.
+#[cfg(target_os = "linux")]
+fn alloc_transparent_buf() -> Option<Vec<HugePageBuf>> {
+    let mut buf: Vec<HugePageBuf> = Vec::new();
+    buf.try_reserve_exact(1).ok()?;
+    let ptr = buf.as_mut_ptr();
+    // SAFETY: `ptr` is the reserved, page-aligned HUGE_PAGE bytes of `buf`;
+    // madvise only changes how they are backed, and all-zero bytes are a
+    // valid HugePageBuf, so the length can cover them once written.
+    unsafe {
+        if madvise(ptr.cast(), HUGE_PAGE, MADV_HUGEPAGE) != 0 {
+            debug!("Memory: madvise(MADV_HUGEPAGE) failed: {}", std::io::Error::last_os_error());
+        }
+        ptr.write_bytes(0, 1);
+        buf.set_len(1);
+    }
+    Some(buf)
+}
+
+/// One memory stress block: a heap buffer or, on Linux, a huge page backed
+/// one.
+enum MemoryBlock {
+    Heap(Vec<u8>),
+    #[cfg(target_os = "linux")]
+    Transparent(Vec<HugePageBuf>),
+    #[cfg(target_os = "linux")]
+    Huge(HugeMapping),
+}
+
+impl MemoryBlock {
+    /// Size in whole MB (rounded up), as counted in `PROGRESS_MEMORY_MB`.
+    fn mb(&self) -> u64 {
+        self.len().div_ceil(MEMORY_BLOCK) as u64
+    }
+}
+
+impl std::ops::Deref for MemoryBlock {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            MemoryBlock::Heap(buf) => buf,
+            #[cfg(target_os = "linux")]
+            MemoryBlock::Transparent(buf) => &buf[0].0,
+            // SAFETY: the mapping is `len` readable bytes for our lifetime.
+            #[cfg(target_os = "linux")]
+            MemoryBlock::Huge(map) => unsafe { std::slice::from_raw_parts(map.ptr, map.len) },
+        }
+    }
+}
+
+impl std::ops::DerefMut for MemoryBlock {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            MemoryBlock::Heap(buf) => buf,
+            #[cfg(target_os = "linux")]
+            MemoryBlock::Transparent(buf) => &mut buf[0].0,
+            // SAFETY: as for Deref, and `&mut self` makes the access unique.
+            #[cfg(target_os = "linux")]
+            MemoryBlock::Huge(map) => unsafe {
+                std::slice::from_raw_parts_mut(map.ptr, map.len)
+            },
+        }
+    }
+}
+
+/// How the memory stress blocks are backed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PageBacking {
+    /// Ordinary heap allocations in 4 KB pages.
+    Normal,
+    /// Reserved huge pages from `MAP_HUGETLB`.
+    HugeTlb,
+    /// Aligned heap buffers advised with `MADV_HUGEPAGE`, left to THP.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Transparent,
+}
+
+/// Hands out memory stress blocks with the selected page backing. Falls
+/// back from `MAP_HUGETLB` to `MADV_HUGEPAGE` once the huge page pool runs
+/// dry.
+#[derive(Clone)]
+struct BlockAllocator {
+    backing: PageBacking,
+}
+
+impl BlockAllocator {
+    fn new(cfg: &Config) -> Self {
+        let backing = if !cfg.memory_hugepages {
+            PageBacking::Normal
+        } else if cfg!(target_os = "linux") {
+            PageBacking::HugeTlb
+        } else {
+            warn!("Memory: --memory-hugepages is only supported on Linux; using normal pages");
+            PageBacking::Normal
+        };
+        BlockAllocator { backing }
+    }
+
+    /// Allocates and fills one block, or `None` if the allocator refuses.
+    fn alloc(&mut self, rng: &mut Rng) -> Option<MemoryBlock> {
+        let mut block = self.alloc_zeroed()?;
+        fill_memory_block(&mut block, rng);
+        Some(block)
+    }
+
+    fn alloc_zeroed(&mut self) -> Option<MemoryBlock> {
+        #[cfg(target_os = "linux")]
+        {
+            if self.backing == PageBacking::HugeTlb {
+                match HugeMapping::new(HUGE_PAGE) {
+                    Ok(map) => return Some(MemoryBlock::Huge(map)),
+                    Err(e) => {
+                        warn!(
+                            "Memory: MAP_HUGETLB allocation failed ({}); falling back to \
+                             madvise(MADV_HUGEPAGE)",
+                            e
+                        );
+                        self.backing = PageBacking::Transparent;
+                    }
+                }
+            }
+            if self.backing == PageBacking::Transparent {
+                return alloc_transparent_buf().map(MemoryBlock::Transparent);
+            }
+        }
+
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(MEMORY_BLOCK).ok()?;
+        buf.resize(MEMORY_BLOCK, 0);
+        Some(MemoryBlock::Heap(buf))
+    }
+}
+
+/// Transparent huge pages mapped by this process in MB, from
+/// `/proc/self/smaps_rollup`. Counts every anonymous mapping, not only the
+/// memory stress blocks.
+fn read_anon_huge_pages_mb() -> Option<u64> {
+    let contents = std::fs::read_to_string("/proc/self/smaps_rollup").ok()?;
+    let line = contents.lines().find(|l| l.starts_with("AnonHugePages:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024)
+}
+
+/// Allocates up to `target_kb` in blocks, stopping early with a warning if
+/// the allocator refuses.
+fn alloc_memory_blocks(
+    target_kb: u64,
+    allocator: &mut BlockAllocator,
+    rng: &mut Rng,
+) -> Vec<MemoryBlock> {
+    let mut blocks = Vec::new();
+    let mut allocated_mb = 0;
+    while allocated_mb * 1024 < target_kb && running() {
+        let block = blocks.try_reserve(1).ok().and_then(|_| allocator.alloc(rng));
+        let block = match block {
+            Some(block) => block,
+            None => {
+                warn!(
+                    "Memory: allocation failed after {} MB; holding what was allocated",
+                    allocated_mb
+                );
+                break;
+            }
+        };
+        allocated_mb += block.mb();
+        PROGRESS_MEMORY_MB.fetch_add(block.mb(), Ordering::Relaxed);
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Total size of `blocks` in MB.
+fn memory_blocks_mb(blocks: &[MemoryBlock]) -> u64 {
+    blocks.iter().map(MemoryBlock::mb).sum()
+}
+
+#[cfg(unix)]
+unsafe extern "C" {
+    fn mlock(addr: *const std::ffi::c_void, len: usize) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "windows")]
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn VirtualLock(addr: *mut std::ffi::c_void, size: usize) -> i32;
+    fn SetProcessWorkingSetSize(process: *mut std::ffi::c_void, min: usize, max: usize) -> i32;
+}
+
+/// Working set headroom on top of the locked blocks, for the rest of the
+/// process.
+#[cfg(target_os = "windows")]
+const LOCK_WORKING_SET_SLACK: usize = 64 * 1024 * 1024;
+
+/// Grows the working set to hold `bytes` of locked memory: Windows only lets
+/// a process lock pages that fit its minimum working set.
+fn reserve_lockable_memory(bytes: u64) {
+    #[cfg(target_os = "windows")]
+    {
+        let min = bytes as usize + LOCK_WORKING_SET_SLACK;
+        // SAFETY: GetCurrentProcess returns a pseudo-handle needing no
+        // cleanup, and this only changes our own working set limits.
+        if unsafe { SetProcessWorkingSetSize(GetCurrentProcess(), min, min * 2) } == 0 {
+            debug!("Memory: cannot grow the working set: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    let _ = bytes;
+}
+
+/// Locks `block` into RAM under `--memory-mlock`.
+fn lock_memory(block: &mut [u8]) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        // SAFETY: the range is one live buffer; mlock only pins its pages.
+        if unsafe { mlock(block.as_ptr().cast(), block.len()) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        // SAFETY: as for mlock; VirtualLock only pins the buffer's pages.
+        if unsafe { VirtualLock(block.as_mut_ptr().cast(), block.len()) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        let _ = block;
+        Err(std::io::Error::new(ErrorKind::Unsupported, "not supported on this platform"))
+    }
+}
+
+/// How to lift the limit that usually stops `--memory-mlock` short.
+fn memory_lock_hint() -> String {
+    #[cfg(unix)]
+    {
+        match get_rlimit(RLIMIT_MEMLOCK) {
+            Some((soft, _)) if soft != u64::MAX => format!(
+                "; RLIMIT_MEMLOCK allows {} KB, raise it with `ulimit -l unlimited` \
+                 (memlock in /etc/security/limits.conf) or run as root",
+                soft / 1024
+            ),
+            _ => String::new(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        String::new()
+    }
+}
+
+/// Reads and rewrites every byte of every block once. Returns bytes moved
+/// (each byte is both read and written).
+fn stream_memory_blocks(blocks: &mut [MemoryBlock]) -> u64 {
+    let mut bytes = 0;
+    for block in blocks.iter_mut() {
+        for byte in block.iter_mut() {
+            *byte = byte.wrapping_add(1);
+        }
+        std::hint::black_box(&mut *block);
+        bytes += 2 * block.len() as u64;
+    }
+    bytes
+}
+
+/// Sleep between steps of `--memory-free`; blocks are dropped at most this
+/// late.
+const MEMORY_FREE_STEP: Duration = Duration::from_millis(100);
+
+/// Drops `blocks` one by one at an even rate so the last goes when `duration`
+/// is up, re-touching the rest every `MEMORY_RETOUCH_INTERVAL` so only the
+/// dropped ones leave memory.
+fn free_memory_blocks(blocks: &mut Vec<MemoryBlock>, duration: Duration) {
+    let total = blocks.len();
+    let start = Instant::now();
+    let mut touched = start;
+    while !blocks.is_empty() && running() {
+        let done = start.elapsed().as_secs_f64() / duration.as_secs_f64();
+        let keep = if done < 1.0 { total - (total as f64 * done) as usize } else { 0 };
+        while blocks.len() > keep {
+            if let Some(block) = blocks.pop() {
+                PROGRESS_MEMORY_MB.fetch_sub(block.mb(), Ordering::Relaxed);
+            }
+        }
+        if touched.elapsed() >= MEMORY_RETOUCH_INTERVAL {
+            touch_memory_blocks(blocks);
+            touched = Instant::now();
+        }
+        thread::sleep(MEMORY_FREE_STEP);
+    }
+}
+
+/// Keeps `blocks` resident until `duration` is up: a single thread re-touches
+/// one byte per page every `MEMORY_RETOUCH_INTERVAL`; with `stream` set it
+/// sweeps the whole region back to back instead. Returns bytes streamed.
+fn hold_memory_blocks(blocks: &mut [MemoryBlock], stream: bool, duration: Duration) -> u64 {
+    let start = Instant::now();
+    let mut streamed = 0;
+    while running() {
+        let left = duration.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            break;
+        }
+        if stream && !blocks.is_empty() {
+            streamed += stream_memory_blocks(blocks);
+        } else {
+            thread::sleep(left.min(MEMORY_RETOUCH_INTERVAL));
+            touch_memory_blocks(blocks);
+        }
+    }
+    streamed
+}
+
+/// Allocates and holds `--memory-percent` of memory, then reports it. Fails
+/// when the memory size cannot be read to size the target against.
+pub fn stress_memory(cfg: &Config) -> Result<MemoryStats, StressError> {
+    let hold = Duration::from_secs(cfg.memory_hold_secs.unwrap_or(cfg.duration_secs));
+    let free = cfg.memory_free_secs.map(Duration::from_secs);
+    let target_kb = memory_target_kb(cfg);
+    if target_kb == 0 && cfg.memory_percent > 0 {
+        return Err(StressError::Unsupported(
+            "cannot read the memory size to apply --memory-percent to".to_string(),
+        ));
+    }
+    let threads = cfg.memory_threads.max(1);
+    let stream = threads > 1;
+
+    info!(
+        "Memory: Allocating ~{} MB ({}% of {} memory) on {} thread(s)",
+        target_kb / 1024,
+        cfg.memory_percent,
+        cfg.memory_use.name(),
+        threads
+    );
+
+    // Workers allocate their share, then wait here so RSS is read once
+    // everything is in place and the hold starts together.
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let allocated_mb = Arc::new(AtomicU64::new(0));
+    let hugetlb_mb = Arc::new(AtomicU64::new(0));
+    let allocator = BlockAllocator::new(cfg);
+    let numa_node = cfg.numa_node;
+    let numa_bound = Arc::new(AtomicBool::new(numa_node.is_some()));
+    let share_kb = target_kb.div_ceil(threads as u64);
+    let seed = cfg.seed;
+    let mlock = cfg.memory_mlock;
+    if mlock {
+        reserve_lockable_memory(target_kb * 1024);
+    }
+    let locked_bytes = Arc::new(AtomicU64::new(0));
+    let lock_error = Arc::new(OnceLock::new());
+    let handles: Vec<_> = (0..threads)
+        .map(|n| {
+            let barrier = Arc::clone(&barrier);
+            let allocated_mb = Arc::clone(&allocated_mb);
+            let hugetlb_mb = Arc::clone(&hugetlb_mb);
+            let mut allocator = allocator.clone();
+            let numa_bound = Arc::clone(&numa_bound);
+            let locked_bytes = Arc::clone(&locked_bytes);
+            let lock_error = Arc::clone(&lock_error);
+            spawn_named(format!("stressr-mem-{}", n), move || {
+                if let Some(node) = numa_node
+                    && let Err(e) = bind_memory_to_node(node)
+                    && numa_bound.swap(false, Ordering::Relaxed)
+                {
+                    warn!("Memory: cannot bind to NUMA node {} ({}), allocating unbound", node, e);
+                }
+                let mut rng = Rng::for_stream(seed, n as u64);
+                let mut blocks = alloc_memory_blocks(share_kb, &mut allocator, &mut rng);
+                allocated_mb.fetch_add(memory_blocks_mb(&blocks), Ordering::Relaxed);
+                // Give up at the first block the OS refuses to lock.
+                for block in blocks.iter_mut().filter(|_| mlock) {
+                    if let Err(e) = lock_memory(block) {
+                        let _ = lock_error.set(e.to_string());
+                        break;
+                    }
+                    locked_bytes.fetch_add(block.len() as u64, Ordering::Relaxed);
+                }
+                #[cfg(target_os = "linux")]
+                hugetlb_mb.fetch_add(
+                    blocks
+                        .iter()
+                        .filter(|b| matches!(b, MemoryBlock::Huge(_)))
+                        .map(MemoryBlock::mb)
+                        .sum(),
+                    Ordering::Relaxed,
+                );
+                barrier.wait();
+                let start = Instant::now();
+                let streamed = hold_memory_blocks(&mut blocks, stream, hold);
+                let elapsed = start.elapsed();
+                if let Some(free) = free {
+                    free_memory_blocks(&mut blocks, free);
+                }
+                PROGRESS_MEMORY_MB.fetch_sub(memory_blocks_mb(&blocks), Ordering::Relaxed);
+                (streamed, elapsed)
+            })
+        })
+        .collect();
+    barrier.wait();
+
+    let mut stats = MemoryStats {
+        threads,
+        requested_mb: target_kb / 1024,
+        allocated_mb: allocated_mb.load(Ordering::Relaxed),
+        rss_mb: read_rss_kb().map(|kb| kb / 1024),
+        huge_pages_mb: (allocator.backing != PageBacking::Normal).then(|| {
+            hugetlb_mb.load(Ordering::Relaxed) + read_anon_huge_pages_mb().unwrap_or(0)
+        }),
+        numa_node: numa_node.filter(|_| numa_bound.load(Ordering::Relaxed)),
+        locked_bytes: mlock.then(|| locked_bytes.load(Ordering::Relaxed)),
+        ..MemoryStats::default()
+    };
+    match stats.rss_mb {
+        Some(rss) => report!(
+            "Memory: {} MB allocated of {} MB requested, RSS {} MB",
+            stats.allocated_mb,
+            stats.requested_mb,
+            rss
+        ),
+        None => report!(
+            "Memory: {} MB allocated of {} MB requested",
+            stats.allocated_mb,
+            stats.requested_mb
+        ),
+    }
+    match stats.huge_pages_mb {
+        Some(0) => warn!("Memory: no huge pages were obtained; blocks use normal pages"),
+        Some(huge) => report!("Memory: {} MB backed by huge pages", huge),
+        None => {}
+    }
+    if let Some(locked) = stats.locked_bytes {
+        match lock_error.get() {
+            None => report!("Memory: {} MB locked into RAM", locked / (1024 * 1024)),
+            Some(e) => warn!(
+                "Memory: locked only {} of {} MB ({} bytes) before locking failed: {}{}",
+                locked / (1024 * 1024),
+                stats.allocated_mb,
+                locked,
+                e,
+                memory_lock_hint()
+            ),
+        }
+    }
+    if let Some(node) = stats.numa_node {
+        stats.numa_used_mb = numa_node_used_mb(node);
+        match stats.numa_used_mb {
+            Some(used) => {
+                report!("Memory: bound to NUMA node {}, {} MB in use on the node", node, used)
+            }
+            None => report!("Memory: bound to NUMA node {}", node),
+        }
+    }
+
+    if let Some(free) = free {
+        info!("Memory: holding for {}s, then freeing over {}s", hold.as_secs(), free.as_secs());
+        sleep_while_running(hold);
+        let mut next_report = Instant::now();
+        while running() && !handles.iter().all(|h| h.is_finished()) {
+            if Instant::now() >= next_report {
+                info!("Memory: {}", memory_allocation_line());
+                next_report += MEMORY_RETOUCH_INTERVAL;
+            }
+            thread::sleep(MEMORY_FREE_STEP);
+        }
+    }
+
+    for (streamed, elapsed) in handles.into_iter().filter_map(join_worker) {
+        stats.streamed_bytes += streamed;
+        stats.elapsed = stats.elapsed.max(elapsed);
+    }
+    if stream {
+        report!(
+            "Memory: {} threads streaming, {:.2} GB/s per thread",
+            threads,
+            stats.gbps_per_thread()
+        );
+    }
+    if free.is_some() {
+        report!("Memory: released, {}", memory_allocation_line());
+    }
+    Ok(stats)
+}
+
+/// Blocks still held and, where known, the resident set size, for the
+/// `--memory-free` updates.
+fn memory_allocation_line() -> String {
+    let held = PROGRESS_MEMORY_MB.load(Ordering::Relaxed);
+    match read_rss_kb() {
+        Some(rss_kb) => format!("{} MB allocated, RSS {} MB", held, rss_kb / 1024),
+        None => format!("{} MB allocated", held),
+    }
+}
+
+/// Histogram buckets per decade of latency (each about 26% wide).
+const LATENCY_BUCKETS_PER_DECADE: usize = 10;
+
+/// 1us to 10s; the last bucket also collects anything slower.
+const LATENCY_BUCKETS: usize = 7 * LATENCY_BUCKETS_PER_DECADE + 1;
+
+/// Per-op latencies in fixed log-spaced buckets, so recording an op is an
+/// index computation and an increment with no allocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    counts: [u64; LATENCY_BUCKETS],
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram { counts: [0; LATENCY_BUCKETS] }
+    }
+}
+
+impl LatencyHistogram {
+    /// Bucket `i` holds latencies up to `upper_bound(i)`, above `upper_bound(i - 1)`.
+    fn bucket(latency: Duration) -> usize {
+        let us = latency.as_secs_f64() * 1e6;
+        if us <= 1.0 {
+            return 0;
+        }
+        ((us.log10() * LATENCY_BUCKETS_PER_DECADE as f64).ceil() as usize).min(LATENCY_BUCKETS - 1)
+    }
+
+    fn upper_bound(bucket: usize) -> Duration {
+        let us = 10f64.powf(bucket as f64 / LATENCY_BUCKETS_PER_DECADE as f64);
+        Duration::from_secs_f64(us / 1e6)
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.counts[Self::bucket(latency)] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Adds `other`'s samples to this histogram.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (count, n) in self.counts.iter_mut().zip(other.counts) {
+            *count += n;
+        }
+    }
+
+    /// Upper bound of the bucket holding the `p`th percentile (`p` in 0..=1),
+    /// or `None` if nothing was recorded.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let rank = ((p * self.count() as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if n > 0 && seen >= rank {
+                return Some(Self::upper_bound(bucket));
+            }
+        }
+        None
+    }
+
+    /// Non-empty buckets as `index:count` pairs, or `-` when empty.
+    fn to_wire(&self) -> String {
+        let buckets: Vec<String> = self
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| **n > 0)
+            .map(|(i, n)| format!("{}:{}", i, n))
+            .collect();
+        if buckets.is_empty() { "-".to_string() } else { buckets.join(",") }
+    }
+
+    fn from_wire(value: &str) -> Option<Self> {
+        let mut hist = LatencyHistogram::default();
+        if value == "-" {
+            return Some(hist);
+        }
+        for pair in value.split(',') {
+            let (bucket, n) = pair.split_once(':')?;
+            *hist.counts.get_mut(bucket.parse::<usize>().ok()?)? = n.parse().ok()?;
+        }
+        Some(hist)
+    }
+}
+
+/// A latency in the unit that keeps it readable: `85us`, `1.25ms`, `2.10s`.
+fn format_latency(latency: Duration) -> String {
+    let us = latency.as_secs_f64() * 1e6;
+    if us < 1e3 {
+        format!("{:.0}us", us)
+    } else if us < 1e6 {
+        format!("{:.2}ms", us / 1e3)
+    } else {
+        format!("{:.2}s", us / 1e6)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WorkerStats {
+    pub worker_id: usize,
+    /// The I/O path the worker ran in.
+    pub path: String,
+    pub elapsed: Duration,
+    pub ops: u64,
+    /// Ops that read and that wrote; under `--io-rwmix` each op does one.
+    pub read_ops: u64,
+    pub write_ops: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub paused: Duration,
+    pub distinct_offsets: u64,
+    pub coverage: f64,
+    pub verify_errors: u64,
+    /// `--io-verify`: mismatching bytes seen by timed reads, how many reads
+    /// had any, and where the first one was.
+    pub read_corrupt_bytes: u64,
+    pub corrupt_reads: u64,
+    pub first_corrupt_offset: Option<u64>,
+    /// Latency of the worker's first read before it wrote anything, so not
+    /// served from its own writes in the page cache; none when every read
+    /// follows a write (R+W mode).
+    pub ttfb: Option<Duration>,
+    /// Slowest single read or write.
+    pub max_latency: Duration,
+    /// Ops slower than `--io-max-latency`.
+    pub latency_violations: u64,
+    /// Sum and count of individual read/write latencies, for the average.
+    pub latency_sum: Duration,
+    pub latency_samples: u64,
+    pub latency_hist: LatencyHistogram,
+    /// `--io-fsync` calls and the time spent in them.
+    pub fsyncs: u64,
+    pub fsync_time: Duration,
+    /// Worker files that saw at least one op (see `--io-files-per-worker`).
+    pub files: u64,
+}
+
+/// Prefix of the line a worker process writes to stdout to hand its stats back.
+const WORKER_STATS_PREFIX: &str = "STRESSR_WORKER_STATS";
+
+/// Prefix of the lines a worker process writes to stdout with the I/O it did
+/// since the previous one, for the parent's live counters.
+const WORKER_PROGRESS_PREFIX: &str = "STRESSR_WORKER_PROGRESS";
+
+/// How often a worker process forwards its live I/O counters.
+const PROGRESS_FORWARD_INTERVAL: Duration = Duration::from_millis(500);
+
+fn progress_to_wire(bytes: u64, ops: u64) -> String {
+    format!("{} bytes={} ops={}", WORKER_PROGRESS_PREFIX, bytes, ops)
+}
+
+/// The (bytes, ops) of a `progress_to_wire` line.
+fn progress_from_wire(line: &str) -> Option<(u64, u64)> {
+    let mut fields = line.split_whitespace();
+    if fields.next()? != WORKER_PROGRESS_PREFIX {
+        return None;
+    }
+    let bytes = fields.next()?.strip_prefix("bytes=")?.parse().ok()?;
+    let ops = fields.next()?.strip_prefix("ops=")?.parse().ok()?;
+    Some((bytes, ops))
+}
+
+/// In a worker process, hands the live I/O counters to the parent on stdout
+/// every `PROGRESS_FORWARD_INTERVAL` until dropped, so its `--progress`,
+/// `--stats-addr` and `--tui` see the I/O done here.
+struct ProgressForwarder {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl ProgressForwarder {
+    fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = spawn_named("stressr-progress-fwd".to_string(), move || {
+            let mut done = false;
+            while !done {
+                thread::sleep(PROGRESS_FORWARD_INTERVAL);
+                done = stop_flag.load(Ordering::Relaxed);
+                let bytes = PROGRESS_IO_BYTES.swap(0, Ordering::Relaxed);
+                let ops = PROGRESS_IO_OPS.swap(0, Ordering::Relaxed);
+                if bytes > 0 || ops > 0 {
+                    println!("{}", progress_to_wire(bytes, ops));
+                }
+            }
+        });
+        ProgressForwarder {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for ProgressForwarder {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl WorkerStats {
+    fn to_wire(&self) -> String {
+        format!(
+            "{} worker_id={} ops={} read_ops={} write_ops={} read_bytes={} write_bytes={} elapsed_ns={} paused_ns={} distinct_offsets={} coverage={} verify_errors={} read_corrupt_bytes={} corrupt_reads={} first_corrupt_offset={} ttfb_ns={} max_latency_ns={} latency_violations={} latency_sum_ns={} latency_samples={} latency_hist={} fsyncs={} fsync_ns={} files={}",
+            WORKER_STATS_PREFIX,
+            self.worker_id,
+            self.ops,
+            self.read_ops,
+            self.write_ops,
+            self.read_bytes,
+            self.write_bytes,
+            self.elapsed.as_nanos(),
+            self.paused.as_nanos(),
+            self.distinct_offsets,
+            self.coverage,
+            self.verify_errors,
+            self.read_corrupt_bytes,
+            self.corrupt_reads,
+            self.first_corrupt_offset.map(|o| o.to_string()).unwrap_or_else(|| "-".into()),
+            self.ttfb.map(|t| t.as_nanos().to_string()).unwrap_or_else(|| "-".into()),
+            self.max_latency.as_nanos(),
+            self.latency_violations,
+            self.latency_sum.as_nanos(),
+            self.latency_samples,
+            self.latency_hist.to_wire(),
+            self.fsyncs,
+            self.fsync_time.as_nanos(),
+            self.files
+        )
+    }
+
+    fn from_wire(line: &str) -> Option<Self> {
+        let mut stats = WorkerStats::default();
+        let mut fields = line.split_whitespace();
+        if fields.next()? != WORKER_STATS_PREFIX {
+            return None;
+        }
+        for field in fields {
+            let (key, value) = field.split_once('=')?;
+            match key {
+                "worker_id" => stats.worker_id = value.parse().ok()?,
+                "ops" => stats.ops = value.parse().ok()?,
+                "read_ops" => stats.read_ops = value.parse().ok()?,
+                "write_ops" => stats.write_ops = value.parse().ok()?,
+                "read_bytes" => stats.read_bytes = value.parse().ok()?,
+                "write_bytes" => stats.write_bytes = value.parse().ok()?,
+                "elapsed_ns" => stats.elapsed = Duration::from_nanos(value.parse().ok()?),
+                "paused_ns" => stats.paused = Duration::from_nanos(value.parse().ok()?),
+                "distinct_offsets" => stats.distinct_offsets = value.parse().ok()?,
+                "coverage" => stats.coverage = value.parse().ok()?,
+                "verify_errors" => stats.verify_errors = value.parse().ok()?,
+                "read_corrupt_bytes" => stats.read_corrupt_bytes = value.parse().ok()?,
+                "corrupt_reads" => stats.corrupt_reads = value.parse().ok()?,
+                "first_corrupt_offset" if value != "-" => {
+                    stats.first_corrupt_offset = Some(value.parse().ok()?)
+                }
+                "ttfb_ns" if value != "-" => {
+                    stats.ttfb = Some(Duration::from_nanos(value.parse().ok()?))
+                }
+                "max_latency_ns" => stats.max_latency = Duration::from_nanos(value.parse().ok()?),
+                "latency_violations" => stats.latency_violations = value.parse().ok()?,
+                "latency_sum_ns" => stats.latency_sum = Duration::from_nanos(value.parse().ok()?),
+                "latency_samples" => stats.latency_samples = value.parse().ok()?,
+                "latency_hist" => stats.latency_hist = LatencyHistogram::from_wire(value)?,
+                "fsyncs" => stats.fsyncs = value.parse().ok()?,
+                "fsync_ns" => stats.fsync_time = Duration::from_nanos(value.parse().ok()?),
+                "files" => stats.files = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(stats)
+    }
+
+    /// Throughput over the time the worker was actually doing I/O
+    /// (memory-pressure pauses excluded).
+    fn mbps(&self, bytes: u64) -> f64 {
+        let secs = self.elapsed.saturating_sub(self.paused).as_secs_f64();
+        if secs > 0.0 {
+            bytes as f64 / secs / 1024.0 / 1024.0
+        } else {
+            0.0
+        }
+    }
+
+    fn total_mbps(&self) -> f64 {
+        self.mbps(self.read_bytes + self.write_bytes)
+    }
+
+    /// Ops per second over the same time as `mbps`.
+    fn ops_per_sec(&self) -> f64 {
+        let secs = self.elapsed.saturating_sub(self.paused).as_secs_f64();
+        if secs > 0.0 { self.ops as f64 / secs } else { 0.0 }
+    }
+
+    /// Share of read and write ops that were reads, in percent.
+    fn read_percent(&self) -> f64 {
+        let ops = self.read_ops + self.write_ops;
+        if ops > 0 { self.read_ops as f64 / ops as f64 * 100.0 } else { 0.0 }
+    }
+
+    fn fsync_avg_us(&self) -> f64 {
+        if self.fsyncs > 0 {
+            self.fsync_time.as_secs_f64() * 1e6 / self.fsyncs as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn to_json(&self, mode: &str) -> Json {
+        Json::object(vec![
+            ("worker_id", self.worker_id.into()),
+            ("mode", mode.into()),
+            ("path", self.path.clone().into()),
+            ("mbps", self.total_mbps().into()),
+            ("write_mbps", self.mbps(self.write_bytes).into()),
+            ("read_mbps", self.mbps(self.read_bytes).into()),
+            ("ops", self.ops.into()),
+            ("read_ops", self.read_ops.into()),
+            ("write_ops", self.write_ops.into()),
+            ("write_bytes", self.write_bytes.into()),
+            ("read_bytes", self.read_bytes.into()),
+            ("elapsed_secs", self.elapsed.as_secs_f64().into()),
+            ("paused_secs", self.paused.as_secs_f64().into()),
+            ("ttfb_us", self.ttfb.map(|t| t.as_secs_f64() * 1e6).into()),
+            ("latency_p50_ms", self.latency_percentile_ms(0.50).into()),
+            ("latency_p95_ms", self.latency_percentile_ms(0.95).into()),
+            ("latency_p99_ms", self.latency_percentile_ms(0.99).into()),
+            ("max_latency_ms", (self.max_latency.as_secs_f64() * 1e3).into()),
+            ("latency_violations", self.latency_violations.into()),
+            ("fsyncs", self.fsyncs.into()),
+            ("fsync_avg_us", self.fsync_avg_us().into()),
+            ("verify_errors", self.verify_errors.into()),
+            ("read_corrupt_bytes", self.read_corrupt_bytes.into()),
+            ("corrupt_reads", self.corrupt_reads.into()),
+            ("first_corrupt_offset", self.first_corrupt_offset.into()),
+            ("coverage", self.coverage.into()),
+            ("files", self.files.into()),
+        ])
+    }
+
+    /// Zeroes the throughput and latency counters at the end of `--warmup`.
+    /// The cold first read, coverage and corruption findings are kept.
+    fn reset_counters(&mut self) {
+        *self = WorkerStats {
+            worker_id: self.worker_id,
+            path: std::mem::take(&mut self.path),
+            ttfb: self.ttfb,
+            read_corrupt_bytes: self.read_corrupt_bytes,
+            corrupt_reads: self.corrupt_reads,
+            first_corrupt_offset: self.first_corrupt_offset,
+            ..WorkerStats::default()
+        };
+    }
+
+    /// Op latency at percentile `p`, no higher than the slowest op seen.
+    pub fn latency_percentile(&self, p: f64) -> Option<Duration> {
+        self.latency_hist.percentile(p).map(|d| d.min(self.max_latency))
+    }
+
+    fn latency_percentile_ms(&self, p: f64) -> Option<f64> {
+        self.latency_percentile(p).map(|d| d.as_secs_f64() * 1e3)
+    }
+
+    /// Records one op's latency against `--io-max-latency`. Returns true when
+    /// the worker should stop because a hard limit was exceeded.
+    fn record_latency(&mut self, cfg: &Config, op: &str, offset: u64, latency: Duration) -> bool {
+        self.max_latency = self.max_latency.max(latency);
+        self.latency_sum += latency;
+        self.latency_samples += 1;
+        self.latency_hist.record(latency);
+        let Some(limit_ms) = cfg.io_max_latency_ms else {
+            return false;
+        };
+        if latency <= Duration::from_millis(limit_ms) {
+            return false;
+        }
+        self.latency_violations += 1;
+        if cfg.io_max_latency_soft {
+            return false;
+        }
+        error!(
+            "[I/O Worker {}] {} at offset {} took {:.3}ms, over --io-max-latency {}ms, aborting",
+            self.worker_id,
+            op,
+            offset,
+            latency.as_secs_f64() * 1e3,
+            limit_ms
+        );
+        LATENCY_ABORT.store(true, Ordering::Relaxed);
+        true
+    }
+}
+
+/// The mode tag printed for data I/O workers, e.g. `WR` or `W+fdatasync`.
+fn io_mode(cfg: &Config) -> String {
+    let synced = cfg.io_write && cfg.io_sync_mode != IoSyncMode::None;
+    format!(
+        "{}{}{}{}",
+        if cfg.io_write { "W" } else { "" },
+        if cfg.io_read { "R" } else { "" },
+        if synced { "+" } else { "" },
+        if synced { cfg.io_sync_mode.name() } else { "" }
+    )
+}
+
+/// Time between ops for one worker under `--io-target-iops`: the target is
+/// shared evenly by every worker on every path.
+fn target_op_interval(cfg: &Config) -> Option<Duration> {
+    let workers = (cfg.io_paths.len() * cfg.io_workers).max(1) as f64;
+    cfg.io_target_iops.map(|iops| Duration::from_secs_f64(workers / iops as f64))
+}
+
+/// Sleeps until op number `ops` is due, so a worker issues ops on a fixed
+/// schedule from `start` and catches up after a slow op instead of drifting.
+fn pace_op(start: Instant, ops: u64, interval: Option<Duration>) {
+    if let Some(interval) = interval {
+        let due = start + interval.mul_f64(ops as f64);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+/// How far ahead a throttled worker may catch up after a slow op.
+const THROTTLE_BURST: Duration = Duration::from_millis(100);
+
+/// Token bucket refilled at `rate` units per second. Spending may run the
+/// balance negative; the caller then sleeps off the debt, so the long-run
+/// rate never exceeds `rate` while a slow op can be made up within the burst.
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        TokenBucket {
+            rate,
+            capacity: rate * THROTTLE_BURST.as_secs_f64(),
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Spends `cost` tokens, sleeping until the balance is no longer negative.
+    fn take(&mut self, cost: f64) {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last).as_secs_f64() * self.rate;
+        self.tokens = (self.tokens + refill).min(self.capacity.max(cost)) - cost;
+        self.last = now;
+        if self.tokens < 0.0 {
+            thread::sleep(Duration::from_secs_f64(-self.tokens / self.rate));
+        }
+    }
+}
+
+/// One worker's share of the `--io-rate` and `--io-bandwidth` caps.
+struct IoThrottle {
+    ops: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl IoThrottle {
+    fn new(cfg: &Config) -> Self {
+        let workers = (cfg.io_paths.len() * cfg.io_workers).max(1) as f64;
+        IoThrottle {
+            ops: cfg.io_rate.map(|rate| TokenBucket::new(rate as f64 / workers)),
+            bytes: cfg
+                .io_bandwidth_mbps
+                .map(|mbps| TokenBucket::new(mbps * 1024.0 * 1024.0 / workers)),
+        }
+    }
+
+    /// Waits until an op moving `bytes` fits under both caps.
+    fn wait(&mut self, bytes: u64) {
+        if let Some(ops) = self.ops.as_mut() {
+            ops.take(1.0);
+        }
+        if let Some(bucket) = self.bytes.as_mut() {
+            bucket.take(bytes as f64);
+        }
+    }
+}
+
+/// Fraction of `--io-target-iops` a run must reach to count as attained.
+const TARGET_IOPS_ATTAINED: f64 = 0.95;
+
+/// Set once any worker exceeds a hard `--io-max-latency`, stopping the others.
+static LATENCY_ABORT: AtomicBool = AtomicBool::new(false);
+
+/// Appends op latency percentiles and any `--io-max-latency` violations to a
+/// worker line.
+fn latency_summary(stats: &WorkerStats, cfg: &Config) -> String {
+    let mut summary = String::new();
+    if let (Some(p50), Some(p95), Some(p99)) = (
+        stats.latency_percentile(0.50),
+        stats.latency_percentile(0.95),
+        stats.latency_percentile(0.99),
+    ) {
+        summary.push_str(&format!(
+            " | latency p50 {} p95 {} p99 {} max {}",
+            format_latency(p50),
+            format_latency(p95),
+            format_latency(p99),
+            format_latency(stats.max_latency)
+        ));
+    }
+    if let Some(limit_ms) = cfg.io_max_latency_ms {
+        summary.push_str(&format!(
+            " | max op {:.3}ms, {} over {}ms",
+            stats.max_latency.as_secs_f64() * 1e3,
+            stats.latency_violations,
+            limit_ms
+        ));
+    }
+    summary
+}
+
+/// Expected content of byte `pos` of a verifiable worker file: a hash of the
+/// 8-byte word it lives in, so any region can be regenerated independently.
+fn fill_pattern(buf: &mut [u8], seed: u64, offset: u64) {
+    let mut word = 0u64;
+    for (i, b) in buf.iter_mut().enumerate() {
+        let pos = offset + i as u64;
+        if i == 0 || pos.is_multiple_of(8) {
+            word = rng::mix(seed ^ (pos / 8).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        }
+        *b = (word >> ((pos % 8) * 8)) as u8;
+    }
+}
+
+/// Buffer size used to lay down and check the pattern for `--io-verify-pass`.
+const VERIFY_BLOCK: usize = 1024 * 1024;
+
+/// Alignment of `--io-direct` buffers, chunk sizes and offsets: a multiple of
+/// both 512-byte and 4K sectors.
+const DIRECT_IO_ALIGN: usize = 4096;
+
+/// A zeroed byte buffer whose start is aligned to `DIRECT_IO_ALIGN`, as
+/// unbuffered I/O requires.
+struct AlignedBuffer {
+    storage: Vec<u8>,
+    start: usize,
+    len: usize,
+}
+
+impl AlignedBuffer {
+    fn new(len: usize) -> Self {
+        let storage = vec![0u8; len + DIRECT_IO_ALIGN];
+        let start = storage.as_ptr().align_offset(DIRECT_IO_ALIGN);
+        AlignedBuffer { storage, start, len }
+    }
+}
+
+impl std::ops::Deref for AlignedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.storage[self.start..self.start + self.len]
+    }
+}
+
+impl std::ops::DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.storage[self.start..self.start + self.len]
+    }
+}
+
+#[cfg(all(target_os = "linux", any(target_arch = "aarch64", target_arch = "arm")))]
+const O_DIRECT: std::ffi::c_int = 0o200000;
+#[cfg(all(target_os = "linux", not(any(target_arch = "aarch64", target_arch = "arm"))))]
+const O_DIRECT: std::ffi::c_int = 0o40000;
+
+#[cfg(target_os = "macos")]
+const F_NOCACHE: std::ffi::c_int = 48;
+
+#[cfg(target_os = "macos")]
+unsafe extern "C" {
+    fn fcntl(fd: std::ffi::c_int, cmd: std::ffi::c_int, ...) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "linux")]
+const O_SYNC: std::ffi::c_int = 0o4010000;
+#[cfg(all(unix, not(target_os = "linux")))]
+const O_SYNC: std::ffi::c_int = 0x80;
+
+#[cfg(target_os = "windows")]
+const FILE_FLAG_NO_BUFFERING: u32 = 0x2000_0000;
+#[cfg(target_os = "windows")]
+const FILE_FLAG_WRITE_THROUGH: u32 = 0x8000_0000;
+
+/// Sets the open flags that bypass the page cache (`--io-direct`) and make
+/// every write synchronous (`--io-sync-mode osync`), where there are such.
+/// Both go in one call because each call replaces the custom flags.
+fn set_open_flags(options: &mut OpenOptions, cfg: &Config) {
+    let osync = cfg.io_sync_mode == IoSyncMode::Osync;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut flags = if osync { O_SYNC } else { 0 };
+        #[cfg(target_os = "linux")]
+        if cfg.io_direct {
+            flags |= O_DIRECT;
+        }
+        options.custom_flags(flags);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        let mut flags = if osync { FILE_FLAG_WRITE_THROUGH } else { 0 };
+        if cfg.io_direct {
+            flags |= FILE_FLAG_NO_BUFFERING;
+        }
+        options.custom_flags(flags);
+    }
+
+    #[cfg(not(any(unix, target_os = "windows")))]
+    let _ = (options, osync);
+}
+
+/// Turns off caching on an open file where that is a per-descriptor setting
+/// (macOS `F_NOCACHE`) rather than an open flag.
+fn disable_file_cache(file: &std::fs::File) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: the descriptor is open for the lifetime of `file`.
+        if unsafe { fcntl(file.as_raw_fd(), F_NOCACHE, 1) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    let _ = file;
+    Ok(())
+}
+
+/// `value` rounded up to a multiple of `align`.
+fn align_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+/// Offsets of mismatching bytes to list before only counting them.
+const VERIFY_REPORT_LIMIT: usize = 8;
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn fallocate(
+        fd: std::ffi::c_int,
+        mode: std::ffi::c_int,
+        offset: i64,
+        len: i64,
+    ) -> std::ffi::c_int;
+}
+
+#[cfg(target_os = "linux")]
+const FALLOC_FL_KEEP_SIZE: std::ffi::c_int = 0x01;
+#[cfg(target_os = "linux")]
+const FALLOC_FL_PUNCH_HOLE: std::ffi::c_int = 0x02;
+
+/// Deallocates the first `len` bytes of `file` (keeping its size), which the
+/// filesystem passes down as a discard/TRIM so SSD cells start out clean.
+fn punch_hole(file: &std::fs::File, len: u64) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let mode = FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE;
+        // SAFETY: the descriptor is owned by `file` and stays open for the call.
+        if unsafe { fallocate(file.as_raw_fd(), mode, 0, len as i64) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (file, len);
+        Err(std::io::Error::new(ErrorKind::Unsupported, "hole punching requires Linux"))
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn ioctl(fd: std::ffi::c_int, request: std::ffi::c_ulong, ...) -> std::ffi::c_int;
+}
+
+/// Header of `struct fiemap`; with `fm_extent_count == 0` the kernel only
+/// counts extents into `fm_mapped_extents`.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct Fiemap {
+    fm_start: u64,
+    fm_length: u64,
+    fm_flags: u32,
+    fm_mapped_extents: u32,
+    fm_extent_count: u32,
+    fm_reserved: u32,
+}
+
+#[cfg(target_os = "linux")]
+const FS_IOC_FIEMAP: std::ffi::c_ulong = 0xC020_660B;
+#[cfg(target_os = "linux")]
+const FIEMAP_FLAG_SYNC: u32 = 0x1;
+
+/// Number of on-disk extents backing `file`, after flushing delayed allocation.
+fn file_extent_count(file: &std::fs::File) -> std::io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let mut map = Fiemap {
+            fm_length: u64::MAX,
+            fm_flags: FIEMAP_FLAG_SYNC,
+            ..Default::default()
+        };
+        // SAFETY: map is a valid fiemap header and asks for no extent records.
+        if unsafe { ioctl(file.as_raw_fd(), FS_IOC_FIEMAP, &mut map as *mut Fiemap) } == 0 {
+            Ok(map.fm_mapped_extents as u64)
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = file;
+        Err(std::io::Error::new(ErrorKind::Unsupported, "FIEMAP requires Linux"))
+    }
+}
+
+/// Fragmentation in percent: 0 when the allocated blocks form one extent,
+/// 100 when every 4 KB block sits in an extent of its own.
+fn fragmentation_score(extents: u64, allocated_bytes: u64) -> f64 {
+    let blocks = allocated_bytes.div_ceil(4096);
+    if extents <= 1 || blocks <= 1 {
+        return 0.0;
+    }
+    (extents - 1).min(blocks - 1) as f64 / (blocks - 1) as f64 * 100.0
+}
+
+/// Parses an `--io-fill` byte written as hex, with or without a `0x` prefix.
+fn parse_fill_byte(value: &str) -> Option<u8> {
+    let hex = value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")).unwrap_or(value);
+    u8::from_str_radix(hex, 16).ok()
+}
+
+/// What the I/O workers write, from `--io-fill` and `--io-pattern`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Payload {
+    /// One byte everywhere: `--io-fill`, or zeros for `--io-pattern zero`.
+    Fill(u8),
+    /// `counter_pattern`, for `--io-pattern incrementing`.
+    Counter,
+    /// Random bytes, or `fill_pattern` where the data is verified.
+    Random,
+}
+
+impl Payload {
+    fn of(cfg: &Config) -> Self {
+        match (cfg.io_fill, cfg.io_pattern) {
+            (Some(byte), _) => Payload::Fill(byte),
+            (None, IoPattern::Zero) => Payload::Fill(0),
+            (None, IoPattern::Incrementing) => Payload::Counter,
+            (None, IoPattern::Random) => Payload::Random,
+        }
+    }
+
+    /// Sets up a write buffer: the fill byte, which is never rewritten.
+    fn prepare(&self, buf: &mut [u8]) {
+        if let Payload::Fill(byte) = self {
+            buf.fill(*byte);
+        }
+    }
+
+    /// Refills `buf` before it is written at `offset`. Random data comes from
+    /// `rng` unless `verified`, when it is the position-based pattern instead.
+    fn refill(&self, buf: &mut [u8], offset: u64, rng: &mut Rng, verified: Option<u64>) {
+        match (self, verified) {
+            (Payload::Fill(_), _) => {}
+            (Payload::Counter, _) => counter_pattern(buf, offset),
+            (Payload::Random, Some(seed)) => fill_pattern(buf, seed, offset),
+            (Payload::Random, None) => rng.fill(buf),
+        }
+    }
+}
+
+/// `--io-pattern incrementing`: each 8-byte word of the file holds its word
+/// index, little-endian, so any region can be checked by position.
+fn counter_pattern(buf: &mut [u8], offset: u64) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        let pos = offset + i as u64;
+        *b = ((pos / 8) >> ((pos % 8) * 8)) as u8;
+    }
+}
+
+/// Expected content of a region of a verifiable worker file for `payload`,
+/// with the position-based pattern standing in for random data.
+fn expected_content(buf: &mut [u8], seed: u64, offset: u64, payload: Payload) {
+    match payload {
+        Payload::Fill(byte) => buf.fill(byte),
+        Payload::Counter => counter_pattern(buf, offset),
+        Payload::Random => fill_pattern(buf, seed, offset),
+    }
+}
+
+/// Number of bytes in `actual`, read from `offset`, that differ from the
+/// expected content. `expected` is scratch space of the same length.
+fn count_corrupt(
+    actual: &[u8],
+    expected: &mut [u8],
+    seed: u64,
+    offset: u64,
+    payload: Payload,
+) -> u64 {
+    expected_content(expected, seed, offset, payload);
+    actual.iter().zip(expected.iter()).filter(|(a, e)| a != e).count() as u64
+}
+
+/// Writes the verification pattern over the whole file so every byte has a
+/// known expected value, whether or not the timed phase touches it.
+fn prefill_pattern(
+    file: &mut std::fs::File,
+    seed: u64,
+    payload: Payload,
+    total_bytes: u64,
+) -> std::io::Result<()> {
+    let mut buf = AlignedBuffer::new(VERIFY_BLOCK);
+    file.seek(SeekFrom::Start(0))?;
+    let mut pos = 0;
+    while pos < total_bytes {
+        let len = (total_bytes - pos).min(VERIFY_BLOCK as u64) as usize;
+        expected_content(&mut buf[..len], seed, pos, payload);
+        file.write_all(&buf[..len])?;
+        pos += len as u64;
+    }
+    file.sync_all()
+}
+
+/// Sequentially reads the file back and returns the number of corrupt bytes,
+/// printing the first few offending offsets.
+fn verify_pattern(
+    file: &mut std::fs::File,
+    worker_id: usize,
+    seed: u64,
+    payload: Payload,
+    total_bytes: u64,
+) -> std::io::Result<u64> {
+    let mut actual = AlignedBuffer::new(VERIFY_BLOCK);
+    let mut expected = vec![0u8; VERIFY_BLOCK];
+    let mut corrupt = 0u64;
+    let mut pos = 0;
+
+    file.seek(SeekFrom::Start(0))?;
+    while pos < total_bytes {
+        let len = (total_bytes - pos).min(VERIFY_BLOCK as u64) as usize;
+        file.read_exact(&mut actual[..len])?;
+        expected_content(&mut expected[..len], seed, pos, payload);
+        for (i, (a, e)) in actual[..len].iter().zip(&expected[..len]).enumerate() {
+            if a != e {
+                if (corrupt as usize) < VERIFY_REPORT_LIMIT {
+                    error!(
+                        "[I/O Worker {}] verify: byte at offset {} is {:#04x}, expected {:#04x}",
+                        worker_id,
+                        pos + i as u64,
+                        a,
+                        e
+                    );
+                }
+                corrupt += 1;
+            }
+        }
+        pos += len as u64;
+    }
+
+    Ok(corrupt)
+}
+
+/// Granularity at which random-mode coverage is tracked.
+const COVERAGE_PAGE: u64 = 4096;
+
+/// Below this fraction of the file touched, random mode warns that the
+/// effective working set is smaller than the file.
+const COVERAGE_WARN_FRACTION: f64 = 0.5;
+
+/// Tracks which pages of a worker file random I/O has touched, and at how many
+/// distinct (page-granular) offsets ops started.
+struct Coverage {
+    touched: Vec<u64>,
+    starts: Vec<u64>,
+    pages: u64,
+}
+
+impl Coverage {
+    fn new(total_bytes: u64) -> Self {
+        let pages = total_bytes.div_ceil(COVERAGE_PAGE).max(1);
+        let words = pages.div_ceil(64) as usize;
+        Coverage {
+            touched: vec![0; words],
+            starts: vec![0; words],
+            pages,
+        }
+    }
+
+    fn set(bits: &mut [u64], page: u64) {
+        bits[(page / 64) as usize] |= 1 << (page % 64);
+    }
+
+    fn count(bits: &[u64]) -> u64 {
+        bits.iter().map(|w| w.count_ones() as u64).sum()
+    }
+
+    fn record(&mut self, offset: u64, len: u64) {
+        let first = offset / COVERAGE_PAGE;
+        let last = ((offset + len).div_ceil(COVERAGE_PAGE)).min(self.pages);
+        Self::set(&mut self.starts, first);
+        for page in first..last {
+            Self::set(&mut self.touched, page);
+        }
+    }
+
+    fn distinct_offsets(&self) -> u64 {
+        Self::count(&self.starts)
+    }
+
+    fn fraction(&self) -> f64 {
+        Self::count(&self.touched) as f64 / self.pages as f64
+    }
+}
+
+/// Picks the next offset for an op of `chunk` bytes in a file of `total` bytes.
+/// Sequential access advances `cursor` by one chunk per call, stepping through
+/// every whole-chunk offset in `[0, total - chunk]` once before wrapping to 0.
+/// A chunk as large as the file can only start at offset 0.
+fn next_offset(random: bool, rng: &mut Rng, cursor: &mut u64, chunk: u64, total: u64) -> u64 {
+    let range = total.saturating_sub(chunk);
+    if range == 0 {
+        return 0;
+    }
+    if random {
+        rng.below(range)
+    } else {
+        let chunk = chunk.max(1);
+        let slots = range / chunk + 1;
+        let offset = *cursor / chunk % slots * chunk;
+        *cursor += chunk;
+        offset
+    }
+}
+
+/// Deletes a worker's scratch file when dropped, so early error returns clean
+/// up after themselves too.
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = remove_file(&self.0);
+    }
+}
+
+/// Name of file `index` of the `files` a worker spreads its ops over.
+fn worker_file_name(worker_id: usize, index: usize, files: usize) -> String {
+    if files > 1 {
+        format!("worker_{}_{}.tmp", worker_id, index)
+    } else {
+        format!("worker_{}.tmp", worker_id)
+    }
+}
+
+/// Opens the test file `name` in `path` at `len` bytes, with the guard that
+/// removes it afterwards unless `--io-keep` or `--io-reuse` is set. Under
+/// `--io-reuse` the file must already exist and be at least `len` bytes; it
+/// is used as it is.
+fn open_worker_file(
+    path: &str,
+    name: &str,
+    len: u64,
+    cfg: &Config,
+) -> std::io::Result<(std::fs::File, Option<RemoveOnDrop>)> {
+    let file_path = PathBuf::from(path).join(name);
+    let mut options = OpenOptions::new();
+    options.create(!cfg.io_reuse).read(true).write(!cfg.io_read_only()).truncate(false);
+    set_open_flags(&mut options, cfg);
+    let file = options.open(&file_path).map_err(|e| {
+        let hint = if cfg.io_direct { " (does the filesystem support --io-direct?)" } else { "" };
+        std::io::Error::new(e.kind(), format!("{}: {}{}", file_path.display(), e, hint))
+    })?;
+    if cfg.io_direct {
+        disable_file_cache(&file)?;
+    }
+    if cfg.io_reuse {
+        let existing = file.metadata()?.len();
+        if existing < len {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} is {} bytes, smaller than the {} bytes the run needs",
+                    file_path.display(),
+                    existing,
+                    len
+                ),
+            ));
+        }
+        return Ok((file, None));
+    }
+
+    let cleanup = (!cfg.io_keep).then(|| RemoveOnDrop(file_path));
+    file.set_len(len)?;
+    Ok((file, cleanup))
+}
+
+/// Runs one I/O worker against a test file in `path` until its duration is up.
+/// Fails if the file cannot be created, sized, written or verified; a read
+/// that hits EOF on a truncated file stops the worker with partial stats.
+pub fn disk_io_worker(
+    path: &str,
+    worker_id: usize,
+    cfg: &Config,
+) -> Result<WorkerStats, StressError> {
+    file_io_worker(path, worker_id, cfg).map_err(|e| StressError::io(path, e))
+}
+
+fn file_io_worker(path: &str, worker_id: usize, cfg: &Config) -> std::io::Result<WorkerStats> {
+    let mut read_chunk = cfg.read_chunk_bytes();
+    let mut write_chunk = cfg.write_chunk_bytes();
+    // With several files, each takes an even share of --io-size, kept on
+    // the alignment boundary; a single file keeps --io-size as given.
+    let file_count = cfg.io_files_per_worker.max(1);
+    let mut total_bytes = cfg.io_size_bytes;
+    if file_count > 1 {
+        total_bytes /= file_count as u64;
+        total_bytes -= total_bytes % cfg.io_align() as u64;
+    }
+    if file_count > 1 && total_bytes == 0 {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "--io-size ({} bytes) is too small for {} file(s) of at least {} bytes",
+                cfg.io_size_bytes,
+                file_count,
+                cfg.io_align()
+            ),
+        ));
+    }
+    let duration = Duration::from_secs(cfg.io_duration_secs);
+    let (random, read, write) = (cfg.io_random, cfg.io_read, cfg.io_write);
+
+    if read_chunk as u64 > total_bytes || write_chunk as u64 > total_bytes {
+        warn!(
+            "[I/O Worker {}] chunk size is larger than the {} byte file, clamping it to the file size",
+            worker_id, total_bytes
+        );
+        read_chunk = read_chunk.min(total_bytes as usize);
+        write_chunk = write_chunk.min(total_bytes as usize);
+    }
+    // Unbuffered I/O only accepts offsets on a block boundary; buffered I/O
+    // is aligned on request so no op straddles a sector.
+    let align = cfg.io_align();
+    let flag = if cfg.io_direct { "--io-direct" } else { "--io-block-align" };
+    if (align_up(read_chunk, align), align_up(write_chunk, align)) != (read_chunk, write_chunk) {
+        warn!(
+            "[I/O Worker {}] {}: rounding chunk sizes up to a multiple of {} bytes",
+            worker_id, flag, align
+        );
+        read_chunk = align_up(read_chunk, align);
+        write_chunk = align_up(write_chunk, align);
+        if read_chunk as u64 > total_bytes || write_chunk as u64 > total_bytes {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} needs the file ({} bytes) to hold at least one {}-byte chunk",
+                    flag, total_bytes, align
+                ),
+            ));
+        }
+    }
+    let align = align as u64;
+
+    // All files are open before the timed phase; the guards remove them.
+    let (mut files, _cleanup): (Vec<_>, Vec<_>) = (0..file_count)
+        .map(|index| {
+            let name = worker_file_name(worker_id, index, file_count);
+            open_worker_file(path, &name, total_bytes, cfg)
+        })
+        .collect::<std::io::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+
+    if cfg.io_pretrim
+        && let Err(e) = files.iter().try_for_each(|file| punch_hole(file, total_bytes))
+    {
+        warn!(
+            "[I/O Worker {}] warning: pre-trim not supported on {} ({}), writes may hit dirty cells",
+            worker_id, path, e
+        );
+    }
+
+    let verify_seed = 0x5EED_0000 ^ worker_id as u64;
+    let patterned = cfg.io_verify || cfg.io_verify_pass;
+    let payload = Payload::of(cfg);
+    if patterned {
+        for file in files.iter_mut() {
+            prefill_pattern(file, verify_seed, payload, total_bytes)?;
+        }
+    }
+
+    let mut read_buffer = AlignedBuffer::new(read_chunk);
+    let mut expected_buffer = vec![0u8; if cfg.io_verify { read_chunk } else { 0 }];
+    let mut write_buffer = AlignedBuffer::new(write_chunk);
+    payload.prepare(&mut write_buffer);
+    let mut rng = Rng::for_stream(cfg.seed, worker_id as u64);
+    let mut read_cursors = vec![0u64; file_count];
+    let mut write_cursors = vec![0u64; file_count];
+    let mut touched = vec![false; file_count];
+    let mut writes = 0u64;
+    // Coverage is over the worker's files laid end to end.
+    let mut coverage = random.then(|| Coverage::new(total_bytes * file_count as u64));
+    let mut stats = WorkerStats {
+        worker_id,
+        path: path.to_string(),
+        ..Default::default()
+    };
+
+    let pause_on_pressure = cfg.io_pause_below_mb.is_some();
+    let op_interval = target_op_interval(cfg);
+    let mut throttle = IoThrottle::new(cfg);
+    let mut warmup = Some(Duration::from_secs(cfg.warmup_secs)).filter(|w| !w.is_zero());
+    let start = Instant::now();
+    let mut measured_from = start;
+
+    let queued = if cfg.io_queue_depth > 1 {
+        let shape = OpShape {
+            read_chunk,
+            write_chunk,
+            align,
+            payload,
+            verified: patterned.then_some(verify_seed),
+        };
+        queued_io_loop(&files[0], cfg, &shape, &mut rng, &mut coverage, &mut stats)?
+    } else {
+        None
+    };
+    if let Some(from) = queued {
+        measured_from = from;
+    }
+
+    while queued.is_none()
+        && start.elapsed() < duration
+        && running()
+        && !LATENCY_ABORT.load(Ordering::Relaxed)
+    {
+        if warmup.is_some_and(|w| start.elapsed() >= w) {
+            warmup = None;
+            stats.reset_counters();
+            measured_from = Instant::now();
+        }
+        if pause_on_pressure && MEMORY_PRESSURE.load(Ordering::Relaxed) {
+            let t0 = Instant::now();
+            thread::sleep(Duration::from_millis(50));
+            stats.paused += t0.elapsed();
+            continue;
+        }
+
+        let (write, read) = match cfg.io_rwmix {
+            Some(mix) => {
+                let read = rng.below(100) < mix;
+                (!read, read)
+            }
+            None => (write, read),
+        };
+        pace_op(measured_from, stats.ops, op_interval);
+        throttle.wait((write as u64 * write_chunk as u64) + (read as u64 * read_chunk as u64));
+        // One file per op, so a read-back hits the file just written.
+        let index = match file_count {
+            1 => 0,
+            _ if random => rng.below(file_count as u64) as usize,
+            _ => (stats.ops % file_count as u64) as usize,
+        };
+        let file = &mut files[index];
+        let base = index as u64 * total_bytes;
+        touched[index] = true;
+        let op_start = Instant::now();
+        let mut write_offset = 0;
+        if write {
+            let offset = next_offset(
+                random,
+                &mut rng,
+                &mut write_cursors[index],
+                write_chunk as u64,
+                total_bytes,
+            );
+            let offset = offset - offset % align;
+            write_offset = offset;
+            payload.refill(&mut write_buffer, offset, &mut rng, patterned.then_some(verify_seed));
+            let t0 = Instant::now();
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&write_buffer)?;
+            stats.write_bytes += write_chunk as u64;
+            stats.write_ops += 1;
+            PROGRESS_IO_BYTES.fetch_add(write_chunk as u64, Ordering::Relaxed);
+            PROGRESS_IO_OPS.fetch_add(1, Ordering::Relaxed);
+            writes += 1;
+            if cfg.io_sync_mode.syncs_after_writes()
+                && writes.is_multiple_of(cfg.io_fsync_every)
+            {
+                let t_sync = Instant::now();
+                if cfg.io_sync_mode == IoSyncMode::Fdatasync {
+                    file.sync_data()?;
+                } else {
+                    file.sync_all()?;
+                }
+                stats.fsync_time += t_sync.elapsed();
+                stats.fsyncs += 1;
+            }
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.record(base + offset, write_chunk as u64);
+            }
+            if stats.record_latency(cfg, "write", offset, t0.elapsed()) {
+                break;
+            }
+        }
+
+        if read {
+            // Read back what was just written unless reads use their own chunk size.
+            let offset = if write && read_chunk == write_chunk {
+                write_offset
+            } else {
+                let offset = next_offset(
+                    random,
+                    &mut rng,
+                    &mut read_cursors[index],
+                    read_chunk as u64,
+                    total_bytes,
+                );
+                offset - offset % align
+            };
+            let t0 = Instant::now();
+            file.seek(SeekFrom::Start(offset))?;
+            let result = file.read_exact(&mut read_buffer);
+            if writes == 0 && stats.ttfb.is_none() {
+                stats.ttfb = Some(t0.elapsed());
+            }
+            if let Err(e) = result {
+                if e.kind() == ErrorKind::UnexpectedEof {
+                    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+                    error!(
+                        "[I/O Worker {}] EOF reading {} bytes at offset {}: file is {} bytes, expected {} (truncated?), stopping worker",
+                        worker_id, read_chunk, offset, len, total_bytes
+                    );
+                } else {
+                    error!(
+                        "[I/O Worker {}] read error at offset {}: {}, stopping worker",
+                        worker_id, offset, e
+                    );
+                }
+                break;
+            }
+            stats.read_bytes += read_chunk as u64;
+            stats.read_ops += 1;
+            PROGRESS_IO_BYTES.fetch_add(read_chunk as u64, Ordering::Relaxed);
+            PROGRESS_IO_OPS.fetch_add(1, Ordering::Relaxed);
+            if cfg.io_verify {
+                let corrupt = count_corrupt(
+                    &read_buffer,
+                    &mut expected_buffer,
+                    verify_seed,
+                    offset,
+                    payload,
+                );
+                if corrupt > 0 {
+                    if (stats.corrupt_reads as usize) < VERIFY_REPORT_LIMIT {
+                        error!(
+                            "[I/O Worker {}] read verify: {} of {} bytes at offset {} are corrupt",
+                            worker_id, corrupt, read_chunk, offset
+                        );
+                    }
+                    stats.read_corrupt_bytes += corrupt;
+                    stats.corrupt_reads += 1;
+                    stats.first_corrupt_offset.get_or_insert(offset);
+                }
+            }
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.record(base + offset, read_chunk as u64);
+            }
+            if stats.record_latency(cfg, "read", offset, t0.elapsed()) {
+                break;
+            }
+        }
+
+        stats.ops += 1;
+        throttle_io(op_start.elapsed());
+    }
+
+    stats.elapsed = measured_from.elapsed();
+    touched[0] |= queued.is_some();
+    stats.files = touched.iter().filter(|&&t| t).count() as u64;
+    if let Some(coverage) = &coverage {
+        stats.distinct_offsets = coverage.distinct_offsets();
+        stats.coverage = coverage.fraction();
+    }
+
+    let mut line = format!(
+        "[{}] {} | {} ops | mode={}",
+        worker_label(cfg, stats.worker_id, path),
+        format_rate(stats.total_mbps(), cfg.precision),
+        stats.ops,
+        io_mode(cfg)
+    );
+    if queued.is_some() {
+        line.push_str(&format!(" | queue depth {} (io_uring)", cfg.io_queue_depth));
+    }
+    if file_count > 1 {
+        line.push_str(&format!(
+            " | touched {} of {} files of {} KB",
+            stats.files,
+            file_count,
+            total_bytes / 1024
+        ));
+    }
+    if read && write {
+        line.push_str(&format!(
+            " | write {} @ {}KB, read {} @ {}KB",
+            format_rate(stats.mbps(stats.write_bytes), cfg.precision),
+            write_chunk / 1024,
+            format_rate(stats.mbps(stats.read_bytes), cfg.precision),
+            read_chunk / 1024
+        ));
+    }
+    if let Some(mix) = cfg.io_rwmix {
+        line.push_str(&format!(
+            " | {:.1}% reads (target {}%)",
+            stats.read_percent(),
+            mix
+        ));
+    }
+    if let Some(ttfb) = stats.ttfb {
+        line.push_str(&format!(" | ttfb {:.0}us", ttfb.as_secs_f64() * 1e6));
+    }
+    if stats.fsyncs > 0 {
+        line.push_str(&format!(
+            " | {} {}s avg {:.0}us ({:.0}% of run)",
+            stats.fsyncs,
+            cfg.io_sync_mode.name(),
+            stats.fsync_avg_us(),
+            stats.fsync_time.as_secs_f64() / stats.elapsed.as_secs_f64().max(f64::EPSILON) * 100.0
+        ));
+    }
+    line.push_str(&latency_summary(&stats, cfg));
+    if !stats.paused.is_zero() {
+        line.push_str(&format!(" | paused {:.1}s", stats.paused.as_secs_f64()));
+    }
+    if cfg.io_verify && read {
+        if stats.corrupt_reads == 0 {
+            line.push_str(" | read verify OK");
+        } else {
+            line.push_str(&format!(
+                " | read verify FAILED: {} corrupt bytes in {} reads, first at offset {}",
+                stats.read_corrupt_bytes,
+                stats.corrupt_reads,
+                stats.first_corrupt_offset.unwrap_or(0)
+            ));
+        }
+    }
+    if cfg.io_verify_pass {
+        let verified = files.iter_mut().try_fold(0, |corrupt, file| {
+            if !cfg.io_read_only() {
+                file.sync_all()?;
+            }
+            Ok::<_, std::io::Error>(
+                corrupt + verify_pattern(file, worker_id, verify_seed, payload, total_bytes)?,
+            )
+        });
+        match verified {
+            Ok(corrupt) => {
+                stats.verify_errors = corrupt;
+                if corrupt == 0 {
+                    line.push_str(" | verify OK");
+                } else {
+                    line.push_str(&format!(" | verify FAILED: {} corrupt bytes", corrupt));
+                }
+            }
+            Err(e) => {
+                error!("[I/O Worker {}] verify pass failed: {}", worker_id, e);
+                line.push_str(" | verify incomplete");
+            }
+        }
+    }
+    if random {
+        line.push_str(&format!(
+            " | coverage {:.1}% ({} distinct offsets)",
+            stats.coverage * 100.0,
+            stats.distinct_offsets
+        ));
+        if stats.coverage < COVERAGE_WARN_FRACTION {
+            warn!(
+                "[I/O Worker {}] warning: random I/O touched only {:.1}% of the file; PRNG quality or run length is limiting the working set",
+                stats.worker_id,
+                stats.coverage * 100.0
+            );
+        }
+    }
+    #[cfg(unix)]
+    if write && let Ok(extents) = files.iter().map(file_extent_count).sum::<std::io::Result<_>>() {
+        use std::os::unix::fs::MetadataExt;
+        let allocated: u64 =
+            files.iter().map(|file| file.metadata().map(|m| m.blocks() * 512).unwrap_or(0)).sum();
+        line.push_str(&format!(
+            " | {} extents (fragmentation {:.1}%)",
+            extents,
+            fragmentation_score(extents, allocated)
+        ));
+    }
+    report!("{}", line);
+
+    Ok(stats)
+}
+
+/// Largest `--io-queue-depth`; every slot holds a chunk-sized buffer.
+const IO_QUEUE_DEPTH_MAX: usize = 1024;
+
+/// One worker's ops once its chunk sizes are clamped and aligned.
+struct OpShape {
+    read_chunk: usize,
+    write_chunk: usize,
+    align: u64,
+    payload: Payload,
+    /// The pattern seed when writes must be verifiable afterwards.
+    verified: Option<u64>,
+}
+
+/// One `--io-queue-depth` buffer and the op it is lent to.
+#[cfg(target_os = "linux")]
+struct QueueSlot {
+    buffer: AlignedBuffer,
+    write: bool,
+    offset: u64,
+    issued: Instant,
+    /// The buffer holds read data, so the write payload must be set up again.
+    stale: bool,
+}
+
+/// Runs the worker's reads and writes with `--io-queue-depth` of them in
+/// flight on an io_uring until the duration is up, counting each completion
+/// as one op. Returns when measurement started, or None (after a warning) if
+/// io_uring is unavailable and the caller should run synchronously.
+#[cfg(target_os = "linux")]
+fn queued_io_loop(
+    file: &std::fs::File,
+    cfg: &Config,
+    shape: &OpShape,
+    rng: &mut Rng,
+    coverage: &mut Option<Coverage>,
+    stats: &mut WorkerStats,
+) -> std::io::Result<Option<Instant>> {
+    let worker_id = stats.worker_id;
+    let depth = cfg.io_queue_depth;
+    let mut ring = match uring::Ring::new(depth as u32) {
+        Ok(ring) => ring,
+        Err(e) => {
+            warn!(
+                "[I/O Worker {}] io_uring unavailable ({}), falling back to synchronous I/O",
+                worker_id, e
+            );
+            return Ok(None);
+        }
+    };
+    let buffer_len = shape.read_chunk.max(shape.write_chunk);
+    let mut slots: Vec<QueueSlot> = (0..depth)
+        .map(|_| QueueSlot {
+            buffer: AlignedBuffer::new(buffer_len),
+            write: false,
+            offset: 0,
+            issued: Instant::now(),
+            stale: true,
+        })
+        .collect();
+    let mut free: Vec<usize> = (0..depth).rev().collect();
+
+    let duration = Duration::from_secs(cfg.io_duration_secs);
+    let pause_on_pressure = cfg.io_pause_below_mb.is_some();
+    let op_interval = target_op_interval(cfg);
+    let mut throttle = IoThrottle::new(cfg);
+    let mut warmup = Some(Duration::from_secs(cfg.warmup_secs)).filter(|w| !w.is_zero());
+    let (mut read_cursor, mut write_cursor) = (0u64, 0u64);
+    let mut issued = 0u64;
+    let mut stopped = false;
+    let mut failure = None;
+    let start = Instant::now();
+    let mut measured_from = start;
+
+    loop {
+        while let Some((id, res)) = ring.pop() {
+            let slot = &slots[id as usize];
+            free.push(id as usize);
+            let latency = slot.issued.elapsed();
+            let (kind, len) = match slot.write {
+                true => ("write", shape.write_chunk),
+                false => ("read", shape.read_chunk),
+            };
+            if res < 0 || res as usize != len {
+                let e = if res < 0 {
+                    std::io::Error::from_raw_os_error(-res)
+                } else {
+                    std::io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        format!("moved {} of {} bytes (file truncated?)", res, len),
+                    )
+                };
+                if !stopped {
+                    error!(
+                        "[I/O Worker {}] {} error at offset {}: {}, stopping worker",
+                        worker_id, kind, slot.offset, e
+                    );
+                }
+                // As in the synchronous loop, a failed read only ends the worker.
+                if slot.write && failure.is_none() {
+                    failure = Some(e);
+                }
+                stopped = true;
+                continue;
+            }
+            if slot.write {
+                stats.write_bytes += len as u64;
+                stats.write_ops += 1;
+            } else {
+                stats.ttfb.get_or_insert(latency);
+                stats.read_bytes += len as u64;
+                stats.read_ops += 1;
+            }
+            stats.ops += 1;
+            PROGRESS_IO_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+            PROGRESS_IO_OPS.fetch_add(1, Ordering::Relaxed);
+            if let Some(coverage) = coverage.as_mut() {
+                coverage.record(slot.offset, len as u64);
+            }
+            stopped |= stats.record_latency(cfg, kind, slot.offset, latency);
+            throttle_io(latency);
+        }
+
+        stopped |=
+            start.elapsed() >= duration || !running() || LATENCY_ABORT.load(Ordering::Relaxed);
+        if !stopped && warmup.is_some_and(|w| start.elapsed() >= w) {
+            warmup = None;
+            stats.reset_counters();
+            measured_from = Instant::now();
+            issued = 0;
+        }
+        let paused = pause_on_pressure && MEMORY_PRESSURE.load(Ordering::Relaxed);
+        if free.len() == depth {
+            if stopped {
+                break;
+            }
+            if paused {
+                let t0 = Instant::now();
+                thread::sleep(Duration::from_millis(50));
+                stats.paused += t0.elapsed();
+                continue;
+            }
+        }
+
+        // Queue one op per pass so completions are reaped between paced ops.
+        if !stopped
+            && !paused
+            && let Some(id) = free.pop()
+        {
+            let write = match cfg.io_rwmix {
+                Some(mix) => rng.below(100) >= mix,
+                None if cfg.io_read && cfg.io_write => issued.is_multiple_of(2),
+                None => cfg.io_write,
+            };
+            let (cursor, chunk) = match write {
+                true => (&mut write_cursor, shape.write_chunk),
+                false => (&mut read_cursor, shape.read_chunk),
+            };
+            pace_op(measured_from, issued, op_interval);
+            throttle.wait(chunk as u64);
+            let offset = next_offset(cfg.io_random, rng, cursor, chunk as u64, cfg.io_size_bytes);
+            let offset = offset - offset % shape.align;
+            let slot = &mut slots[id];
+            let buf = &mut slot.buffer[..chunk];
+            if write {
+                if slot.stale {
+                    shape.payload.prepare(buf);
+                }
+                shape.payload.refill(buf, offset, rng, shape.verified);
+            }
+            ring.push(file, write, buf.as_mut_ptr(), chunk as u32, offset, id as u64);
+            (slot.write, slot.offset, slot.stale) = (write, offset, !write);
+            slot.issued = Instant::now();
+            issued += 1;
+        }
+
+        let wait = if stopped || paused || free.is_empty() { 1 } else { 0 };
+        if let Err(e) = ring.submit_and_wait(wait) {
+            // The kernel may still be filling the buffers of ops in flight.
+            std::mem::forget(slots);
+            return Err(e);
+        }
+    }
+
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(Some(measured_from)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn queued_io_loop(
+    _file: &std::fs::File,
+    _cfg: &Config,
+    _shape: &OpShape,
+    _rng: &mut Rng,
+    _coverage: &mut Option<Coverage>,
+    stats: &mut WorkerStats,
+) -> std::io::Result<Option<Instant>> {
+    warn!(
+        "[I/O Worker {}] --io-queue-depth needs io_uring (Linux), running synchronously",
+        stats.worker_id
+    );
+    Ok(None)
+}
+
+/// Bytes written into each file by the metadata churn worker.
+const METADATA_FILE_BYTES: usize = 4096;
+
+/// Files created per churn round before they are all deleted again.
+const METADATA_BATCH: usize = 64;
+
+/// Name of file `n` of a metadata worker; `remove_metadata_files` sweeps up
+/// everything under this prefix.
+fn metadata_file_name(worker_id: usize, n: usize) -> String {
+    format!("meta_{}_{}.tmp", worker_id, n)
+}
+
+/// Removes whatever files and directories a metadata worker left behind, such
+/// as those of a round that failed partway.
+fn remove_metadata_files(path: &str, worker_id: usize) {
+    let prefix = format!("meta_{}_", worker_id);
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = remove_file(entry.path()).or_else(|_| std::fs::remove_dir(entry.path()));
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetadataStats {
+    worker_id: usize,
+    creates: u64,
+    deletes: u64,
+    create_time: Duration,
+    delete_time: Duration,
+    fsync_time: Duration,
+    fsync_max: Duration,
+    renames: u64,
+    rename_time: Duration,
+    links: u64,
+    link_time: Duration,
+    dirs: u64,
+    dir_time: Duration,
+    elapsed: Duration,
+}
+
+impl MetadataStats {
+    fn per_sec(count: u64, time: Duration) -> f64 {
+        let secs = time.as_secs_f64();
+        if secs > 0.0 { count as f64 / secs } else { 0.0 }
+    }
+
+    /// An `--io-churn` worker's files as I/O stats, one write op per file, so
+    /// they count toward the I/O totals.
+    fn churn_stats(&self, path: &str) -> WorkerStats {
+        WorkerStats {
+            worker_id: self.worker_id,
+            path: path.to_string(),
+            ops: self.creates,
+            write_ops: self.creates,
+            write_bytes: self.creates * METADATA_FILE_BYTES as u64,
+            elapsed: self.elapsed,
+            ..Default::default()
+        }
+    }
+}
+
+/// Stresses filesystem metadata rather than data throughput: each round creates
+/// a batch of small files (write + fsync each), then deletes them all. With
+/// `--io-metadata-ops` each round also renames every file, creates and removes
+/// a hardlink to it, and creates and removes a batch of directories. Under
+/// `--io-churn` a round is a single file and nothing is synced, so the run
+/// measures the create/unlink rate. Fails on the first operation that does,
+/// after removing whatever the worker left behind.
+fn metadata_worker(
+    path: &str,
+    worker_id: usize,
+    cfg: &Config,
+) -> Result<MetadataStats, StressError> {
+    let mut stats = MetadataStats {
+        worker_id,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let result = metadata_rounds(path, cfg, &mut stats);
+    stats.elapsed = start.elapsed();
+    remove_metadata_files(path, worker_id);
+    result?;
+
+    let fsync_avg_us = if stats.creates > 0 {
+        stats.fsync_time.as_secs_f64() * 1e6 / stats.creates as f64
+    } else {
+        0.0
+    };
+
+    let mut line = if cfg.io_churn {
+        format!(
+            "[Metadata Worker {}] {:.0} files/s | {} files churned (create, write {} KB, close, delete)",
+            stats.worker_id,
+            MetadataStats::per_sec(stats.creates, stats.elapsed),
+            stats.creates,
+            METADATA_FILE_BYTES / 1024
+        )
+    } else {
+        format!(
+            "[Metadata Worker {}] {:.0} creates/s | {:.0} deletes/s | {} files | fsync avg {:.0}us max {:.0}us",
+            stats.worker_id,
+            MetadataStats::per_sec(stats.creates, stats.create_time),
+            MetadataStats::per_sec(stats.deletes, stats.delete_time),
+            stats.creates,
+            fsync_avg_us,
+            stats.fsync_max.as_secs_f64() * 1e6
+        )
+    };
+    if cfg.io_metadata_ops {
+        line.push_str(&format!(
+            " | {:.0} renames/s | {:.0} link+unlink/s | {:.0} mkdir+rmdir/s",
+            MetadataStats::per_sec(stats.renames, stats.rename_time),
+            MetadataStats::per_sec(stats.links, stats.link_time),
+            MetadataStats::per_sec(stats.dirs, stats.dir_time)
+        ));
+    }
+    report!("{}", line);
+
+    Ok(stats)
+}
+
+fn metadata_rounds(path: &str, cfg: &Config, stats: &mut MetadataStats) -> Result<(), StressError> {
+    let fail = |e| StressError::io(path, e);
+    let worker_id = stats.worker_id;
+    let duration = Duration::from_secs(cfg.io_duration_secs);
+    let payload = vec![0xA5u8; METADATA_FILE_BYTES];
+    let batch_len = if cfg.io_churn { 1 } else { METADATA_BATCH };
+
+    let start = Instant::now();
+    let mut batch: Vec<PathBuf> = Vec::with_capacity(batch_len);
+    let mut links_supported = true;
+
+    while start.elapsed() < duration && running() {
+        for n in 0..batch_len {
+            let file_path = PathBuf::from(path).join(metadata_file_name(worker_id, n));
+
+            let t0 = Instant::now();
+            let mut file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&file_path)
+                .map_err(fail)?;
+            file.write_all(&payload).map_err(fail)?;
+
+            if !cfg.io_churn {
+                let t_sync = Instant::now();
+                file.sync_all().map_err(fail)?;
+                let sync = t_sync.elapsed();
+                stats.fsync_time += sync;
+                stats.fsync_max = stats.fsync_max.max(sync);
+            }
+            drop(file);
+
+            stats.create_time += t0.elapsed();
+            stats.creates += 1;
+            batch.push(file_path);
+        }
+
+        if cfg.io_metadata_ops {
+            let t0 = Instant::now();
+            for file_path in batch.iter_mut() {
+                let renamed = file_path.with_extension("renamed");
+                std::fs::rename(&*file_path, &renamed).map_err(fail)?;
+                *file_path = renamed;
+                stats.renames += 1;
+            }
+            stats.rename_time += t0.elapsed();
+
+            let t0 = Instant::now();
+            for file_path in batch.iter() {
+                if !links_supported {
+                    break;
+                }
+                let link = file_path.with_extension("link");
+                match std::fs::hard_link(file_path, &link) {
+                    Ok(()) => {
+                        remove_file(&link).map_err(fail)?;
+                        stats.links += 1;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "[Metadata Worker {}] hardlinks not supported on {} ({}), skipping them",
+                            worker_id, path, e
+                        );
+                        links_supported = false;
+                    }
+                }
+            }
+            stats.link_time += t0.elapsed();
+
+            let t0 = Instant::now();
+            let dirs: Vec<PathBuf> = (0..METADATA_BATCH)
+                .map(|n| PathBuf::from(path).join(format!("meta_{}_dir_{}", worker_id, n)))
+                .collect();
+            for dir in &dirs {
+                std::fs::create_dir(dir).map_err(fail)?;
+            }
+            for dir in &dirs {
+                std::fs::remove_dir(dir).map_err(fail)?;
+                stats.dirs += 1;
+            }
+            stats.dir_time += t0.elapsed();
+        }
+
+        let t0 = Instant::now();
+        for file_path in batch.drain(..) {
+            remove_file(&file_path).map_err(fail)?;
+            stats.deletes += 1;
+        }
+        stats.delete_time += t0.elapsed();
+    }
+    Ok(())
+}
+
+/// One operation of an `--io-trace` file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TraceOp {
+    write: bool,
+    offset: u64,
+    size: usize,
+}
+
+/// Parses a trace with one `R|W <offset> <size>` operation per line (bytes);
+/// blank lines and `#` comments are skipped.
+fn parse_io_trace(text: &str) -> Result<Vec<TraceOp>, String> {
+    let mut ops = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let op = match fields.as_slice() {
+            [kind, offset, size] => {
+                let write = match kind.to_ascii_uppercase().as_str() {
+                    "R" | "READ" => false,
+                    "W" | "WRITE" => true,
+                    _ => return Err(format!("line {}: unknown operation {:?}", n + 1, kind)),
+                };
+                let offset = offset.parse().map_err(|_| format!("line {}: bad offset", n + 1))?;
+                let size = size.parse().map_err(|_| format!("line {}: bad size", n + 1))?;
+                TraceOp { write, offset, size }
+            }
+            _ => return Err(format!("line {}: expected \"R|W <offset> <size>\"", n + 1)),
+        };
+        if op.size == 0 {
+            return Err(format!("line {}: size must be greater than 0", n + 1));
+        }
+        ops.push(op);
+    }
+    if ops.is_empty() {
+        return Err("trace contains no operations".to_string());
+    }
+    Ok(ops)
+}
+
+/// Loads `--io-trace` once per process and shares it between workers.
+fn io_trace_ops(path: &str) -> Result<&'static [TraceOp], String> {
+    static IO_TRACE: OnceLock<Vec<TraceOp>> = OnceLock::new();
+    if let Some(ops) = IO_TRACE.get() {
+        return Ok(ops);
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let ops = parse_io_trace(&text).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(IO_TRACE.get_or_init(|| ops))
+}
+
+/// Replays a captured access pattern against the worker file, in order, either
+/// once or looping until `--io-duration` expires. The file is sized to cover
+/// the furthest extent in the trace.
+fn trace_worker(
+    path: &str,
+    worker_id: usize,
+    cfg: &Config,
+    ops: &[TraceOp],
+) -> std::io::Result<WorkerStats> {
+    let duration = Duration::from_secs(cfg.io_duration_secs);
+    let file_len = ops.iter().map(|op| op.offset + op.size as u64).max().unwrap_or(0);
+
+    let name = worker_file_name(worker_id, 0, 1);
+    let (mut file, _cleanup) = open_worker_file(path, &name, file_len, cfg)?;
+
+    let mut buffer = vec![0u8; ops.iter().map(|op| op.size).max().unwrap_or(0)];
+    let payload = Payload::of(cfg);
+    payload.prepare(&mut buffer);
+    let mut rng = Rng::for_stream(cfg.seed, worker_id as u64);
+    let mut stats = WorkerStats {
+        worker_id,
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let mut passes = 0u64;
+    let mut index = 0;
+
+    let pause_on_pressure = cfg.io_pause_below_mb.is_some();
+    let op_interval = target_op_interval(cfg);
+    let mut throttle = IoThrottle::new(cfg);
+    let mut warmup = Some(Duration::from_secs(cfg.warmup_secs)).filter(|w| !w.is_zero());
+    let start = Instant::now();
+    let mut measured_from = start;
+
+    while start.elapsed() < duration && running() && !LATENCY_ABORT.load(Ordering::Relaxed) {
+        if warmup.is_some_and(|w| start.elapsed() >= w) {
+            warmup = None;
+            stats.reset_counters();
+            measured_from = Instant::now();
+        }
+        if index == ops.len() {
+            passes += 1;
+            if cfg.io_trace_once {
+                break;
+            }
+            index = 0;
+        }
+        if pause_on_pressure && MEMORY_PRESSURE.load(Ordering::Relaxed) {
+            let t0 = Instant::now();
+            thread::sleep(Duration::from_millis(50));
+            stats.paused += t0.elapsed();
+            continue;
+        }
+
+        pace_op(measured_from, stats.ops, op_interval);
+        let op = ops[index];
+        throttle.wait(op.size as u64);
+        let buf = &mut buffer[..op.size];
+        let t0 = Instant::now();
+        let result = file.seek(SeekFrom::Start(op.offset)).and_then(|_| {
+            if op.write {
+                payload.refill(buf, op.offset, &mut rng, None);
+                file.write_all(buf)
+            } else {
+                file.read_exact(buf)
+            }
+        });
+        if !op.write && stats.ttfb.is_none() {
+            stats.ttfb = Some(t0.elapsed());
+        }
+        if let Err(e) = result {
+            error!(
+                "[I/O Worker {}] trace op {} ({} {} bytes at offset {}) failed: {}, stopping worker",
+                worker_id,
+                index + 1,
+                if op.write { "write" } else { "read" },
+                op.size,
+                op.offset,
+                e
+            );
+            break;
+        }
+        if op.write {
+            stats.write_bytes += op.size as u64;
+            stats.write_ops += 1;
+        } else {
+            stats.read_bytes += op.size as u64;
+            stats.read_ops += 1;
+        }
+        PROGRESS_IO_BYTES.fetch_add(op.size as u64, Ordering::Relaxed);
+        PROGRESS_IO_OPS.fetch_add(1, Ordering::Relaxed);
+        stats.ops += 1;
+        index += 1;
+        let kind = if op.write { "write" } else { "read" };
+        if stats.record_latency(cfg, kind, op.offset, t0.elapsed()) {
+            break;
+        }
+        throttle_io(t0.elapsed());
+    }
+
+    stats.elapsed = measured_from.elapsed();
+    let mut line = format!(
+        "[{}] {} | {} ops | mode=trace",
+        worker_label(cfg, stats.worker_id, path),
+        format_rate(stats.total_mbps(), cfg.precision),
+        stats.ops
+    );
+    if passes > 0 {
+        line.push_str(&format!(" | trace completed {}x ({} ops)", passes, ops.len()));
+    } else {
+        line.push_str(&format!(" | trace incomplete ({}/{} ops)", index, ops.len()));
+    }
+    if let Some(ttfb) = stats.ttfb {
+        line.push_str(&format!(" | ttfb {:.0}us", ttfb.as_secs_f64() * 1e6));
+    }
+    line.push_str(&latency_summary(&stats, cfg));
+    if !stats.paused.is_zero() {
+        line.push_str(&format!(" | paused {:.1}s", stats.paused.as_secs_f64()));
+    }
+    report!("{}", line);
+
+    Ok(stats)
+}
+
+/// Hidden flag that turns a stressr process into a single I/O worker.
+const WORKER_CHILD_FLAG: &str = "--io-worker-child";
+
+/// Entry point of a worker process: run one worker and report its stats on stdout.
+fn run_worker_child(path: &str, worker_id: usize, cfg: &Config) -> Result<(), StressError> {
+    let _monitor = cfg.io_pause_below_mb.map(MemoryPressureMonitor::start);
+    let live = cfg.progress_secs.is_some() || cfg.stats_addr.is_some() || cfg.tui;
+    let _forwarder = live.then(ProgressForwarder::start);
+    install_interrupt_handler();
+    if cfg.signal_throttle {
+        install_throttle_signals();
+    }
+    if cfg.io_metadata {
+        let stats = metadata_worker(path, worker_id, cfg).map_err(|e| {
+            StressError::Failed(format!("[Metadata Worker {}] {}", worker_id, e))
+        })?;
+        if cfg.io_churn {
+            println!("{}", stats.churn_stats(path).to_wire());
+        }
+        return Ok(());
+    }
+    let result = match &cfg.io_trace {
+        Some(trace) => match io_trace_ops(trace) {
+            Ok(ops) => {
+                trace_worker(path, worker_id, cfg, ops).map_err(|e| StressError::io(path, e))
+            }
+            Err(e) => Err(StressError::InvalidConfig(format!("cannot load I/O trace {}", e))),
+        },
+        None => disk_io_worker(path, worker_id, cfg),
+    };
+    let stats =
+        result.map_err(|e| StressError::Failed(format!("[I/O Worker {}] {}", worker_id, e)))?;
+    println!("{}", stats.to_wire());
+    Ok(())
+}
+
+/// Worker processes whose output is still being read, with their path and
+/// worker id, so that `kill_abandoned_processes` can reach them.
+static WORKER_PROCESSES: Mutex<Vec<(String, usize, Child)>> = Mutex::new(Vec::new());
+
+fn worker_processes() -> std::sync::MutexGuard<'static, Vec<(String, usize, Child)>> {
+    WORKER_PROCESSES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Kills the worker processes still running once their workers have been
+/// abandoned under `--grace`, and removes the files they leave behind.
+fn kill_abandoned_processes(cfg: &Config) {
+    let abandoned = std::mem::take(&mut *worker_processes());
+    for (path, worker_id, mut child) in abandoned {
+        let _ = child.kill();
+        let _ = child.wait();
+        info!("[I/O Worker {}] on {}: killed the worker process", worker_id, path);
+        remove_worker_files(cfg, &path, worker_id);
+    }
+}
+
+/// Removes the files worker `worker_id` made in `path`, for a worker process
+/// that was killed before it could clean up.
+fn remove_worker_files(cfg: &Config, path: &str, worker_id: usize) {
+    if cfg.io_metadata {
+        remove_metadata_files(path, worker_id);
+        return;
+    }
+    if cfg.io_keep || cfg.io_reuse {
+        return;
+    }
+    let (single, prefix) = (worker_file_name(worker_id, 0, 1), format!("worker_{}_", worker_id));
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name == single || name.starts_with(&prefix) {
+            let _ = remove_file(entry.path());
+        }
+    }
+}
+
+/// Re-launches this binary as a worker process running `cfg` for one (path,
+/// worker) pair, forwarding its output and collecting its stats.
+fn run_worker_process(path: &str, worker_id: usize, cfg: &Config) -> Option<WorkerStats> {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!("[I/O Worker {}] cannot locate own executable: {}", worker_id, e);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+            return None;
+        }
+    };
+
+    // Everything the worker runs comes from `cfg`; STRESSR_* variables
+    // would be applied underneath it.
+    let mut command = Command::new(exe);
+    for (key, _) in env::vars_os() {
+        if key.to_string_lossy().starts_with(ENV_PREFIX) {
+            command.env_remove(key);
+        }
+    }
+    let child = command
+        .args(cfg.to_args())
+        .arg(WORKER_CHILD_FLAG)
+        .arg(worker_id.to_string())
+        .arg(path)
+        .stdout(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            error!("[I/O Worker {}] failed to spawn worker process: {}", worker_id, e);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+            return None;
+        }
+    };
+
+    let pid = child.id();
+    let stdout = child.stdout.take();
+    worker_processes().push((path.to_string(), worker_id, child));
+    let mut stats = None;
+    if let Some(stdout) = stdout {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some((bytes, ops)) = progress_from_wire(&line) {
+                PROGRESS_IO_BYTES.fetch_add(bytes, Ordering::Relaxed);
+                PROGRESS_IO_OPS.fetch_add(ops, Ordering::Relaxed);
+                continue;
+            }
+            match WorkerStats::from_wire(&line) {
+                Some(s) => stats = Some(WorkerStats { path: path.to_string(), ..s }),
+                None => log::emit(false, line),
+            }
+        }
+    }
+
+    // Gone from the list when the worker was abandoned and its process killed.
+    let mut processes = worker_processes();
+    let index = processes.iter().position(|(_, _, child)| child.id() == pid)?;
+    let (_, _, mut child) = processes.swap_remove(index);
+    drop(processes);
+    match child.wait() {
+        Ok(status) if !status.success() => {
+            error!("[I/O Worker {}] worker process exited with {}", worker_id, status);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+        }
+        Err(e) => {
+            error!("[I/O Worker {}] failed to wait for worker process: {}", worker_id, e);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+
+    stats
+}
+
+/// Per-worker file size in MB when each of `workers` files gets `percent`% of
+/// `free_bytes` between them.
+fn size_from_free_space(free_bytes: u64, percent: u64, workers: usize) -> u64 {
+    let share = (free_bytes as u128 * percent as u128 / 100) as u64;
+    share / workers.max(1) as u64 / (1024 * 1024)
+}
+
+/// The worker file size in bytes to use on `path`, honouring `--io-size-percent`.
+fn resolve_io_size(cfg: &Config, path: &str) -> u64 {
+    let Some(percent) = cfg.io_size_percent else {
+        return cfg.io_size_bytes;
+    };
+
+    match available_space_bytes(path) {
+        Some(free) => {
+            let size_mb = size_from_free_space(free, percent, cfg.io_workers).max(1);
+            info!(
+                "I/O: {}: {} MB per worker ({}% of {} MB free across {} workers)",
+                path,
+                size_mb,
+                percent,
+                free / (1024 * 1024),
+                cfg.io_workers
+            );
+            size_mb * 1024 * 1024
+        }
+        None => {
+            warn!(
+                "I/O: {}: cannot determine free space, using --io-size {} MB",
+                path,
+                cfg.io_size_bytes / (1024 * 1024)
+            );
+            cfg.io_size_bytes
+        }
+    }
+}
+
+/// One summary line for a group of workers that ran concurrently: their
+/// throughputs add up, so the total is a sum rather than an average.
+fn io_summary_line(label: &str, stats: &[WorkerStats], precision: usize) -> String {
+    let total = io_totals(stats);
+    let mut line = format!(
+        "[{}] {} | {} ops | {} MB | {} workers",
+        label,
+        format_rate(total.total_mbps(), precision),
+        total.ops,
+        (total.read_bytes + total.write_bytes) / (1024 * 1024),
+        stats.len()
+    );
+    // Only worth a mention when workers spread over several files each.
+    if total.files > stats.len() as u64 {
+        line.push_str(&format!(" | {} files", total.files));
+    }
+    if let (Some(p50), Some(p99)) = (total.latency_percentile(0.50), total.latency_percentile(0.99))
+    {
+        line.push_str(&format!(
+            " | latency p50 {} p99 {}",
+            format_latency(p50),
+            format_latency(p99)
+        ));
+    }
+    line
+}
+
+/// The workers in `stats` combined: counters summed, latency histograms
+/// merged, and an elapsed time that makes `total_mbps` the sum of theirs.
+fn io_totals<'a>(stats: impl IntoIterator<Item = &'a WorkerStats>) -> WorkerStats {
+    let mut total = WorkerStats::default();
+    let mut mbps = 0.0;
+    for s in stats {
+        total.ops += s.ops;
+        total.read_ops += s.read_ops;
+        total.write_ops += s.write_ops;
+        total.read_bytes += s.read_bytes;
+        total.write_bytes += s.write_bytes;
+        total.max_latency = total.max_latency.max(s.max_latency);
+        total.latency_sum += s.latency_sum;
+        total.latency_samples += s.latency_samples;
+        total.latency_hist.merge(&s.latency_hist);
+        total.read_corrupt_bytes += s.read_corrupt_bytes;
+        total.verify_errors += s.verify_errors;
+        total.files += s.files;
+        mbps += s.total_mbps();
+    }
+    let bytes = (total.read_bytes + total.write_bytes) as f64 / (1024.0 * 1024.0);
+    if mbps > 0.0 {
+        total.elapsed = Duration::from_secs_f64(bytes / mbps);
+    }
+    total
+}
+
+/// `io_totals` for each of `--io-paths`, in order, with its worker count.
+fn io_path_totals(cfg: &Config, stats: &[WorkerStats]) -> Vec<(String, usize, WorkerStats)> {
+    cfg.io_paths
+        .iter()
+        .map(|path| {
+            let workers = stats.iter().filter(|s| &s.path == path).count();
+            (path.clone(), workers, io_totals(stats.iter().filter(|s| &s.path == path)))
+        })
+        .collect()
+}
+
+/// How a worker is named in its result line; with several `--io-paths` the
+/// ids repeat per path, so the path is part of it.
+fn worker_label(cfg: &Config, worker_id: usize, path: &str) -> String {
+    if cfg.io_paths.len() > 1 {
+        format!("I/O Worker {} {}", worker_id, path)
+    } else {
+        format!("I/O Worker {}", worker_id)
+    }
+}
+
+/// Runs `io_workers` workers on each of `io_paths` and reports the combined
+/// throughput, with per-path subtotals when there are several paths. A worker
+/// that fails is reported and the rest carry on; the first error comes back
+/// only when no worker produced stats.
+pub fn run_disk_io(cfg: &Config) -> Result<Vec<WorkerStats>, StressError> {
+    if cfg.warmup_secs > 0 {
+        info!(
+            "I/O: the first {}s of {}s are warmup and left out of the results",
+            cfg.warmup_secs, cfg.io_duration_secs
+        );
+    }
+    let _monitor = if cfg.io_processes {
+        None
+    } else {
+        cfg.io_pause_below_mb.map(MemoryPressureMonitor::start)
+    };
+    let mut handles = vec![];
+    let deadline = grace_deadline(cfg, cfg.io_duration_secs);
+    let first_error = Arc::new(Mutex::new(None));
+
+    for path in &cfg.io_paths {
+        let size_bytes = resolve_io_size(cfg, path);
+        let mut path_handles = vec![];
+        for id in 0..cfg.io_workers {
+            let path = path.clone();
+            let cfg = Config {
+                io_size_bytes: size_bytes,
+                ..cfg.clone()
+            };
+
+            let name = format!("stressr-io-{}-{}", path, id);
+            let label = format!("[I/O Worker {}] on {}", id, path);
+            let first_error = Arc::clone(&first_error);
+            path_handles.push(Worker::spawn(name, label, move || {
+                if cfg.io_processes {
+                    return run_worker_process(&path, id, &cfg);
+                }
+                // Metadata workers report their own results; churn also
+                // counts toward the I/O totals.
+                let result = match &cfg.io_trace {
+                    _ if cfg.io_metadata => metadata_worker(&path, id, &cfg)
+                        .map(|stats| cfg.io_churn.then(|| stats.churn_stats(&path))),
+                    Some(trace) => match io_trace_ops(trace) {
+                        Ok(ops) => trace_worker(&path, id, &cfg, ops)
+                            .map(Some)
+                            .map_err(|e| StressError::io(&path, e)),
+                        Err(e) => Err(StressError::InvalidConfig(e)),
+                    },
+                    None => disk_io_worker(&path, id, &cfg).map(Some),
+                };
+                // One bad path (read-only, full) must not take the other workers down.
+                match result {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        error!("[I/O Worker {}] {}", id, e);
+                        WORKER_FAILED.store(true, Ordering::Relaxed);
+                        first_error.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert(e);
+                        None
+                    }
+                }
+            }));
+        }
+        handles.push((path.clone(), path_handles));
+    }
+
+    let by_path: Vec<(String, Vec<WorkerStats>)> = handles
+        .into_iter()
+        .map(|(path, hs)| {
+            let stats = hs.into_iter().filter_map(|h| h.join_by(deadline).flatten()).collect();
+            (path, stats)
+        })
+        .collect();
+    if cfg.io_processes {
+        kill_abandoned_processes(cfg);
+    }
+
+    if by_path.len() > 1 {
+        for (path, stats) in by_path.iter().filter(|(_, stats)| !stats.is_empty()) {
+            report!("{}", io_summary_line(&format!("I/O {}", path), stats, cfg.precision));
+        }
+    }
+    let all: Vec<WorkerStats> = by_path.into_iter().flat_map(|(_, stats)| stats).collect();
+    if !all.is_empty() {
+        report!("{}", io_summary_line("I/O Total", &all, cfg.precision));
+    }
+    if cfg.io_churn && !all.is_empty() {
+        let files: u64 = all.iter().map(|s| s.ops).sum();
+        let rate: f64 = all.iter().map(WorkerStats::ops_per_sec).sum();
+        report!("I/O churn: {:.0} files/s in total | {} files churned", rate, files);
+    }
+    if let Some(mix) = cfg.io_rwmix {
+        let read_ops: u64 = all.iter().map(|s| s.read_ops).sum();
+        let write_ops: u64 = all.iter().map(|s| s.write_ops).sum();
+        report!(
+            "I/O mix: {} reads, {} writes ({:.1}% reads, target {}%)",
+            read_ops,
+            write_ops,
+            read_ops as f64 / (read_ops + write_ops).max(1) as f64 * 100.0,
+            mix
+        );
+    }
+    match first_error.lock().unwrap_or_else(|e| e.into_inner()).take() {
+        Some(e) if all.is_empty() => Err(e),
+        _ => Ok(all),
+    }
+}
+
+/// Bytes per network `write`/`read` call.
+const NET_BUFFER: usize = 64 * 1024;
+
+/// How long the server's accept loop and drain threads wait before checking
+/// whether the run is over.
+const NET_POLL: Duration = Duration::from_millis(50);
+
+/// Delay before a client connection retries after a failed connect or write.
+const NET_RETRY: Duration = Duration::from_millis(200);
+
+/// Totals of one side of `--net`: bytes sent (client) or received (server),
+/// connections completed (client) or accepted (server), and failed ones.
+#[derive(Debug, Default, Clone)]
+pub struct NetStats {
+    pub server: bool,
+    pub threads: usize,
+    pub bytes: u64,
+    pub connections: u64,
+    pub errors: u64,
+    pub elapsed: Duration,
+}
+
+impl NetStats {
+    pub fn gbps(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.bytes as f64 * 8.0 / secs / 1e9 } else { 0.0 }
+    }
+
+    pub fn connections_per_sec(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.connections as f64 / secs } else { 0.0 }
+    }
+
+    fn role(&self) -> &'static str {
+        if self.server { "server" } else { "client" }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{:.3} Gbps | {} MB | {} connections ({:.1}/s) | {} errors",
+            self.gbps(),
+            self.bytes / (1024 * 1024),
+            self.connections,
+            self.connections_per_sec(),
+            self.errors
+        )
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("role", self.role().into()),
+            ("threads", self.threads.into()),
+            ("gbps", self.gbps().into()),
+            ("bytes", self.bytes.into()),
+            ("connections", self.connections.into()),
+            ("connections_per_sec", self.connections_per_sec().into()),
+            ("errors", self.errors.into()),
+            ("elapsed_secs", self.elapsed.as_secs_f64().into()),
+        ])
+    }
+}
+
+/// Reads and discards what one client sends until it disconnects or the
+/// server stops, adding the bytes to `received`.
+fn drain_connection(
+    mut stream: std::net::TcpStream,
+    received: &AtomicU64,
+    deadline: Instant,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(NET_POLL))?;
+    let mut buf = vec![0u8; NET_BUFFER];
+    while running() && Instant::now() < deadline {
+        match stream.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => {
+                received.fetch_add(n as u64, Ordering::Relaxed);
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// `--net-server`: accepts connections for `--net-duration`, with a thread
+/// per connection draining it.
+fn run_net_server(listener: std::net::TcpListener, cfg: &Config) -> std::io::Result<NetStats> {
+    listener.set_nonblocking(true)?;
+    info!("Net: listening on {}", listener.local_addr()?);
+
+    let received = Arc::new(AtomicU64::new(0));
+    let errors = Arc::new(AtomicU64::new(0));
+    let mut accepted = 0u64;
+    let mut handles = Vec::new();
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(cfg.net_duration_secs);
+
+    while running() && Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                accepted += 1;
+                stream.set_nonblocking(false)?;
+                let received = received.clone();
+                let errors = errors.clone();
+                handles.push(spawn_named(format!("stressr-net-srv-{}", accepted), move || {
+                    if let Err(e) = drain_connection(stream, &received, deadline) {
+                        error!("[Net Server] connection from {} failed: {}", addr, e);
+                        errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(NET_POLL),
+            Err(e) => {
+                error!("[Net Server] accept failed: {}", e);
+                errors.fetch_add(1, Ordering::Relaxed);
+                thread::sleep(NET_POLL);
+            }
+        }
+    }
+    let threads = handles.len();
+    for handle in handles {
+        join_worker(handle);
+    }
+
+    Ok(NetStats {
+        server: true,
+        threads,
+        bytes: received.load(Ordering::Relaxed),
+        connections: accepted,
+        errors: errors.load(Ordering::Relaxed),
+        elapsed: start.elapsed(),
+    })
+}
+
+/// One `--net-connect` connection thread: connects, sends `--net-size`
+/// bytes, and reconnects until `deadline`. Refused connects and failed
+/// writes are counted and retried; only the first is printed.
+fn net_client_connection(
+    addrs: &[std::net::SocketAddr],
+    conn_id: usize,
+    cfg: &Config,
+    deadline: Instant,
+) -> NetStats {
+    let mut payload = vec![0u8; NET_BUFFER];
+    Rng::for_stream(cfg.seed, conn_id as u64).fill(&mut payload);
+    let mut stats = NetStats { threads: 1, ..NetStats::default() };
+    let start = Instant::now();
+    let fail = |stats: &mut NetStats, what: &str, e: std::io::Error| {
+        // A connect or send cut short by the end of the run is not a failure.
+        if !running() || Instant::now() >= deadline {
+            return;
+        }
+        if stats.errors == 0 {
+            warn!("[Net Conn {}] {} failed: {} (retrying)", conn_id, what, e);
+        }
+        stats.errors += 1;
+        sleep_while_running(NET_RETRY.min(deadline.saturating_duration_since(Instant::now())));
+    };
+
+    'connections: while running() && Instant::now() < deadline {
+        let timeout = deadline.saturating_duration_since(Instant::now()).max(NET_POLL);
+        let mut connected = Err(std::io::Error::new(ErrorKind::NotFound, "no addresses"));
+        for addr in addrs {
+            connected = std::net::TcpStream::connect_timeout(addr, timeout);
+            if connected.is_ok() {
+                break;
+            }
+        }
+        let mut stream = match connected {
+            Ok(stream) => stream,
+            Err(e) => {
+                fail(&mut stats, "connect", e);
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        let _ = stream.set_write_timeout(Some(NET_POLL));
+
+        let mut sent = 0u64;
+        while sent < cfg.net_size_bytes {
+            if !running() || Instant::now() >= deadline {
+                break 'connections;
+            }
+            let len = (cfg.net_size_bytes - sent).min(NET_BUFFER as u64) as usize;
+            match stream.write(&payload[..len]) {
+                // write() may take only part of the buffer; the rest goes next time.
+                Ok(n) => sent += n as u64,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    stats.bytes += sent;
+                    fail(&mut stats, "send", e);
+                    continue 'connections;
+                }
+            }
+        }
+        stats.bytes += sent;
+        stats.connections += 1;
+    }
+    stats.elapsed = start.elapsed();
+    stats
+}
+
+/// `--net-connect`: `--net-conns` threads sending to the server at `addr`.
+fn run_net_client(addr: &str, cfg: &Config) -> std::io::Result<NetStats> {
+    use std::net::ToSocketAddrs;
+    let addrs: Arc<Vec<std::net::SocketAddr>> = Arc::new(addr.to_socket_addrs()?.collect());
+    info!("Net: {} connections to {}", cfg.net_conns, addr);
+
+    let deadline = Instant::now() + Duration::from_secs(cfg.net_duration_secs);
+    let handles: Vec<_> = (0..cfg.net_conns)
+        .map(|conn_id| {
+            let addrs = addrs.clone();
+            let cfg = cfg.clone();
+            spawn_named(format!("stressr-net-{}", conn_id), move || {
+                net_client_connection(&addrs, conn_id, &cfg, deadline)
+            })
+        })
+        .collect();
+
+    let mut total = NetStats::default();
+    for (conn_id, handle) in handles.into_iter().enumerate() {
+        let Some(stats) = join_worker(handle) else {
+            continue;
+        };
+        report!("[Net Conn {}] {}", conn_id, stats.summary());
+        total.threads += 1;
+        total.bytes += stats.bytes;
+        total.connections += stats.connections;
+        total.errors += stats.errors;
+        total.elapsed = total.elapsed.max(stats.elapsed);
+    }
+    Ok(total)
+}
+
+/// Runs the `--net` role `cfg` asks for and reports its totals. A client
+/// that never got a byte through marks the run as failed.
+pub fn stress_net(cfg: &Config) -> Option<NetStats> {
+    let result = match (&cfg.net_server, &cfg.net_connect) {
+        (Some(bind), _) => {
+            std::net::TcpListener::bind(bind).and_then(|listener| run_net_server(listener, cfg))
+        }
+        (None, Some(addr)) => run_net_client(addr, cfg),
+        (None, None) => return None,
+    };
+    match result {
+        Ok(stats) => {
+            report!("[Net {}] {}", if stats.server { "Server" } else { "Total" }, stats.summary());
+            if !stats.server && stats.bytes == 0 {
+                error!("Net: no data reached the server");
+                WORKER_FAILED.store(true, Ordering::Relaxed);
+            }
+            Some(stats)
+        }
+        Err(e) => {
+            error!("Net: {}", e);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/// What a participant reports back to the coordinator after its run.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct PeerResult {
+    name: String,
+    io_workers: u64,
+    io_mbps: f64,
+    io_ops: u64,
+    io_bytes: u64,
+}
+
+impl PeerResult {
+    fn from_io_stats(name: &str, stats: &[WorkerStats]) -> Self {
+        PeerResult {
+            name: name.to_string(),
+            io_workers: stats.len() as u64,
+            io_mbps: stats.iter().map(WorkerStats::total_mbps).sum(),
+            io_ops: stats.iter().map(|s| s.ops).sum(),
+            io_bytes: stats.iter().map(|s| s.read_bytes + s.write_bytes).sum(),
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "RESULT io_workers={} io_mbps={} io_ops={} io_bytes={}",
+            self.io_workers, self.io_mbps, self.io_ops, self.io_bytes
+        )
+    }
+
+    fn parse_line(name: &str, line: &str) -> Option<Self> {
+        let mut result = PeerResult {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        for field in line.strip_prefix("RESULT ")?.split_whitespace() {
+            let (key, value) = field.split_once('=')?;
+            match key {
+                "io_workers" => result.io_workers = value.parse().ok()?,
+                "io_mbps" => result.io_mbps = value.parse().ok()?,
+                "io_ops" => result.io_ops = value.parse().ok()?,
+                "io_bytes" => result.io_bytes = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(result)
+    }
+}
+
+/// A name for this host to report to the coordinator.
+fn host_name() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| format!("pid-{}", std::process::id()))
+}
+
+/// Barrier coordinator: waits for `peers` participants to send `READY <name>`,
+/// releases them all at once with `GO`, then collects one `RESULT` line each.
+fn run_coordinator(listener: std::net::TcpListener, peers: usize) -> Vec<PeerResult> {
+    let mut participants = Vec::new();
+
+    while participants.len() < peers {
+        let (stream, addr) = match listener.accept() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Coordinator: accept failed: {}", e);
+                continue;
+            }
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() {
+            continue;
+        }
+        let Some(name) = line.trim().strip_prefix("READY ").map(str::to_string) else {
+            warn!("Coordinator: ignoring {} (bad greeting {:?})", addr, line.trim());
+            continue;
+        };
+        info!(
+            "Coordinator: {} checked in from {} ({}/{})",
+            name,
+            addr,
+            participants.len() + 1,
+            peers
+        );
+        participants.push((name, reader));
+    }
+
+    info!("Coordinator: all {} participants ready, starting", peers);
+    for (name, reader) in participants.iter_mut() {
+        if let Err(e) = reader.get_mut().write_all(b"GO\n") {
+            error!("Coordinator: failed to release {}: {}", name, e);
+        }
+    }
+
+    let handles: Vec<_> = participants
+        .into_iter()
+        .map(|(name, mut reader)| {
+            spawn_named(format!("stressr-coord-{}", name), move || {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(n) if n > 0 => PeerResult::parse_line(&name, line.trim()),
+                    _ => None,
+                }
+                .or_else(|| {
+                    warn!("Coordinator: {} did not report results", name);
+                    None
+                })
+            })
+        })
+        .collect();
+
+    handles.into_iter().filter_map(|h| h.join().unwrap()).collect()
+}
+
+fn print_coordinated_results(results: &[PeerResult], precision: usize) {
+    for r in results {
+        println!(
+            "[Peer {}] {} | {} ops | {} I/O workers",
+            r.name,
+            format_rate(r.io_mbps, precision),
+            r.io_ops,
+            r.io_workers
+        );
+    }
+    println!(
+        "[Aggregate] {} peers | {} | {} ops | {} MB total",
+        results.len(),
+        format_rate(results.iter().map(|r| r.io_mbps).sum(), precision),
+        results.iter().map(|r| r.io_ops).sum::<u64>(),
+        results.iter().map(|r| r.io_bytes).sum::<u64>() / (1024 * 1024)
+    );
+}
+
+/// Checks in with the coordinator and blocks until it says `GO`.
+fn wait_for_coordinator(addr: &str, name: &str) -> std::io::Result<std::net::TcpStream> {
+    let mut stream = std::net::TcpStream::connect(addr)?;
+    writeln!(stream, "READY {}", name)?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim() != "GO" {
+        return Err(std::io::Error::other(format!("unexpected reply {:?}", line.trim())));
+    }
+    Ok(stream)
+}
+
+/// Version of the structured result formats. Bump it whenever a field is
+/// renamed, removed or changes meaning so parsers can branch on it.
+const RESULT_FORMAT_VERSION: u32 = 2;
+
+/// Drop (in percent) of a higher-is-better metric flagged as a regression.
+const REGRESSION_PERCENT: f64 = 5.0;
+
+/// Where each run's headline metrics are saved for `--compare-last`.
+fn last_run_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".stressr").join("last"))
+}
+
+/// Headline metrics of a run, all higher-is-better.
+fn run_metrics(
+    cpu_stats: Option<&CpuStats>,
+    io_stats: &[WorkerStats],
+    net_stats: Option<&NetStats>,
+) -> Vec<(String, f64)> {
+    let mut metrics = Vec::new();
+    if let Some(cpu) = cpu_stats {
+        metrics.push(("cpu_mops".into(), cpu.mops()));
+    }
+    if !io_stats.is_empty() {
+        metrics.push(("io_mbps".into(), io_stats.iter().map(WorkerStats::total_mbps).sum()));
+        metrics.push(("io_ops".into(), io_stats.iter().map(|s| s.ops as f64).sum()));
+    }
+    if let Some(net) = net_stats {
+        metrics.push(("net_gbps".into(), net.gbps()));
+    }
+    metrics
+}
+
+fn parse_metrics(text: &str) -> Vec<(String, f64)> {
+    text.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| {
+            let (k, v) = l.split_once('=')?;
+            Some((k.trim().to_string(), v.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn format_metrics(metrics: &[(String, f64)]) -> String {
+    let mut out = format!("# stressr_result_version={}\n", RESULT_FORMAT_VERSION);
+    for (k, v) in metrics {
+        out.push_str(&format!("{}={}\n", k, v));
+    }
+    out
+}
+
+/// One line per metric present in both runs: old -> new and the percent change.
+fn compare_metrics(previous: &[(String, f64)], current: &[(String, f64)]) -> Vec<String> {
+    current
+        .iter()
+        .filter_map(|(key, new)| {
+            let (_, old) = previous.iter().find(|(k, _)| k == key)?;
+            let delta = if *old != 0.0 { (new - old) / old * 100.0 } else { 0.0 };
+            let flag = if delta <= -REGRESSION_PERCENT { "  REGRESSION" } else { "" };
+            Some(format!("{}: {:.2} -> {:.2} ({:+.1}%){}", key, old, new, delta, flag))
+        })
+        .collect()
+}
+
+/// Prints the comparison against the previous run (if asked) and saves this
+/// run's metrics for next time.
+fn compare_and_save_last(metrics: &[(String, f64)], compare: bool) {
+    let Some(path) = last_run_path() else {
+        return;
+    };
+
+    if compare {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                report!("Compared to previous run:");
+                for line in compare_metrics(&parse_metrics(&text), metrics) {
+                    report!("  {}", line);
+                }
+            }
+            Err(_) => info!("No previous run recorded at {}", path.display()),
+        }
+    }
+
+    if metrics.is_empty() {
+        return;
+    }
+    let saved = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, format_metrics(metrics)));
+    if let Err(e) = saved {
+        warn!("Could not save run results to {}: {}", path.display(), e);
+    }
+}
+
+/// One `--repeat` run's metrics: `run_metrics` plus the I/O latency
+/// percentiles across all workers.
+fn repeat_metrics(results: &StressResults) -> Vec<(String, f64)> {
+    let mut metrics = run_metrics(results.cpu.as_ref(), &results.io, results.net.as_ref());
+    let mut hist = LatencyHistogram::default();
+    for stats in &results.io {
+        hist.merge(&stats.latency_hist);
+    }
+    for (name, p) in [("p50", 0.50), ("p95", 0.95), ("p99", 0.99)] {
+        if let Some(latency) = hist.percentile(p) {
+            metrics.push((format!("io_latency_{}_ms", name), latency.as_secs_f64() * 1e3));
+        }
+    }
+    metrics
+}
+
+/// Min, max, mean and sample standard deviation of one metric over the runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Spread {
+    min: f64,
+    max: f64,
+    mean: f64,
+    stddev: f64,
+}
+
+impl Spread {
+    fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = if values.len() > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        Some(Spread {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean,
+            stddev: variance.sqrt(),
+        })
+    }
+
+    /// Standard deviation as a percentage of the mean.
+    fn cv_percent(&self) -> f64 {
+        if self.mean != 0.0 { self.stddev / self.mean.abs() * 100.0 } else { 0.0 }
+    }
+}
+
+/// The spread of each metric over the `--repeat` runs, in first-seen order.
+fn repeat_spreads(runs: &[Vec<(String, f64)>]) -> Vec<(String, Vec<f64>, Spread)> {
+    let mut keys: Vec<&str> = Vec::new();
+    for (key, _) in runs.iter().flatten() {
+        if !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+    keys.into_iter()
+        .filter_map(|key| {
+            let values: Vec<f64> = runs
+                .iter()
+                .filter_map(|run| run.iter().find(|(k, _)| k == key).map(|(_, v)| *v))
+                .collect();
+            let spread = Spread::of(&values)?;
+            Some((key.to_string(), values, spread))
+        })
+        .collect()
+}
+
+const CSV_HEADER: [&str; 12] = [
+    "timestamp",
+    "subsystem",
+    "worker_id",
+    "path",
+    "mode",
+    "mbps",
+    "ops",
+    "bytes",
+    "latency_p50_ms",
+    "latency_p99_ms",
+    "errors",
+    "labels",
+];
+
+/// `--csv` rows for one run: a row per I/O worker and an I/O total, then a
+/// row each for CPU, memory and network if they ran. Columns that do not
+/// apply to a subsystem are left empty; `errors` counts corrupt bytes found
+/// by `--io-verify` and `--io-verify-pass`, or failed network connections.
+/// Every row carries the `--label` tags.
+fn csv_rows(
+    cfg: &Config,
+    timestamp: u64,
+    cpu_stats: Option<&CpuStats>,
+    memory_stats: Option<&MemoryStats>,
+    io_stats: &[WorkerStats],
+    net_stats: Option<&NetStats>,
+) -> Vec<Vec<String>> {
+    let rate = |mbps: f64| format!("{:.*}", cfg.precision, mbps);
+    let latency = |latency: Option<Duration>| {
+        latency.map(|d| format!("{:.3}", d.as_secs_f64() * 1e3)).unwrap_or_default()
+    };
+    let mode = io_mode(cfg);
+    let labels = format_labels(&cfg.labels);
+    let mut rows = Vec::new();
+    let io_row = |worker: String, path: String, stats: &WorkerStats| {
+        vec![
+            timestamp.to_string(),
+            "io".to_string(),
+            worker,
+            path,
+            mode.clone(),
+            rate(stats.total_mbps()),
+            stats.ops.to_string(),
+            (stats.read_bytes + stats.write_bytes).to_string(),
+            latency(stats.latency_percentile(0.50)),
+            latency(stats.latency_percentile(0.99)),
+            (stats.read_corrupt_bytes + stats.verify_errors).to_string(),
+            labels.clone(),
+        ]
+    };
+
+    for stats in io_stats {
+        rows.push(io_row(stats.worker_id.to_string(), stats.path.clone(), stats));
+    }
+    if !io_stats.is_empty() {
+        // Totals' rates are the sum of the workers', like the summary lines.
+        if cfg.io_paths.len() > 1 {
+            for (path, workers, total) in io_path_totals(cfg, io_stats) {
+                if workers > 0 {
+                    rows.push(io_row("total".to_string(), path, &total));
+                }
+            }
+        }
+        rows.push(io_row("total".to_string(), cfg.io_paths.join(","), &io_totals(io_stats)));
+    }
+    if let Some(cpu) = cpu_stats {
+        let mut row = vec![String::new(); CSV_HEADER.len()];
+        row[0] = timestamp.to_string();
+        row[11] = labels.clone();
+        row[1] = "cpu".to_string();
+        row[4] = cfg.cpu_workload.name().to_string();
+        row[6] = cpu.ops.to_string();
+        rows.push(row);
+    }
+    if let Some(memory) = memory_stats {
+        let secs = memory.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut row = vec![String::new(); CSV_HEADER.len()];
+        row[0] = timestamp.to_string();
+        row[11] = labels.clone();
+        row[1] = "memory".to_string();
+        row[4] = if memory.threads > 1 { "stream" } else { "hold" }.to_string();
+        row[5] = rate(memory.streamed_bytes as f64 / secs / 1024.0 / 1024.0);
+        row[7] = memory.streamed_bytes.to_string();
+        rows.push(row);
+    }
+    if let Some(net) = net_stats {
+        let secs = net.elapsed.as_secs_f64().max(f64::EPSILON);
+        let mut row = vec![String::new(); CSV_HEADER.len()];
+        row[0] = timestamp.to_string();
+        row[11] = labels.clone();
+        row[1] = "net".to_string();
+        row[3] = cfg.net_server.clone().or_else(|| cfg.net_connect.clone()).unwrap_or_default();
+        row[4] = net.role().to_string();
+        row[5] = rate(net.bytes as f64 / secs / 1024.0 / 1024.0);
+        row[6] = net.connections.to_string();
+        row[7] = net.bytes.to_string();
+        row[10] = net.errors.to_string();
+        rows.push(row);
+    }
+    rows
+}
+
+/// Appends `rows` to the `--csv` file, writing the result format version
+/// and the header first if the file is new or empty.
+fn append_csv(path: &str, rows: &[Vec<String>]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut out = String::new();
+    if file.metadata()?.len() == 0 {
+        out.push_str(&format!("# stressr_result_version={}
+", RESULT_FORMAT_VERSION));
+        out.push_str(&csv::row(&CSV_HEADER));
+    }
+    for row in rows {
+        out.push_str(&csv::row(row));
+    }
+    file.write_all(out.as_bytes())
+}
+
+/// Memory percentage at or above which a run asks for confirmation.
+const CONFIRM_MEMORY_PERCENT: u64 = 90;
+
+fn is_raw_device(path: &str) -> bool {
+    path.starts_with("/dev/") || path.starts_with(r"\\.\")
+}
+
+/// Reasons a run should be confirmed before starting, and whether any of them
+/// is destructive enough to require `--yes` even without a terminal.
+fn confirmation_reasons(cfg: &Config) -> (Vec<String>, bool) {
+    let mut reasons = Vec::new();
+    let mut destructive = false;
+
+    if cfg.io_enabled {
+        let planned_mb = cfg.io_size_bytes / (1024 * 1024)
+            * cfg.io_workers as u64
+            * cfg.io_paths.len() as u64;
+        if planned_mb > cfg.confirm_threshold_mb {
+            reasons.push(format!(
+                "I/O files will total {} MB (threshold {} MB)",
+                planned_mb, cfg.confirm_threshold_mb
+            ));
+        }
+        for path in cfg.io_paths.iter().filter(|p| is_raw_device(p)) {
+            reasons.push(format!("{} looks like a raw device", path));
+            destructive = true;
+        }
+    }
+
+    if cfg.memory_percent >= CONFIRM_MEMORY_PERCENT {
+        reasons.push(format!("{}% of system memory will be allocated", cfg.memory_percent));
+    }
+
+    (reasons, destructive)
+}
+
+/// Asks the user to confirm risky runs. Non-interactive stdin is treated as
+/// automation and allowed through, except for destructive runs.
+fn confirm_run(cfg: &Config) -> bool {
+    let (reasons, destructive) = confirmation_reasons(cfg);
+    if reasons.is_empty() || cfg.assume_yes {
+        return true;
+    }
+
+    for reason in &reasons {
+        warn!("Warning: {}", reason);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        if destructive {
+            error!(
+                "Refusing to run a destructive test non-interactively; pass --yes to proceed"
+            );
+            return false;
+        }
+        return true;
+    }
+
+    eprint!("Proceed? [y/N] ");
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// What a run of `cfg` will do, resolved for this machine, for `--dry-run`.
+#[derive(Debug, Default)]
+struct RunPlan {
+    cpu_threads: usize,
+    memory_mb: u64,
+    /// (path, worker file size) for each `--io-paths` entry.
+    io_files: Vec<(String, u64)>,
+    /// Upper bound on bytes written when `--io-bandwidth` caps it.
+    io_write_limit_bytes: Option<u64>,
+    peak_memory_mb: u64,
+}
+
+impl RunPlan {
+    fn of(cfg: &Config) -> Self {
+        let mut plan = RunPlan::default();
+        let mut peak_bytes = 0u64;
+        if cfg.cpu_percent > 0 || cfg.cpu_system_percent.is_some() {
+            plan.cpu_threads = cpu_thread_count(cfg);
+            if cfg.cpu_workload == CpuWorkload::Cache {
+                peak_bytes += plan.cpu_threads as u64 * cfg.cache_size_mb * 1024 * 1024;
+            }
+        }
+        if cfg.memory_percent > 0 {
+            plan.memory_mb = memory_target_kb(cfg) / 1024;
+            peak_bytes += plan.memory_mb * 1024 * 1024;
+        }
+        if cfg.io_enabled {
+            let size = |p: &String| if cfg.io_churn { 0 } else { resolve_io_size(cfg, p) };
+            plan.io_files = cfg.io_paths.iter().map(|p| (p.clone(), size(p))).collect();
+            let workers = (cfg.io_paths.len() * cfg.io_workers) as u64;
+            let verify_chunk = if cfg.io_verify { cfg.read_chunk_bytes() } else { 0 };
+            let buffers = cfg.read_chunk_bytes() + cfg.write_chunk_bytes() + verify_chunk;
+            peak_bytes += workers * buffers as u64;
+            plan.io_write_limit_bytes = cfg
+                .io_bandwidth_mbps
+                .filter(|_| cfg.io_write)
+                .map(|mbps| (mbps * 1024.0 * 1024.0) as u64 * cfg.io_duration_secs);
+        }
+        if cfg.net_enabled {
+            peak_bytes += (cfg.net_conns.max(1) * NET_BUFFER) as u64;
+        }
+        plan.peak_memory_mb = peak_bytes.div_ceil(1024 * 1024);
+        plan
+    }
+
+    /// Bytes the worker files take on disk in total.
+    fn io_file_bytes(&self, cfg: &Config) -> u64 {
+        self.io_files.iter().map(|(_, size)| size * cfg.io_workers as u64).sum()
+    }
+
+    fn lines(&self, cfg: &Config) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.cpu_threads > 0 {
+            let load = match cfg.cpu_system_percent {
+                Some(target) => format!("holding {}% system utilization", target),
+                None => format!("@ {}%", cfg.cpu_percent),
+            };
+            lines.push(format!(
+                "CPU: {} threads {} ({} workload) for {}s",
+                self.cpu_threads,
+                load,
+                cfg.cpu_workload.name(),
+                cfg.duration_secs
+            ));
+        }
+        if cfg.memory_percent > 0 {
+            lines.push(format!(
+                "Memory: {} MB ({}% of {} memory) on {} thread(s) for {}s",
+                self.memory_mb,
+                cfg.memory_percent,
+                cfg.memory_use.name(),
+                cfg.memory_threads.max(1),
+                cfg.memory_duration_secs()
+            ));
+        }
+        for (path, size) in &self.io_files {
+            if cfg.io_churn {
+                lines.push(format!(
+                    "I/O: {}: {} workers churning {} KB files for {}s",
+                    path,
+                    cfg.io_workers,
+                    METADATA_FILE_BYTES / 1024,
+                    cfg.io_duration_secs
+                ));
+                continue;
+            }
+            lines.push(format!(
+                "I/O: {}: {} workers ({}, {}) for {}s, {} x {} MB files",
+                path,
+                cfg.io_workers,
+                io_mode(cfg),
+                if cfg.io_random { "random" } else { "sequential" },
+                cfg.io_duration_secs,
+                cfg.io_workers * cfg.io_files_per_worker,
+                size / cfg.io_files_per_worker.max(1) as u64 / (1024 * 1024)
+            ));
+        }
+        if !self.io_files.is_empty() && !cfg.io_churn {
+            let mut total =
+                format!("I/O: {} MB of worker files in total", self.io_file_bytes(cfg) >> 20);
+            if let Some(limit) = self.io_write_limit_bytes {
+                total.push_str(&format!(", at most {} MB written (--io-bandwidth)", limit >> 20));
+            }
+            lines.push(total);
+        }
+        if cfg.net_enabled {
+            let role = match (&cfg.net_server, &cfg.net_connect) {
+                (Some(bind), _) => format!("server on {}", bind),
+                (None, Some(addr)) => format!("{} connections to {}", cfg.net_conns, addr),
+                (None, None) => String::new(),
+            };
+            lines.push(format!("Network: {} for {}s", role, cfg.net_duration_secs));
+        }
+        lines.push(format!("Estimated peak memory: {} MB", self.peak_memory_mb));
+        lines
+    }
+
+    fn to_json(&self, cfg: &Config) -> Json {
+        Json::object(vec![
+            ("cpu_threads", self.cpu_threads.into()),
+            ("memory_mb", self.memory_mb.into()),
+            ("io_file_bytes", self.io_file_bytes(cfg).into()),
+            ("io_write_limit_bytes", self.io_write_limit_bytes.into()),
+            ("peak_memory_mb", self.peak_memory_mb.into()),
+        ])
+    }
+}
+
+/// `--dry-run`: checks that every I/O path can take the worker files, then
+/// prints the plan. Fails listing every path that cannot.
+fn dry_run(cfg: &Config) -> Result<(), StressError> {
+    if cfg.io_enabled {
+        check_io_paths(cfg)?;
+    }
+
+    let plan = RunPlan::of(cfg);
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        let out = Json::object(vec![
+            ("dry_run", true.into()),
+            ("config", cfg.to_json()),
+            ("plan", plan.to_json(cfg)),
+        ]);
+        println!("{}", out);
+    } else {
+        report!("Dry run: the configuration is valid; nothing was stressed");
+        for line in plan.lines(cfg) {
+            report!("Plan: {}", line);
+        }
+    }
+    Ok(())
+}
+
+/// How long the preflight runs the CPU kernel on every thread.
+const PREFLIGHT_CPU_TIME: Duration = Duration::from_secs(1);
+
+/// The preflight allocates and fills this fraction of the memory target...
+const PREFLIGHT_MEMORY_DIVISOR: u64 = 4;
+/// ...but never more than this many MB.
+const PREFLIGHT_MEMORY_MAX_MB: u64 = 1024;
+
+fn preflight_cpu(cfg: &Config) -> Result<String, String> {
+    let threads = cpu_thread_count(cfg);
+    let workload = if cfg.cpu_workload == CpuWorkload::Avx512 && !avx512_supported() {
+        CpuWorkload::Int
+    } else {
+        cfg.cpu_workload
+    };
+    let (cache_size_mb, seed) = (cfg.cache_size_mb, cfg.seed);
+    let mut handles = Vec::new();
+    for n in 0..threads {
+        let handle = thread::Builder::new()
+            .name(format!("stressr-cpu-{}", n))
+            .spawn(move || {
+                let mut chain = CacheChain::for_thread(workload, cache_size_mb, seed, n);
+                let start = Instant::now();
+                while start.elapsed() < PREFLIGHT_CPU_TIME {
+                    run_cpu_kernel(workload, &mut chain);
+                }
+            })
+            .map_err(|e| format!("cannot spawn CPU thread {}: {}", n, e))?;
+        handles.push(handle);
+    }
+    for h in handles {
+        h.join().map_err(|_| "CPU thread panicked".to_string())?;
+    }
+    Ok(format!("{} threads ran the {} kernel", threads, workload.name()))
+}
+
+/// Allocates and fills part of the memory target the way `stress_memory`
+/// does, then frees it.
+fn preflight_memory(cfg: &Config) -> Result<String, String> {
+    let target_mb = memory_target_kb(cfg).div_ceil(1024);
+    let probe_mb = (target_mb / PREFLIGHT_MEMORY_DIVISOR).clamp(1, PREFLIGHT_MEMORY_MAX_MB);
+    let mut allocator = BlockAllocator::new(cfg);
+    let mut rng = Rng::new(cfg.seed);
+    let mut blocks = Vec::new();
+    let mut allocated_mb = 0;
+    while allocated_mb < probe_mb {
+        let block = blocks.try_reserve(1).ok().and_then(|_| allocator.alloc(&mut rng));
+        let Some(block) = block else {
+            return Err(format!("allocation failed after {} of {} MB", allocated_mb, probe_mb));
+        };
+        allocated_mb += block.mb();
+        blocks.push(block);
+    }
+    Ok(format!("allocated and filled {} MB of the {} MB target", allocated_mb, target_mb))
+}
+
+fn preflight_io_path(cfg: &Config, path: &str) -> Result<String, String> {
+    if cfg.io_size_percent.is_none()
+        && !cfg.io_metadata
+        && !cfg.io_reuse
+        && let Some(free) = available_space_bytes(path)
+    {
+        let needed = cfg.io_size_bytes * cfg.io_workers as u64;
+        if free < needed {
+            return Err(format!(
+                "needs {} MB but only {} MB is free",
+                needed / (1024 * 1024),
+                free / (1024 * 1024)
+            ));
+        }
+    }
+
+    let probe = PathBuf::from(path).join(format!("stressr_preflight_{}.tmp", std::process::id()));
+    let mut written = vec![0u8; cfg.write_chunk_bytes()];
+    Rng::new(1).fill(&mut written);
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .create_new(true)
+            .read(true)
+            .write(true)
+            .open(&probe)?;
+        file.write_all(&written)?;
+        file.sync_all()?;
+        let mut read_back = vec![0u8; written.len()];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut read_back)?;
+        Ok::<bool, std::io::Error>(read_back == written)
+    })();
+    let _ = remove_file(&probe);
+
+    match result {
+        Ok(true) => Ok("write, fsync and read-back succeeded".to_string()),
+        Ok(false) => Err("data read back differs from what was written".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Runs a short smoke test of every enabled subsystem: CPU threads spawn and
+/// run the kernel, the memory target can be reserved, and each I/O path takes
+/// a write, fsync and read-back. Returns (subsystem, outcome) pairs.
+fn run_preflight(cfg: &Config) -> Vec<(String, Result<String, String>)> {
+    let mut results = Vec::new();
+    if cfg.cpu_percent > 0 || cfg.cpu_system_percent.is_some() {
+        results.push(("cpu".to_string(), preflight_cpu(cfg)));
+    }
+    if cfg.memory_percent > 0 {
+        results.push(("memory".to_string(), preflight_memory(cfg)));
+    }
+    if cfg.io_enabled {
+        for path in &cfg.io_paths {
+            results.push((format!("io {}", path), preflight_io_path(cfg, path)));
+        }
+    }
+    results
+}
+
+/// Length of each `--benchmark` probe.
+const BENCHMARK_PROBE: Duration = Duration::from_secs(3);
+
+/// Gain a `--benchmark` step must make over the best so far for the sweep to
+/// go on; anything less counts as the plateau.
+const BENCHMARK_MIN_GAIN: f64 = 0.05;
+
+/// Most I/O workers per path and deepest queue a `--benchmark` sweep tries.
+const BENCHMARK_MAX_IO_WORKERS: usize = 64;
+const BENCHMARK_MAX_QUEUE_DEPTH: usize = 256;
+
+/// Kernel iterations between clock reads in a CPU probe.
+const BENCHMARK_CPU_BATCH: u64 = 64;
+
+/// The values a sweep probes: `first` doubled up to `max`, plus the `extra`
+/// ones within that range, in order.
+fn sweep_points(first: usize, max: usize, extra: &[usize]) -> Vec<usize> {
+    let mut points: Vec<usize> = std::iter::successors(Some(first), |p| p.checked_mul(2))
+        .take_while(|&p| p <= max)
+        .collect();
+    points.extend(extra.iter().filter(|&&p| p >= first && p <= max));
+    points.sort_unstable();
+    points.dedup();
+    points
+}
+
+/// Probes each of `points` in turn until a step gains less than
+/// `BENCHMARK_MIN_GAIN`, a probe fails (returns None) or the run is
+/// interrupted. Returns the best (param, rate), or None if nothing was
+/// probed successfully.
+fn sweep_peak(
+    points: &[usize],
+    mut probe: impl FnMut(usize) -> Option<f64>,
+) -> Option<(usize, f64)> {
+    let mut best: Option<(usize, f64)> = None;
+    for &param in points {
+        if !running() {
+            break;
+        }
+        let Some(rate) = probe(param) else { break };
+        match best {
+            Some((_, peak)) if rate < peak * (1.0 + BENCHMARK_MIN_GAIN) => {
+                if rate > peak {
+                    best = Some((param, rate));
+                }
+                break;
+            }
+            _ => best = Some((param, rate)),
+        }
+    }
+    best
+}
+
+/// Runs `workload` flat out on `threads` threads for one probe and returns
+/// the aggregate kernel iterations per second, in millions, or None if a
+/// thread panicked.
+fn benchmark_cpu_probe(cfg: &Config, workload: CpuWorkload, threads: usize) -> Option<f64> {
+    let (cache_size_mb, seed) = (cfg.cache_size_mb, cfg.seed);
+    let start = Instant::now();
+    let handles: Vec<_> = (0..threads)
+        .map(|n| {
+            spawn_named(format!("stressr-cpu-{}", n), move || {
+                let mut chain = CacheChain::for_thread(workload, cache_size_mb, seed, n);
+                let mut iterations = 0u64;
+                while start.elapsed() < BENCHMARK_PROBE && running() {
+                    for _ in 0..BENCHMARK_CPU_BATCH {
+                        run_cpu_kernel(workload, &mut chain);
+                    }
+                    iterations += BENCHMARK_CPU_BATCH;
+                }
+                iterations
+            })
+        })
+        .collect();
+    let counts: Vec<_> = handles.into_iter().map(join_worker).collect();
+    let iterations: u64 = counts.into_iter().sum::<Option<u64>>()?;
+    Some(iterations as f64 / start.elapsed().as_secs_f64() / 1e6)
+}
+
+/// Runs the I/O workers once as `cfg` says for one probe, with only their
+/// warnings and errors shown, and returns the aggregate MB/s, or None if a
+/// worker failed.
+fn benchmark_io_probe(cfg: &Config) -> Option<f64> {
+    log::hold();
+    let stats = run_disk_io(cfg);
+    log::release_diagnostics();
+    let stats = stats.ok().filter(|_| !WORKER_FAILED.load(Ordering::Relaxed))?;
+    Some(stats.iter().map(WorkerStats::total_mbps).sum())
+}
+
+/// Whether queue depths above 1 run on io_uring here.
+fn queue_depth_supported() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        uring::Ring::new(2).is_ok()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// `--benchmark`: instead of running at the configured load, sweeps CPU
+/// threads, then I/O workers and queue depth, with short probes until each
+/// stops scaling, and reports the peak and where it was reached.
+fn run_benchmark(cfg: &Config) {
+    if cfg.memory_percent > 0 || cfg.net_enabled {
+        warn!("Benchmark: only CPU and I/O are swept; memory and network are skipped");
+    }
+    let probe_secs = BENCHMARK_PROBE.as_secs_f64();
+    let mut results = Vec::new();
+
+    if cfg.cpu_percent > 0 || cfg.cpu_system_percent.is_some() {
+        let workload = if cfg.cpu_workload == CpuWorkload::Avx512 && !avx512_supported() {
+            warn!("CPU: AVX-512 is not supported on this CPU, falling back to the int workload");
+            CpuWorkload::Int
+        } else {
+            cfg.cpu_workload
+        };
+        // Past the core count to show whether SMT siblings add anything.
+        let cpus = detect_cpu_count();
+        let max_threads = cpus * 2;
+        info!(
+            "Benchmark: CPU, {} workload, up to {} threads in {:.0}s probes",
+            workload.name(),
+            max_threads,
+            probe_secs
+        );
+        let points = sweep_points(1, max_threads, &[cpus, max_threads]);
+        let peak = sweep_peak(&points, |threads| {
+            let mops = benchmark_cpu_probe(cfg, workload, threads)?;
+            info!("Benchmark: CPU {} threads: {:.2} Mops/s", threads, mops);
+            Some(mops)
+        });
+        if let Some((threads, mops)) = peak {
+            report!(
+                "Benchmark: CPU peak {:.2} Mops/s with {} threads ({} workload)",
+                mops,
+                threads,
+                workload.name()
+            );
+            results.push((
+                "cpu",
+                Json::object(vec![
+                    ("workload", workload.name().into()),
+                    ("threads", threads.into()),
+                    ("mops", mops.into()),
+                ]),
+            ));
+        }
+    }
+
+    if cfg.io_enabled && running() && !WORKER_FAILED.load(Ordering::Relaxed) {
+        // Hold the data per path fixed, so only the parallelism changes.
+        let path_bytes = cfg.io_size_bytes * cfg.io_workers as u64;
+        let align = DIRECT_IO_ALIGN.max(cfg.write_chunk_bytes()) as u64;
+        let probe_cfg = |workers: usize, depth: usize| Config {
+            io_workers: workers,
+            io_queue_depth: depth,
+            io_size_bytes: (path_bytes / workers as u64 / align * align).max(align),
+            io_duration_secs: BENCHMARK_PROBE.as_secs(),
+            warmup_secs: 0,
+            ..cfg.clone()
+        };
+        info!(
+            "Benchmark: I/O ({}), up to {} workers per path in {:.0}s probes",
+            io_mode(cfg),
+            BENCHMARK_MAX_IO_WORKERS,
+            probe_secs
+        );
+        let counts = sweep_points(1, BENCHMARK_MAX_IO_WORKERS, &[]);
+        let by_workers = sweep_peak(&counts, |workers| {
+            let mbps = benchmark_io_probe(&probe_cfg(workers, 1))?;
+            info!("Benchmark: I/O {} workers: {}", workers, format_rate(mbps, cfg.precision));
+            Some(mbps)
+        });
+        let queued = probe_cfg(1, 2).validate().is_ok() && queue_depth_supported();
+        if !queued {
+            info!(
+                "Benchmark: I/O queue depth stays at 1 (it needs io_uring and none of \
+                 --io-verify, --io-fsync, --io-sync-mode fdatasync or --io-trace)"
+            );
+        }
+        let peak = by_workers.map(|(workers, mbps)| {
+            let by_depth = queued
+                .then(|| {
+                    let depths = sweep_points(2, BENCHMARK_MAX_QUEUE_DEPTH, &[]);
+                    sweep_peak(&depths, |depth| {
+                        let mbps = benchmark_io_probe(&probe_cfg(workers, depth))?;
+                        info!(
+                            "Benchmark: I/O {} workers at queue depth {}: {}",
+                            workers,
+                            depth,
+                            format_rate(mbps, cfg.precision)
+                        );
+                        Some(mbps)
+                    })
+                })
+                .flatten()
+                .filter(|&(_, queued_mbps)| queued_mbps > mbps);
+            match by_depth {
+                Some((depth, queued_mbps)) => (workers, depth, queued_mbps),
+                None => (workers, 1, mbps),
+            }
+        });
+        if let Some((workers, depth, mbps)) = peak {
+            report!(
+                "Benchmark: I/O peak {} with {} workers per path at queue depth {}",
+                format_rate(mbps, cfg.precision),
+                workers,
+                depth
+            );
+            results.push((
+                "io",
+                Json::object(vec![
+                    ("mode", io_mode(cfg).into()),
+                    ("workers", workers.into()),
+                    ("queue_depth", depth.into()),
+                    ("mbps", mbps.into()),
+                ]),
+            ));
+        }
+    }
+
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        let out = Json::object(vec![
+            ("benchmark", Json::object(results)),
+            ("config", cfg.to_json()),
+        ]);
+        println!("{}", out);
+    }
+}
+
+/// Runs one `--sequential` phase on its own thread, between a banner and a
+/// timing summary, waiting for it until the `deadline` worked out as it
+/// starts. Skipped once the run has been interrupted or a worker abandoned.
+fn run_phase<T: Send + 'static>(
+    name: &str,
+    phase: usize,
+    phases: usize,
+    deadline: impl FnOnce() -> Option<Instant>,
+    stress: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    if !running() || WORKER_ABANDONED.load(Ordering::Relaxed) {
+        return None;
+    }
+    info!("=== Phase {}/{}: {} ===", phase, phases, name);
+    let start = Instant::now();
+    let thread_name = format!("stressr-{}", name.to_lowercase());
+    let worker = Worker::spawn(thread_name, format!("{} stressor", name), stress);
+    let result = worker.join_by(deadline());
+    report!(
+        "Phase {}/{} ({}) finished in {:.1}s",
+        phase,
+        phases,
+        name,
+        start.elapsed().as_secs_f64()
+    );
+    result
+}
+
+/// What one pass of the stressors produced. A stressor that did not run, or
+/// whose thread failed, is left empty.
+#[derive(Default)]
+struct StressResults {
+    cpu: Option<CpuStats>,
+    memory: Option<MemoryStats>,
+    io: Vec<WorkerStats>,
+    net: Option<NetStats>,
+}
+
+/// `stress_memory` for the CLI, where an error fails the run but not the
+/// other stressors.
+fn run_memory(cfg: &Config) -> Option<MemoryStats> {
+    stress_memory(cfg)
+        .inspect_err(|e| {
+            error!("Memory: {}", e);
+            WORKER_FAILED.store(true, Ordering::Relaxed);
+        })
+        .ok()
+}
+
+/// `--sequential`: CPU, then memory, then I/O, then network, each run to
+/// completion.
+fn run_sequential(cfg: &Config, cpu_enabled: bool, memory_enabled: bool) -> StressResults {
+    let phases = [cpu_enabled, memory_enabled, cfg.io_enabled, cfg.net_enabled]
+        .iter()
+        .filter(|&&on| on)
+        .count();
+    let mut phase = 0;
+    let mut next_phase = || {
+        phase += 1;
+        phase
+    };
+
+    let cpu_stats = cpu_enabled
+        .then(|| {
+            let deadline = || grace_deadline(cfg, cfg.duration_secs);
+            let cfg = cfg.clone();
+            run_phase("CPU", next_phase(), phases, deadline, move || stress_cpu(&cfg))
+        })
+        .flatten();
+    let memory_stats = memory_enabled
+        .then(|| {
+            let deadline = || grace_deadline(cfg, cfg.memory_duration_secs());
+            let cfg = cfg.clone();
+            run_phase("Memory", next_phase(), phases, deadline, move || run_memory(&cfg))
+        })
+        .flatten()
+        .flatten();
+    let io_stats = cfg
+        .io_enabled
+        .then(|| {
+            let cfg = cfg.clone();
+            // run_disk_io abandons stuck I/O workers itself and has reported
+            // any that failed.
+            run_phase("I/O", next_phase(), phases, || None, move || run_disk_io(&cfg))
+        })
+        .flatten()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let net_stats = cfg
+        .net_enabled
+        .then(|| {
+            let deadline = || grace_deadline(cfg, cfg.net_duration_secs);
+            let cfg = cfg.clone();
+            run_phase("Network", next_phase(), phases, deadline, move || stress_net(&cfg))
+        })
+        .flatten()
+        .flatten();
+    StressResults {
+        cpu: cpu_stats,
+        memory: memory_stats,
+        io: io_stats,
+        net: net_stats,
+    }
+}
+
+/// One pass of every enabled stressor, all at once or `--sequential`, with
+/// `--progress` snapshots while it runs.
+fn run_stressors(cfg: &Config, cpu_enabled: bool, memory_enabled: bool) -> StressResults {
+    let _progress = cfg.progress_secs.map(|secs| {
+        let phase_secs = [
+            if cpu_enabled { cfg.duration_secs } else { 0 },
+            if memory_enabled { cfg.memory_duration_secs() } else { 0 },
+            if cfg.io_enabled { cfg.io_duration_secs } else { 0 },
+            if cfg.net_enabled { cfg.net_duration_secs } else { 0 },
+        ];
+        let total_secs = if cfg.sequential {
+            phase_secs.iter().sum()
+        } else {
+            phase_secs.into_iter().max().unwrap_or(0)
+        };
+        ProgressReporter::start(
+            Duration::from_secs(secs),
+            Duration::from_secs(total_secs),
+            cfg.precision,
+        )
+    });
+
+    if cfg.sequential {
+        return run_sequential(cfg, cpu_enabled, memory_enabled);
+    }
+
+    let cpu_deadline = grace_deadline(cfg, cfg.duration_secs);
+    let memory_deadline = grace_deadline(cfg, cfg.memory_duration_secs());
+    let net_deadline = grace_deadline(cfg, cfg.net_duration_secs);
+    let cpu_handle = cpu_enabled.then(|| {
+        let cfg = cfg.clone();
+        Worker::spawn("stressr-cpu".to_string(), "CPU stressor".to_string(), move || {
+            stress_cpu(&cfg)
+        })
+    });
+
+    let memory_handle = memory_enabled.then(|| {
+        let cfg = cfg.clone();
+        Worker::spawn("stressr-mem".to_string(), "Memory stressor".to_string(), move || {
+            run_memory(&cfg)
+        })
+    });
+
+    let io_handle = cfg.io_enabled.then(|| {
+        let cfg = cfg.clone();
+        spawn_named("stressr-io".to_string(), move || run_disk_io(&cfg))
+    });
+
+    let net_handle = cfg.net_enabled.then(|| {
+        let cfg = cfg.clone();
+        Worker::spawn("stressr-net".to_string(), "Network stressor".to_string(), move || {
+            stress_net(&cfg)
+        })
+    });
+
+    // run_disk_io abandons stuck I/O workers itself, so its thread is joined
+    // without a deadline; any that failed are already reported.
+    StressResults {
+        cpu: cpu_handle.and_then(|w| w.join_by(cpu_deadline)),
+        memory: memory_handle.and_then(|w| w.join_by(memory_deadline)).flatten(),
+        io: io_handle.and_then(join_worker).and_then(Result::ok).unwrap_or_default(),
+        net: net_handle.and_then(|w| w.join_by(net_deadline)).flatten(),
+    }
+}
+
+/// `Abandoned` or `Failed` once the run is over if any worker was abandoned
+/// or failed along the way.
+fn workers_outcome() -> Result<(), StressError> {
+    if WORKER_ABANDONED.load(Ordering::Relaxed) {
+        return Err(StressError::Abandoned);
+    }
+    if WORKER_FAILED.load(Ordering::Relaxed) {
+        return Err(StressError::Failed("One or more workers failed; see the errors above".into()));
+    }
+    Ok(())
+}
+
+/// Runs everything `cfg` asks for the way the CLI does: checks, stressors
+/// and reports. Returns the error that kept the run from starting, or
+/// `Failed`, `Abandoned` or `Interrupted` for a run that did not end well,
+/// for the caller to report and exit with [`StressError::exit_code`].
+pub fn run(cfg: Config) -> Result<(), StressError> {
+    let result = run_checked(cfg);
+    match &result {
+        Ok(()) => {}
+        Err(e @ (StressError::Failed(_) | StressError::Abandoned | StressError::Interrupted)) => {
+            log::note(format_args!("Run ended: {} (exit code {})", e, e.exit_code()))
+        }
+        Err(e) => log::note(format_args!("Run not started: {}", e)),
+    }
+    result
+}
+
+fn run_checked(mut cfg: Config) -> Result<(), StressError> {
+    // Start afresh after an earlier run in this process that failed, was
+    // interrupted or hit the latency limit.
+    RUNNING.store(true, Ordering::Relaxed);
+    WORKER_FAILED.store(false, Ordering::Relaxed);
+    WORKER_ABANDONED.store(false, Ordering::Relaxed);
+    LATENCY_ABORT.store(false, Ordering::Relaxed);
+    JSON_OUTPUT.store(cfg.output == OutputFormat::Json, Ordering::Relaxed);
+    log::set_level(cfg.log_level);
+
+    if let Some((id, path)) = &cfg.io_worker_child {
+        return run_worker_child(path, *id, &cfg);
+    }
+
+    if let Some(path) = &cfg.log_file {
+        log::open_file(path).map_err(|e| StressError::io(path, e))?;
+        log::note(format_args!("stressr v0.1.0 started (pid {})", std::process::id()));
+    }
+
+    if let Some(bind) = &cfg.coordinate_bind {
+        let listener = std::net::TcpListener::bind(bind).map_err(|e| StressError::io(bind, e))?;
+        info!("Coordinator: waiting for {} participants on {}", cfg.peers, bind);
+        let results = run_coordinator(listener, cfg.peers);
+        print_coordinated_results(&results, cfg.precision);
+        return Ok(());
+    }
+
+    debug!("Running stress test:\n{:#?}", cfg);
+    apply_numa_node(&mut cfg);
+    log::note(format_args!("Config: {}", cfg.to_json()));
+
+    if cfg.io_enabled {
+        check_io_paths(&cfg)?;
+        check_fd_limit(&cfg)?;
+        check_io_space(&cfg)?;
+    }
+
+    if cfg.io_enabled
+        && let Some(trace) = &cfg.io_trace
+    {
+        let ops = io_trace_ops(trace)
+            .map_err(|e| StressError::InvalidConfig(format!("cannot load I/O trace {}", e)))?;
+        info!("I/O: replaying {} operations from {}", ops.len(), trace);
+    }
+
+    if cfg.dry_run {
+        return dry_run(&cfg);
+    }
+
+    if !confirm_run(&cfg) {
+        return Err(StressError::Aborted("the run was not confirmed".into()));
+    }
+
+    if cfg.signal_throttle {
+        if install_throttle_signals() {
+            info!(
+                "Throttle: kill -TSTP {} halves the load, kill -CONT {} restores it",
+                std::process::id(),
+                std::process::id()
+            );
+        } else {
+            warn!("Throttle: signal handling is not supported on this platform");
+        }
+    }
+    if cfg.signal_adjust {
+        if install_adjust_signals() {
+            info!(
+                "Adjust: kill -USR1 {} raises the CPU target by {}%, kill -USR2 {} lowers it",
+                std::process::id(),
+                CPU_ADJUST_STEP,
+                std::process::id()
+            );
+        } else {
+            warn!("Adjust: signal handling is not supported on this platform");
+        }
+    }
+
+    match apply_resource_limits(&cfg) {
+        Ok(applied) if !applied.is_empty() => {
+            info!("Resource limits: {}", applied.join(", "))
+        }
+        Ok(_) => {}
+        Err(e) => {
+            return Err(StressError::Unsupported(format!(
+                "could not apply resource limits: {}",
+                e
+            )));
+        }
+    }
+
+    if cfg.preflight {
+        let results = run_preflight(&cfg);
+        for (subsystem, outcome) in &results {
+            match outcome {
+                Ok(detail) => report!("Preflight: {} OK ({})", subsystem, detail),
+                Err(e) => report!("Preflight: {} FAILED: {}", subsystem, e),
+            }
+        }
+        if results.iter().any(|(_, outcome)| outcome.is_err()) {
+            return Err(StressError::Aborted("preflight failed".into()));
+        }
+    }
+
+    let coordinator = cfg
+        .coordinator
+        .as_ref()
+        .map(|addr| {
+            info!("Waiting for coordinator {} to start the run", addr);
+            wait_for_coordinator(addr, &host_name()).map_err(|e| StressError::io(addr, e))
+        })
+        .transpose()?;
+
+    let labels = cfg.labels.clone();
+    let compare_last = cfg.compare_last;
+    let io_processes = cfg.io_enabled && cfg.io_processes;
+    let max_latency = cfg.io_max_latency_ms.filter(|_| cfg.io_enabled);
+    let latency_soft = cfg.io_max_latency_soft;
+    let target_iops = cfg.io_target_iops.filter(|_| cfg.io_enabled);
+    let io_rate = cfg.io_rate.filter(|_| cfg.io_enabled);
+    let io_bandwidth = cfg.io_bandwidth_mbps.filter(|_| cfg.io_enabled);
+    let precision = cfg.precision;
+    if !install_interrupt_handler() {
+        warn!("Ctrl-C handling is not available; an interrupt will discard results");
+    }
+    wait_for_start(&cfg);
+
+    if cfg.benchmark {
+        run_benchmark(&cfg);
+        if !running() {
+            return Err(StressError::Interrupted);
+        }
+        return workers_outcome();
+    }
+
+    let config_json = cfg.to_json();
+    let io_mode = io_mode(&cfg);
+    let energy_start = read_rapl_energy();
+    let run_start = Instant::now();
+    let cpu_enabled = cfg.cpu_percent > 0 || cfg.cpu_system_percent.is_some();
+    let memory_enabled = cfg.memory_percent > 0;
+    let stats_server = cfg
+        .stats_addr
+        .as_ref()
+        .map(|addr| StatsServer::start(addr, &cfg.labels).map_err(|e| StressError::io(addr, e)))
+        .transpose()?;
+    let tui = cfg.tui.then(|| {
+        if std::io::stdout().is_terminal() {
+            Some(TuiDashboard::start(precision))
+        } else {
+            warn!("--tui needs a terminal on stdout; using text output");
+            None
+        }
+    });
+    let mut runs = Vec::new();
+    let mut stats = StressResults::default();
+    for round in 1..=cfg.repeat {
+        if !running()
+            || LATENCY_ABORT.load(Ordering::Relaxed)
+            || WORKER_ABANDONED.load(Ordering::Relaxed)
+        {
+            break;
+        }
+        if cfg.repeat > 1 {
+            info!("=== Run {}/{} ===", round, cfg.repeat);
+        }
+        stats = run_stressors(&cfg, cpu_enabled, memory_enabled);
+        if running() {
+            runs.push(repeat_metrics(&stats));
+        }
+    }
+    drop(tui);
+    drop(stats_server);
+    let StressResults {
+        cpu: cpu_stats,
+        memory: memory_stats,
+        io: io_stats,
+        net: net_stats,
+    } = stats;
+    let elapsed = run_start.elapsed();
+
+    let spreads = repeat_spreads(&runs);
+    if cfg.repeat > 1 {
+        report!("Repeat: {} of {} runs completed", runs.len(), cfg.repeat);
+        for (key, _, spread) in &spreads {
+            let value = |v: f64| {
+                if key.ends_with("_ms") {
+                    format_latency(Duration::from_secs_f64(v / 1e3))
+                } else {
+                    format!("{:.*}", precision, v)
+                }
+            };
+            report!(
+                "  {}: mean {}, min {}, max {}, stddev {} ({:.1}%)",
+                key,
+                value(spread.mean),
+                value(spread.min),
+                value(spread.max),
+                value(spread.stddev),
+                spread.cv_percent()
+            );
+        }
+    }
+
+    let watts = match (energy_start, read_rapl_energy()) {
+        (Some(before), Some(after)) => Some(rapl_joules(&before, &after) / elapsed.as_secs_f64()),
+        _ => None,
+    };
+    if let Some(watts) = watts {
+        let mut line = format!("Power: {:.1} W average package power", watts);
+        if watts > 0.0 {
+            if !io_stats.is_empty() {
+                let mbps: f64 = io_stats.iter().map(WorkerStats::total_mbps).sum();
+                line.push_str(&format!(" | I/O {:.2} MB/s/W", mbps / watts));
+            }
+            if let Some(cpu) = &cpu_stats {
+                line.push_str(&format!(" | CPU {:.3} Mops/s/W", cpu.mops() / watts));
+            }
+        }
+        report!("{}", line);
+    }
+
+    if let Some(mut stream) = coordinator {
+        let result = PeerResult::from_io_stats(&host_name(), &io_stats);
+        if let Err(e) = writeln!(stream, "{}", result.to_line()) {
+            error!("Failed to report results to coordinator: {}", e);
+        }
+    }
+
+    if !labels.is_empty() {
+        report!("Labels: {}", format_labels(&labels));
+    }
+
+    let usage = resource_usage(false);
+    if let Some(usage) = &usage {
+        report!("Resource usage: {}", usage.summary());
+    }
+    if io_processes && let Some(usage) = resource_usage(true) {
+        report!("Resource usage (worker processes): {}", usage.summary());
+    }
+
+    let repeat_json = (cfg.repeat > 1).then(|| {
+        let metrics = spreads
+            .iter()
+            .map(|(key, values, spread)| {
+                let values = values.iter().map(|&v| Json::from(v)).collect();
+                let fields = vec![
+                    ("mean", spread.mean.into()),
+                    ("min", spread.min.into()),
+                    ("max", spread.max.into()),
+                    ("stddev", spread.stddev.into()),
+                    ("values", Json::Array(values)),
+                ];
+                (key.clone(), Json::object(fields))
+            })
+            .collect();
+        Json::object(vec![("runs", runs.len().into()), ("metrics", Json::Object(metrics))])
+    });
+
+    let mut checks = Vec::new();
+    let results_json = |checks: Vec<(&str, Json)>| {
+        let labels = labels.iter().map(|(k, v)| (k.clone(), Json::from(v))).collect();
+        let io = (!io_stats.is_empty()).then(|| {
+            let workers: Vec<Json> = io_stats.iter().map(|s| s.to_json(&io_mode)).collect();
+            let paths: Vec<Json> = io_path_totals(&cfg, &io_stats)
+                .into_iter()
+                .filter(|(_, workers, _)| *workers > 0)
+                .map(|(path, workers, total)| {
+                    Json::object(vec![
+                        ("path", path.into()),
+                        ("workers", workers.into()),
+                        ("mbps", total.total_mbps().into()),
+                        ("ops", total.ops.into()),
+                        ("bytes", (total.read_bytes + total.write_bytes).into()),
+                        ("latency_p50_ms", total.latency_percentile_ms(0.50).into()),
+                        ("latency_p99_ms", total.latency_percentile_ms(0.99).into()),
+                    ])
+                })
+                .collect();
+            Json::object(vec![
+                ("total_mbps", io_stats.iter().map(WorkerStats::total_mbps).sum::<f64>().into()),
+                ("total_ops", io_stats.iter().map(|s| s.ops).sum::<u64>().into()),
+                ("paths", Json::Array(paths)),
+                ("workers", Json::Array(workers)),
+            ])
+        });
+        Json::object(vec![
+            ("stressr_result_version", (RESULT_FORMAT_VERSION as u64).into()),
+            ("labels", Json::Object(labels)),
+            ("elapsed_secs", elapsed.as_secs_f64().into()),
+            ("interrupted", (!running()).into()),
+            ("workers_failed", WORKER_FAILED.load(Ordering::Relaxed).into()),
+            ("workers_abandoned", WORKER_ABANDONED.load(Ordering::Relaxed).into()),
+            ("config", config_json.clone()),
+            ("cpu", cpu_stats.as_ref().map(CpuStats::to_json).into()),
+            ("memory", memory_stats.as_ref().map(MemoryStats::to_json).into()),
+            ("io", io.into()),
+            ("net", net_stats.as_ref().map(NetStats::to_json).into()),
+            ("checks", Json::object(checks)),
+            ("power_watts", watts.into()),
+            ("resource_usage", usage.as_ref().map(ResourceUsage::to_json).into()),
+            ("repeat", repeat_json.clone().into()),
+        ])
+    };
+
+    if !running() {
+        if JSON_OUTPUT.load(Ordering::Relaxed) {
+            println!("{}", results_json(checks));
+        } else {
+            println!(
+                "Interrupted after {:.1}s: the results above are partial",
+                elapsed.as_secs_f64()
+            );
+        }
+        return Err(StressError::Interrupted);
+    }
+
+    compare_and_save_last(
+        &run_metrics(cpu_stats.as_ref(), &io_stats, net_stats.as_ref()),
+        compare_last,
+    );
+
+    if let Some(path) = &cfg.csv {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let rows = csv_rows(
+            &cfg,
+            timestamp,
+            cpu_stats.as_ref(),
+            memory_stats.as_ref(),
+            &io_stats,
+            net_stats.as_ref(),
+        );
+        if let Err(e) = append_csv(path, &rows) {
+            error!("Could not write CSV results to {}: {}", path, e);
+        }
+    }
+
+    let mut failed = false;
+
+    if let Some(target) = target_iops {
+        let achieved: f64 = io_stats
+            .iter()
+            .map(|s| s.ops as f64 / s.elapsed.as_secs_f64().max(f64::EPSILON))
+            .sum();
+        let samples: u64 = io_stats.iter().map(|s| s.latency_samples).sum();
+        let latency_sum: Duration = io_stats.iter().map(|s| s.latency_sum).sum();
+        let avg_ms = if samples > 0 {
+            latency_sum.as_secs_f64() * 1e3 / samples as f64
+        } else {
+            0.0
+        };
+        let max_ms = io_stats.iter().map(|s| s.max_latency).max().unwrap_or_default();
+        let attained = achieved >= target as f64 * TARGET_IOPS_ATTAINED;
+        report!(
+            "Target IOPS: {} {} (achieved {:.0}, {:.1}%) | avg op latency {:.3}ms, max {:.3}ms",
+            target,
+            if attained { "ATTAINED" } else { "NOT ATTAINED" },
+            achieved,
+            achieved / target as f64 * 100.0,
+            avg_ms,
+            max_ms.as_secs_f64() * 1e3
+        );
+        checks.push((
+            "target_iops",
+            Json::object(vec![
+                ("target", target.into()),
+                ("achieved", achieved.into()),
+                ("attained", attained.into()),
+                ("avg_latency_ms", avg_ms.into()),
+                ("max_latency_ms", (max_ms.as_secs_f64() * 1e3).into()),
+            ]),
+        ));
+        failed |= !attained;
+    }
+
+    if io_rate.is_some() || io_bandwidth.is_some() {
+        let achieved_ops: f64 = io_stats
+            .iter()
+            .map(|s| s.ops as f64 / s.elapsed.as_secs_f64().max(f64::EPSILON))
+            .sum();
+        let achieved_mbps: f64 = io_stats.iter().map(WorkerStats::total_mbps).sum();
+        if let Some(rate) = io_rate {
+            report!("I/O rate: target {} ops/s, achieved {:.0} ops/s", rate, achieved_ops);
+        }
+        if let Some(mbps) = io_bandwidth {
+            report!(
+                "I/O bandwidth: target {}, achieved {}",
+                format_rate(mbps, precision),
+                format_rate(achieved_mbps, precision)
+            );
+        }
+        checks.push((
+            "io_throttle",
+            Json::object(vec![
+                ("target_ops", io_rate.into()),
+                ("achieved_ops", achieved_ops.into()),
+                ("target_mbps", io_bandwidth.into()),
+                ("achieved_mbps", achieved_mbps.into()),
+            ]),
+        ));
+    }
+
+    if let Some(limit_ms) = max_latency {
+        let violations: u64 = io_stats.iter().map(|s| s.latency_violations).sum();
+        let slowest = io_stats.iter().map(|s| s.max_latency).max().unwrap_or_default();
+        if violations == 0 {
+            report!(
+                "Latency SLA: PASSED (slowest op {:.3}ms <= {}ms)",
+                slowest.as_secs_f64() * 1e3,
+                limit_ms
+            );
+        } else {
+            report!(
+                "Latency SLA: FAILED ({} ops over {}ms, slowest {:.3}ms)",
+                violations,
+                limit_ms,
+                slowest.as_secs_f64() * 1e3
+            );
+            failed |= !latency_soft;
+        }
+        checks.push((
+            "latency_sla",
+            Json::object(vec![
+                ("max_latency_ms", limit_ms.into()),
+                ("passed", (violations == 0).into()),
+                ("violations", violations.into()),
+                ("slowest_ms", (slowest.as_secs_f64() * 1e3).into()),
+            ]),
+        ));
+    }
+
+    if JSON_OUTPUT.load(Ordering::Relaxed) {
+        println!("{}", results_json(checks));
+    }
+
+    workers_outcome()?;
+    if failed {
+        return Err(StressError::Failed("An end-of-run check failed; see above".into()));
+    }
+
+    info!("Done");
+    log::note(format_args!("Run finished (exit code 0)"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_detect_memory() {
+        let mem_kb = read_total_memory_kb();
+        assert!(mem_kb > 128_000, "Should detect >128MB of RAM, got {}", mem_kb);
+    }
+
+    #[test]
+    fn test_detect_available_memory() {
+        let avail_kb = read_available_memory_kb().expect("available memory should be readable");
+        assert!(avail_kb > 0 && avail_kb <= read_total_memory_kb());
+    }
+
+    #[test]
+    fn test_default_io_path_is_writable_temp_dir() {
+        let cfg = Config::default();
+        assert_eq!(cfg.io_paths, vec![std::env::temp_dir().to_string_lossy().into_owned()]);
+        assert!(probe_writable_dir(&cfg.io_paths[0]).is_ok());
+        assert!(probe_writable_dir("/definitely/not/a/real/path").is_err());
+    }
+
+    #[test]
+    fn test_check_io_paths_lists_every_bad_path() {
+        let tmp = std::env::temp_dir().to_string_lossy().into_owned();
+        let file = std::env::temp_dir().join(format!("stressr_not_a_dir_{}", std::process::id()));
+        std::fs::write(&file, b"x").unwrap();
+        let file = file.to_string_lossy().into_owned();
+        let mut cfg = Config {
+            io_paths: vec![tmp.clone(), "/definitely/not/a/real/path".into(), file.clone()],
+            ..Config::default()
+        };
+        match check_io_paths(&cfg) {
+            Err(StressError::InvalidConfig(msg)) => {
+                assert!(msg.starts_with("2 I/O paths cannot be used"), "{}", msg);
+                assert!(msg.contains("/definitely/not/a/real/path does not exist"));
+                assert!(msg.contains(&format!("{} is not a directory", file)));
+            }
+            other => panic!("expected InvalidConfig, got {:?}", other),
+        }
+        cfg.io_paths = vec![tmp];
+        assert!(check_io_paths(&cfg).is_ok());
+        cfg.io_reuse = true;
+        assert!(cfg.io_read_only());
+        assert!(check_io_paths(&cfg).is_ok());
+        let _ = std::fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_fd_limits_and_projection() {
+        #[cfg(unix)]
+        {
+            let (soft, hard) = fd_limits().expect("getrlimit should succeed");
+            assert!(soft > 0 && soft <= hard);
+            let (soft, hard) = get_rlimit(RLIMIT_AS).expect("getrlimit should succeed");
+            assert!(soft <= hard);
+        }
+        assert_eq!(apply_resource_limits(&Config::default()), Ok(vec![]));
+
+        let cfg = Config {
+            io_enabled: true,
+            io_paths: vec!["/a".into(), "/b".into()],
+            io_workers: 8,
+            ..Config::default()
+        };
+        assert_eq!(projected_fd_usage(&cfg), FD_BASE_OVERHEAD + 16);
+
+        let huge = Config {
+            io_workers: 10_000_000,
+            ..cfg
+        };
+        assert!(matches!(check_fd_limit(&huge), Err(StressError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_available_space() {
+        let tmp = std::env::temp_dir();
+        let free = available_space_bytes(tmp.to_str().unwrap()).expect("free space of temp dir");
+        assert!(free > 0);
+        assert!(available_space_bytes("/definitely/not/a/real/path").is_none());
+    }
+
+    #[test]
+    fn test_io_space_check() {
+        let tmp = std::env::temp_dir().to_string_lossy().into_owned();
+        let cfg = Config {
+            io_enabled: true,
+            io_paths: vec![tmp.clone(), tmp.clone()],
+            io_workers: 2,
+            io_size_bytes: 1024 * 1024,
+            ..Config::default()
+        };
+        // Both paths are on one filesystem and share its free space.
+        assert_eq!(io_space_needs(&cfg), vec![(vec![tmp.clone(), tmp], 4 * 1024 * 1024)]);
+        assert!(check_io_space(&cfg).is_ok());
+
+        let huge = Config { io_size_bytes: u64::MAX / 4, ..cfg };
+        match check_io_space(&huge) {
+            Err(StressError::OutOfSpace(shortfalls)) => {
+                assert_eq!(shortfalls.len(), 1);
+                assert!(shortfalls[0].needed > shortfalls[0].available);
+            }
+            other => panic!("expected OutOfSpace, got {:?}", other),
+        }
+        assert!(check_io_space(&Config { io_allow_overcommit: true, ..huge }).is_ok());
+    }
+
+    #[test]
+    fn test_size_from_free_space() {
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(size_from_free_space(100 * gib, 50, 2), 25 * 1024);
+        assert_eq!(size_from_free_space(10 * gib, 10, 1), 1024);
+        assert_eq!(size_from_free_space(gib, 10, 0), 102);
+    }
+
+    #[test]
+    fn test_simple_memory_stress() {
+        let cfg = Config { memory_percent: 1, duration_secs: 1, ..Config::default() };
+        let stats = stress_memory(&cfg).unwrap();
+        assert!(stats.allocated_mb > 0);
+        #[cfg(target_os = "linux")]
+        assert!(stats.rss_mb.unwrap() >= stats.allocated_mb);
+    }
+
+    #[test]
+    fn test_memory_use_available() {
+        let mut cfg = Config { memory_percent: 50, ..Config::default() };
+        cfg.apply_args(&["--memory-use".to_string(), "available".to_string()]);
+        assert_eq!(cfg.memory_use, MemoryBase::Available);
+        let available = memory_target_kb(&cfg);
+        cfg.memory_use = MemoryBase::Total;
+        assert!(available <= memory_target_kb(&cfg));
+        assert!(available > 0);
+    }
+
+    #[test]
+    fn test_memory_hold_then_free() {
+        let mut blocks: Vec<_> =
+            (0..10).map(|_| MemoryBlock::Heap(vec![1u8; MEMORY_PAGE])).collect();
+        PROGRESS_MEMORY_MB.fetch_add(10, Ordering::Relaxed);
+        let start = Instant::now();
+        free_memory_blocks(&mut blocks, Duration::from_millis(500));
+        assert!(blocks.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(400));
+
+        let mut cfg = Config { duration_secs: 60, ..Config::default() };
+        assert_eq!(cfg.memory_duration_secs(), 60);
+        cfg.apply_args(&["--memory-hold".into(), "2".into(), "--memory-free".into(), "1m".into()]);
+        assert_eq!((cfg.memory_hold_secs, cfg.memory_free_secs), (Some(2), Some(60)));
+        assert_eq!(cfg.memory_duration_secs(), 62);
+
+        let cfg = Config {
+            memory_percent: 1,
+            memory_hold_secs: Some(0),
+            memory_free_secs: Some(1),
+            ..Config::default()
+        };
+        let start = Instant::now();
+        let stats = stress_memory(&cfg).unwrap();
+        assert!(stats.allocated_mb > 0);
+        assert!(start.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_memory_hugepages() {
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--memory-hugepages".to_string()]);
+        assert!(cfg.memory_hugepages);
+
+        let mut allocator = BlockAllocator::new(&cfg);
+        let block = allocator.alloc(&mut Rng::new(1)).unwrap();
+        if cfg!(target_os = "linux") {
+            // Either a MAP_HUGETLB page or an advised heap buffer of that size.
+            assert_eq!((block.len(), block.mb()), (HUGE_PAGE, 2));
+            assert_ne!(allocator.backing, PageBacking::Normal);
+        } else {
+            assert_eq!(allocator.backing, PageBacking::Normal);
+        }
+        assert!(block.chunks(MEMORY_PAGE).all(|page| page.iter().any(|&b| b != 0)));
+
+        let cfg = Config {
+            memory_percent: 1,
+            memory_hugepages: true,
+            duration_secs: 0,
+            ..Config::default()
+        };
+        let stats = stress_memory(&cfg).unwrap();
+        assert!(stats.allocated_mb >= stats.requested_mb);
+        assert_eq!(stats.huge_pages_mb.is_some(), cfg!(target_os = "linux"));
+    }
+
+    #[test]
+    fn test_memory_mlock() {
+        let mut cfg = Config { memory_percent: 1, duration_secs: 0, ..Config::default() };
+        cfg.apply_args(&["--memory-mlock".to_string()]);
+        assert!(cfg.memory_mlock);
+        let stats = stress_memory(&cfg).unwrap();
+        // How much gets locked depends on RLIMIT_MEMLOCK, but never more than was allocated.
+        let locked = stats.locked_bytes.unwrap();
+        assert!(locked <= stats.allocated_mb * 1024 * 1024);
+        let unlocked = stress_memory(&Config { memory_mlock: false, ..cfg }).unwrap();
+        assert!(unlocked.locked_bytes.is_none());
+    }
+
+    #[test]
+    fn test_numa_node() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(parse_cpu_list("0-x"), None);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--numa-node".to_string(), "4096".to_string()]);
+        assert_eq!(cfg.numa_node, Some(4096));
+        // A node that does not exist leaves the run unbound.
+        apply_numa_node(&mut cfg);
+        assert_eq!((cfg.numa_node, cfg.cpu_affinity.is_empty()), (None, true));
+
+        if cfg!(target_os = "linux") && let Ok((_, cores)) = numa_node_cpus(0) {
+            let mut cfg = Config { numa_node: Some(0), ..Config::default() };
+            apply_numa_node(&mut cfg);
+            assert_eq!(cfg.cpu_threads, cores.len());
+            assert_eq!(cfg.cpu_affinity, cores);
+
+            let cfg = Config { memory_percent: 1, duration_secs: 0, ..cfg };
+            let stats = stress_memory(&cfg).unwrap();
+            if bind_memory_to_node(0).is_ok() {
+                assert_eq!(stats.numa_node, Some(0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_threads_stream() {
+        let cfg = Config {
+            memory_percent: 1,
+            memory_threads: 2,
+            duration_secs: 1,
+            ..Config::default()
+        };
+        let stats = stress_memory(&cfg).unwrap();
+        assert_eq!(stats.threads, 2);
+        assert!(stats.allocated_mb >= stats.requested_mb);
+        assert!(stats.streamed_bytes >= 2 * stats.allocated_mb * 1024 * 1024);
+        assert!(stats.gbps_per_thread() > 0.0);
+    }
+
+    #[test]
+    fn test_memory_block_fill_is_non_zero() {
+        let mut block = vec![0u8; 64 * 1024];
+        fill_memory_block(&mut block, &mut Rng::new(1));
+        // Every page must be dirtied, not left mapped to the zero page.
+        assert!(block.chunks(MEMORY_PAGE).all(|page| page.iter().any(|&b| b != 0)));
+        let before = block[0];
+        let mut blocks = vec![MemoryBlock::Heap(block)];
+        touch_memory_blocks(&mut blocks);
+        assert_eq!(blocks[0][0], before.wrapping_add(1));
+    }
+
+    #[test]
+    fn test_disk_io_worker_write_only() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 4 * 1024,
+            io_write: true,
+            ..Config::default()
+        };
+
+        let stats = disk_io_worker(path, 9999, &cfg).unwrap();
+        assert!(stats.ops > 0);
+        assert_eq!(stats.read_bytes, 0);
+        assert_eq!(stats.ttfb, None);
+    }
+
+    #[test]
+    fn test_io_keep_and_reuse() {
+        let dir = std::env::temp_dir().join("stressr_keep_reuse");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let file = dir.join("worker_0.tmp");
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            io_write: true,
+            io_keep: true,
+            ..Config::default()
+        };
+        disk_io_worker(path, 0, &cfg).unwrap();
+        assert_eq!(std::fs::metadata(&file).unwrap().len(), 1024 * 1024);
+
+        let reuse = Config {
+            io_write: false,
+            io_read: true,
+            io_keep: false,
+            io_reuse: true,
+            ..cfg.clone()
+        };
+        assert!(disk_io_worker(path, 0, &reuse).unwrap().read_bytes > 0);
+        assert!(file.exists(), "--io-reuse must not remove the file");
+
+        let too_big = Config { io_size_bytes: 2 * 1024 * 1024, ..reuse.clone() };
+        assert!(disk_io_worker(path, 0, &too_big).is_err());
+        assert_eq!(std::fs::metadata(&file).unwrap().len(), 1024 * 1024);
+        assert!(disk_io_worker(path, 1, &reuse).is_err());
+        assert!(Config { io_pretrim: true, ..reuse }.validate().is_err());
+        let pretrim = Config { io_pretrim: true, ..cfg.clone() };
+        assert!(pretrim.validate().is_ok());
+        assert!(Config { io_verify: true, ..pretrim.clone() }.validate().is_err());
+        assert!(Config { io_verify_pass: true, ..pretrim }.validate().is_err());
+
+        std::fs::remove_file(&file).unwrap();
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_io_rwmix() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+        let mut cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 4 * 1024,
+            io_random: true,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--io-rwmix".to_string(), "70".to_string()]);
+        assert!(cfg.io_read && cfg.io_write);
+
+        let stats = disk_io_worker(path, 9994, &cfg).unwrap();
+        assert_eq!(stats.read_ops + stats.write_ops, stats.ops);
+        assert!((stats.read_percent() - 70.0).abs() < 5.0, "{}", stats.read_percent());
+        assert_eq!(stats.read_bytes, stats.read_ops * 4096);
+
+        assert!(Config { io_rwmix: Some(101), ..cfg }.validate().is_err());
+    }
+
+    #[test]
+    fn test_io_files_per_worker() {
+        let dir = std::env::temp_dir().join("stressr_files_per_worker_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let mut cfg = Config {
+            io_size_bytes: 4 * 1024 * 1024,
+            io_duration_secs: 1,
+            io_write: true,
+            io_read: true,
+            io_verify: true,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--io-files-per-worker".to_string(), "4".to_string()]);
+        assert_eq!(cfg.io_files_per_worker, 4);
+        assert!(cfg.validate().is_ok());
+        assert!(Config { io_queue_depth: 2, ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_files_per_worker: 0, ..cfg.clone() }.validate().is_err());
+
+        // Round-robin touches every file; each is a quarter of --io-size.
+        let stats = disk_io_worker(path, 0, &cfg).unwrap();
+        assert_eq!(stats.files, 4);
+        assert_eq!(stats.corrupt_reads, 0);
+        let random = Config { io_random: true, io_keep: true, ..cfg.clone() };
+        let stats = disk_io_worker(path, 1, &random).unwrap();
+        assert_eq!(stats.files, 4);
+        for index in 0..4 {
+            let file = dir.join(worker_file_name(1, index, 4));
+            assert_eq!(std::fs::metadata(&file).unwrap().len(), 1024 * 1024);
+            std::fs::remove_file(file).unwrap();
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let tiny = Config { io_size_bytes: 3, ..cfg };
+        assert!(disk_io_worker(path, 2, &tiny).is_err());
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_io_churn() {
+        let dir = std::env::temp_dir().join("stressr_churn_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let mut cfg = Config { io_duration_secs: 1, ..Config::default() };
+        cfg.apply_args(&["--io-churn".to_string()]);
+        assert!(cfg.io_churn && cfg.io_metadata);
+        assert!(cfg.validate().is_ok());
+        assert!(Config { io_metadata_ops: true, ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_files_per_worker: 2, ..cfg.clone() }.validate().is_err());
+
+        // A straggler an earlier run of this worker left behind is swept up too.
+        std::fs::write(dir.join("meta_0_straggler.tmp"), b"x").unwrap();
+        let meta = metadata_worker(path, 0, &cfg).unwrap();
+        assert!(meta.creates > 0);
+        assert_eq!(meta.fsync_time, Duration::ZERO);
+        let stats = meta.churn_stats(path);
+        assert_eq!(stats.ops, meta.creates);
+        assert_eq!(stats.write_bytes, stats.ops * METADATA_FILE_BYTES as u64);
+        assert!(stats.ops_per_sec() > 0.0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_io_block_align() {
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--io-block-align".to_string(), "4K".to_string()]);
+        assert_eq!((cfg.io_block_align, cfg.io_align()), (Some(4096), 4096));
+        assert_eq!(Config { io_block_align: Some(512), ..cfg.clone() }.io_align(), 512);
+        let direct = Config { io_direct: true, io_block_align: None, ..cfg.clone() };
+        assert_eq!(direct.io_align(), DIRECT_IO_ALIGN);
+        assert!(Config { io_block_align: Some(0), ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_block_align: Some(512), io_direct: true, ..cfg.clone() }
+            .validate()
+            .is_err());
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 6 * 1024,
+            io_read: true,
+            io_write: true,
+            io_random: true,
+            io_verify: true,
+            ..cfg
+        };
+        assert!(cfg.validate().is_ok());
+        let path = std::env::temp_dir();
+        let stats = disk_io_worker(path.to_str().unwrap(), 9992, &cfg).unwrap();
+        assert!(stats.ops > 0);
+        assert_eq!(stats.read_bytes, stats.read_ops * 8192);
+        assert_eq!(stats.corrupt_reads, 0);
+        // Clamped to the 6000-byte file, the chunks round up past its end.
+        let too_small = Config { io_size_bytes: 6000, ..cfg };
+        assert!(disk_io_worker(path.to_str().unwrap(), 9992, &too_small).is_err());
+    }
+
+    #[test]
+    fn test_io_direct_alignment() {
+        let buf = AlignedBuffer::new(5000);
+        assert_eq!(buf.len(), 5000);
+        assert!((buf.as_ptr() as usize).is_multiple_of(DIRECT_IO_ALIGN));
+        let up = |value| align_up(value, DIRECT_IO_ALIGN);
+        assert_eq!((up(1), up(4096), up(6144)), (4096, 4096, 8192));
+
+        let tmp = std::env::temp_dir();
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 6 * 1024,
+            io_read: true,
+            io_write: true,
+            io_random: true,
+            io_verify: true,
+            io_direct: true,
+            ..Config::default()
+        };
+        assert!(cfg.validate().is_ok());
+        match disk_io_worker(tmp.to_str().unwrap(), 9993, &cfg) {
+            Ok(stats) => {
+                assert!(stats.ops > 0);
+                assert_eq!(stats.read_bytes, stats.read_ops * 8192);
+                assert_eq!(stats.corrupt_reads, 0);
+            }
+            // Some filesystems (older tmpfs) refuse unbuffered I/O.
+            Err(e) => assert!(e.to_string().contains("--io-direct"), "{}", e),
+        }
+
+        assert!(Config { io_size_bytes: 1000, ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_trace: Some("t".into()), ..cfg }.validate().is_err());
+    }
+
+    #[test]
+    fn test_disk_io_worker_fsync_every() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let mut cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 4 * 1024,
+            io_write: true,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--io-fsync-every".to_string(), "4".to_string()]);
+        assert_eq!(cfg.io_sync_mode, IoSyncMode::Fsync);
+        assert_eq!(io_mode(&cfg), "W+fsync");
+
+        let stats = disk_io_worker(path, 9995, &cfg).unwrap();
+        assert!(stats.fsyncs > 0);
+        assert_eq!(stats.fsyncs, stats.ops / 4);
+    }
+
+    #[test]
+    fn test_io_sync_mode() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let mut cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 4 * 1024,
+            io_write: true,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--io-sync-mode".to_string(), "fdatasync".to_string()]);
+        assert_eq!(cfg.io_sync_mode, IoSyncMode::Fdatasync);
+        assert_eq!(io_mode(&cfg), "W+fdatasync");
+        // --io-fsync-every keeps an explicit fdatasync.
+        cfg.apply_args(&["--io-fsync-every".to_string(), "2".to_string()]);
+        assert_eq!(cfg.io_sync_mode, IoSyncMode::Fdatasync);
+        let stats = disk_io_worker(path, 9990, &cfg).unwrap();
+        assert_eq!(stats.fsyncs, stats.ops / 2);
+        assert!(Config { io_queue_depth: 2, ..cfg.clone() }.validate().is_err());
+
+        let osync = Config { io_sync_mode: IoSyncMode::Osync, io_fsync_every: 1, ..cfg.clone() };
+        assert!(osync.validate().is_ok());
+        assert!(Config { io_fsync_every: 2, ..osync.clone() }.validate().is_err());
+        let stats = disk_io_worker(path, 9991, &osync).unwrap();
+        assert!(stats.write_ops > 0);
+        assert_eq!(stats.fsyncs, 0);
+
+        cfg.apply_args(&["--io-sync-mode".to_string(), "sometimes".to_string()]);
+        assert_eq!(cfg.io_sync_mode, IoSyncMode::Fdatasync);
+    }
+
+    #[test]
+    fn test_disk_io_worker_queue_depth() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let mut cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 16 * 1024,
+            io_read: true,
+            io_write: true,
+            io_random: true,
+            io_verify_pass: true,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--io-queue-depth".to_string(), "8".to_string()]);
+        assert_eq!(cfg.io_queue_depth, 8);
+        assert!(cfg.validate().is_ok());
+
+        // Where io_uring is blocked the worker falls back to synchronous I/O.
+        let stats = disk_io_worker(path, 9993, &cfg).unwrap();
+        assert!(stats.read_ops > 0 && stats.write_ops > 0);
+        assert_eq!(stats.write_bytes, stats.write_ops * 16 * 1024);
+        assert_eq!(stats.verify_errors, 0);
+
+        assert!(Config { io_queue_depth: 0, ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_verify: true, ..cfg.clone() }.validate().is_err());
+        assert!(Config { io_sync_mode: IoSyncMode::Fsync, ..cfg }.validate().is_err());
+    }
+
+    #[test]
+    fn test_disk_io_worker_chunk_larger_than_file() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            chunk_size_bytes: 2048 * 1024,
+            io_read: true,
+            io_write: true,
+            ..Config::default()
+        };
+        let stats = disk_io_worker(path, 9996, &cfg).unwrap();
+        assert!(stats.ops > 0);
+        assert_eq!(stats.write_bytes, stats.ops * 1024 * 1024);
+
+        let random = Config {
+            chunk_size_bytes: 1024 * 1024,
+            io_random: true,
+            ..cfg
+        };
+        let stats = disk_io_worker(path, 9996, &random).unwrap();
+        assert!(stats.ops > 0);
+        assert_eq!(next_offset(true, &mut Rng::new(1), &mut 0, 4096, 4096), 0);
+        assert_eq!(next_offset(false, &mut Rng::new(1), &mut 4096, 4096, 4096), 0);
+    }
+
+    #[test]
+    fn test_start_at_schedule() {
+        assert_eq!(parse_clock_time("14:30"), Some(14 * 3600 + 30 * 60));
+        assert_eq!(parse_clock_time("00:00:05"), Some(5));
+        let last = parse_clock_time("23:59:59").map(format_clock_time);
+        assert_eq!(last.as_deref(), Some("23:59:59"));
+        for bad in ["24:00", "12:60", "12", "1:2:3:4", "ab:cd"] {
+            assert_eq!(parse_clock_time(bad), None, "{}", bad);
+        }
+
+        // 2024-01-01 10:00:00 UTC.
+        let now = 1_704_103_200;
+        assert_eq!(secs_until_clock_time(now, 0, 10 * 3600 + 90), 90);
+        assert_eq!(secs_until_clock_time(now, 0, 10 * 3600), 0);
+        // Already past today, so tomorrow.
+        assert_eq!(secs_until_clock_time(now, 0, 9 * 3600), 23 * 3600);
+        // 10:00 UTC is 12:00 two hours east and 23:00 the day before 11 hours west.
+        assert_eq!(secs_until_clock_time(now, 2 * 3600, 12 * 3600 + 1), 1);
+        assert_eq!(secs_until_clock_time(now, -11 * 3600, 0), 3600);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--delay".into(), "1".into(), "--start-at".into(), "7:05".into()]);
+        assert_eq!((cfg.delay_secs, cfg.start_at), (1, Some(7 * 3600 + 5 * 60)));
+        let start = Instant::now();
+        wait_for_start(&Config { delay_secs: 1, ..Config::default() });
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sequential_offsets_cover_file() {
+        let chunk = 64 * 1024;
+        // A whole number of chunks, and one with a partial chunk at the end.
+        for total in [16 * chunk, 16 * chunk + 1000] {
+            let mut rng = Rng::new(1);
+            let mut cursor = 0;
+            let pass: Vec<u64> =
+                (0..16).map(|_| next_offset(false, &mut rng, &mut cursor, chunk, total)).collect();
+            let expected: Vec<u64> = (0..16).map(|i| i * chunk).collect();
+            assert_eq!(pass, expected, "total {}", total);
+            assert_eq!(next_offset(false, &mut rng, &mut cursor, chunk, total), 0);
+        }
+
+        // Far past 4 GB, where usize arithmetic would overflow on 32-bit.
+        let total = 6 * 1024 * 1024 * 1024;
+        let mut cursor = total - chunk;
+        let last = next_offset(false, &mut Rng::new(1), &mut cursor, chunk, total);
+        assert_eq!(last, total - chunk);
+        assert_eq!(next_offset(false, &mut Rng::new(1), &mut cursor, chunk, total), 0);
+    }
+
+    #[test]
+    fn test_disk_io_worker_truncated_file_stops_cleanly() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp").to_string();
+        let file_path = tmp.join("worker_9998.tmp");
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 2,
+            chunk_size_bytes: 4 * 1024,
+            io_random: true,
+            io_read: true,
+            ..Config::default()
+        };
+        let worker = thread::spawn(move || {
+            let _ = disk_io_worker(&path, 9998, &cfg);
+        });
+
+        thread::sleep(Duration::from_millis(300));
+        if let Ok(f) = OpenOptions::new().write(true).open(&file_path) {
+            f.set_len(0).unwrap();
+        }
+
+        assert!(worker.join().is_ok(), "worker should stop on EOF, not panic");
+    }
+
+    #[test]
+    fn test_disk_io_worker_asymmetric_chunks() {
+        let tmp = std::env::temp_dir();
+        let path = tmp.to_str().unwrap_or("/tmp");
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            io_read_chunk_bytes: Some(4 * 1024),
+            io_write_chunk_bytes: Some(128 * 1024),
+            io_read: true,
+            io_write: true,
+            ..Config::default()
+        };
+
+        let stats = disk_io_worker(path, 9997, &cfg).unwrap();
+        assert!(stats.ops > 0);
+        // Every read follows a write, so none of them is a cold first read.
+        assert_eq!(stats.ttfb, None);
+        assert_eq!(stats.read_bytes, stats.ops * 4 * 1024);
+        assert_eq!(stats.write_bytes, stats.ops * 128 * 1024);
+    }
+
+    #[test]
+    fn test_failing_path_does_not_stop_other_workers() {
+        let bad = "/definitely/not/a/real/path";
+        let cfg = Config {
+            io_enabled: true,
+            io_paths: vec![bad.into(), std::env::temp_dir().to_str().unwrap().into()],
+            io_workers: 1,
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            io_write: true,
+            ..Config::default()
+        };
+        match disk_io_worker(bad, 0, &cfg) {
+            Err(StressError::Io { path, .. }) => assert_eq!(path, bad),
+            other => panic!("expected an I/O error, got {:?}", other.map(|s| s.ops)),
+        }
+
+        let stats = run_disk_io(&cfg).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert!(stats[0].ops > 0);
+        assert!(WORKER_FAILED.load(Ordering::Relaxed));
+
+        // With no worker left, the first error comes back.
+        let only_bad = Config { io_paths: vec![bad.into()], ..cfg };
+        assert!(matches!(run_disk_io(&only_bad), Err(StressError::Io { .. })));
+    }
+
+    #[test]
+    fn test_io_summary_sums_concurrent_workers() {
+        let worker = |ops| WorkerStats {
+            ops,
+            write_bytes: 10 * 1024 * 1024,
+            elapsed: Duration::from_secs(1),
+            ..WorkerStats::default()
+        };
+        let stats = vec![worker(5), worker(7)];
+        assert_eq!(
+            io_summary_line("I/O Total", &stats, 1),
+            "[I/O Total] 20.0 MB/s | 12 ops | 20 MB | 2 workers"
+        );
+    }
+
+    #[test]
+    fn test_io_totals_per_path() {
+        let worker = |path: &str, secs, latency_ms| {
+            let mut stats = WorkerStats {
+                path: path.to_string(),
+                ops: 1,
+                write_bytes: 10 * 1024 * 1024,
+                elapsed: Duration::from_secs(secs),
+                ..WorkerStats::default()
+            };
+            stats.record_latency(&Config::default(), "write", 0, Duration::from_millis(latency_ms));
+            stats
+        };
+        let stats = vec![worker("/ssd", 1, 1), worker("/hdd", 5, 20), worker("/ssd", 2, 1)];
+        let cfg = Config {
+            io_paths: vec!["/ssd".to_string(), "/hdd".to_string(), "/unused".to_string()],
+            ..Config::default()
+        };
+        let totals = io_path_totals(&cfg, &stats);
+        assert_eq!(totals.len(), 3);
+        let (path, workers, ssd) = &totals[0];
+        assert_eq!((path.as_str(), *workers, ssd.ops), ("/ssd", 2, 2));
+        assert!((ssd.total_mbps() - 15.0).abs() < 1e-6);
+        assert!((totals[1].2.total_mbps() - 2.0).abs() < 1e-6);
+        assert_eq!(totals[2].1, 0);
+
+        let line = io_summary_line("I/O /hdd", &stats[1..2], 1);
+        assert!(line.ends_with("| 1 workers | latency p50 20.00ms p99 20.00ms"), "{}", line);
+        assert_eq!(worker_label(&cfg, 3, "/hdd"), "I/O Worker 3 /hdd");
+
+        let rows = csv_rows(&cfg, 0, None, None, &stats, None);
+        let totals: Vec<_> = rows.iter().filter(|r| r[2] == "total").map(|r| &r[3]).collect();
+        assert_eq!(totals, ["/ssd", "/hdd", "/ssd,/hdd,/unused"]);
+    }
+
+    #[test]
+    fn test_record_latency() {
+        let mut stats = WorkerStats::default();
+        let soft = Config {
+            io_max_latency_ms: Some(10),
+            io_max_latency_soft: true,
+            ..Config::default()
+        };
+        assert!(!stats.record_latency(&soft, "write", 0, Duration::from_millis(5)));
+        assert!(!stats.record_latency(&soft, "write", 0, Duration::from_millis(25)));
+        assert_eq!(stats.latency_violations, 1);
+        assert_eq!(stats.max_latency, Duration::from_millis(25));
+
+        let unlimited = Config::default();
+        assert!(!stats.record_latency(&unlimited, "read", 0, Duration::from_secs(1)));
+        assert_eq!(stats.latency_violations, 1);
+        assert_eq!(stats.max_latency, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sequential_phases_run_back_to_back() {
+        let dir = std::env::temp_dir().join("stressr_sequential");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = Config {
+            cpu_percent: 10,
+            cpu_threads: 1,
+            duration_secs: 1,
+            io_enabled: true,
+            io_write: true,
+            io_paths: vec![dir.to_string_lossy().into_owned()],
+            io_workers: 1,
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            sequential: true,
+            ..Config::default()
+        };
+        let start = Instant::now();
+        let results = run_sequential(&cfg, true, false);
+        // Two one-second phases one after the other, not overlapping.
+        assert!(start.elapsed() >= Duration::from_secs(2));
+        assert!(results.cpu.is_some_and(|c| c.ops > 0));
+        assert!(results.memory.is_none());
+        assert_eq!(results.io.len(), 1);
+        assert!(results.net.is_none());
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_worker_join_by() {
+        // `finished_by` rather than `join_by` for the stuck worker, so the
+        // global abandoned flag other tests' runs check stays clear.
+        let quick = Worker::spawn("t-quick".into(), "quick".into(), || 7);
+        assert!(quick.finished_by(Instant::now() + Duration::from_secs(5)));
+        assert_eq!(quick.join_by(Some(Instant::now())), Some(7));
+        let stuck = Worker::spawn("t-stuck".into(), "stuck".into(), || {
+            thread::sleep(Duration::from_millis(500));
+        });
+        assert!(!stuck.finished_by(Instant::now() + Duration::from_millis(50)));
+        assert_eq!(stuck.join_by(None), Some(()));
+    }
+
+    #[test]
+    fn test_net_client_and_server() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let cfg = Config {
+            net_enabled: true,
+            net_connect: Some(addr.clone()),
+            net_conns: 2,
+            net_size_bytes: 1024 * 1024,
+            net_duration_secs: 1,
+            ..Config::default()
+        };
+        assert!(cfg.validate().is_ok());
+        let server_cfg = Config { net_duration_secs: 2, ..cfg.clone() };
+        let server = thread::spawn(move || run_net_server(listener, &server_cfg).unwrap());
+
+        let client = run_net_client(&addr, &cfg).unwrap();
+        let server = server.join().unwrap();
+        assert_eq!(client.threads, 2);
+        assert!(client.connections > 0 && client.errors == 0);
+        assert!(client.bytes >= client.connections * 1024 * 1024);
+        assert!(server.connections >= client.connections);
+        assert!(server.bytes >= client.connections * 1024 * 1024);
+
+        // Nothing listens any more: each connection counts its refusals.
+        let refused = run_net_client(&addr, &Config { net_conns: 1, ..cfg.clone() }).unwrap();
+        assert_eq!((refused.bytes, refused.connections), (0, 0));
+        assert!(refused.errors > 0);
+
+        let no_role = Config { net_connect: None, ..cfg };
+        assert!(no_role.validate().is_err());
+    }
+
+    #[test]
+    fn test_progress_line() {
+        let line = progress_line(Duration::from_millis(2900), Duration::from_secs(10), 12.5, 1);
+        assert!(line.starts_with("[Progress] 3s elapsed, 7s remaining | I/O 12.5 MB/s"), "{}", line);
+        let line = progress_line(Duration::from_secs(12), Duration::from_secs(10), 0.0, 1);
+        assert!(line.contains("0s remaining"), "{}", line);
+    }
+
+    #[test]
+    fn test_tui_frame() {
+        assert_eq!(tui_gauge(0.25), format!("[{}{}]", "█".repeat(10), "░".repeat(30)));
+        assert_eq!(tui_gauge(1.5), tui_gauge(1.0));
+        assert_eq!(tui_sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
+        assert_eq!(tui_sparkline(&[0.0, 0.0]), "▁▁");
+
+        let sample = TuiSample {
+            elapsed: Duration::from_secs(12),
+            cpu_threads: 4,
+            cpu_percent: 50.0,
+            system_cpu_percent: None,
+            memory_mb: 512,
+            total_memory_mb: 2048,
+            io_mbps: 100.0,
+        };
+        let frame = tui_frame(&sample, &[50.0, 100.0], &["Memory: done".to_string()], 1);
+        assert!(frame.starts_with("\x1b[H stressr  12s elapsed"), "{:?}", frame);
+        assert!(frame.ends_with(" Memory: done\x1b[K\r\n\x1b[J"), "{:?}", frame);
+        assert!(frame.contains(" 50.0%  4 threads"));
+        assert!(frame.contains(&format!("{} 512 MB of 2048 MB", tui_gauge(0.25))));
+        assert!(frame.contains("100.0 MB/s  ▅█"), "{:?}", frame);
+        assert!(!frame.contains("System CPU"));
+    }
+
+    #[test]
+    fn test_stats_server_metrics() {
+        let port = {
+            let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            probe.local_addr().unwrap().port()
+        };
+        let labels = vec![("host".to_string(), "db-1".to_string())];
+        let server = StatsServer::start(&format!("127.0.0.1:{}", port), &labels).unwrap();
+        let body = http_get(&format!("http://127.0.0.1:{}/metrics", port)).unwrap();
+        assert!(body.contains("# TYPE stressr_io_bytes_total counter\n"), "{}", body);
+        for name in ["stressr_io_mbps", "stressr_memory_allocated_bytes", "stressr_cpu_threads"] {
+            let prefix = format!("{}{{host=\"db-1\"}} ", name);
+            let sample = body.lines().find(|l| l.starts_with(&prefix)).unwrap();
+            assert!(sample.split(' ').nth(1).unwrap().parse::<f64>().is_ok(), "{}", sample);
+        }
+        let tricky = [("rack-id".to_string(), "say \"hi\" \\ x".to_string())];
+        assert_eq!(prometheus_labels(&tricky), r#"{rack_id="say \"hi\" \\ x"}"#);
+        assert_eq!(prometheus_labels(&[]), "");
+        let missing = http_get(&format!("http://127.0.0.1:{}/other", port));
+        assert!(missing.unwrap_err().contains("404"));
+        drop(server);
+        assert!(std::net::TcpStream::connect(("127.0.0.1", port)).is_err());
+    }
+
+    #[test]
+    fn test_warmup_excluded_from_results() {
+        let dir = std::env::temp_dir().join("stressr_warmup");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cfg = Config {
+            io_enabled: true,
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 2,
+            warmup_secs: 1,
+            io_read: true,
+            ..Config::default()
+        };
+        assert!(cfg.validate().is_ok());
+        let stats = disk_io_worker(dir.to_str().unwrap(), 0, &cfg).unwrap();
+        // Only the second after the warmup is measured.
+        assert!(stats.elapsed < Duration::from_millis(1500), "{:?}", stats.elapsed);
+        assert!(stats.ops > 0);
+        assert_eq!(stats.latency_samples, stats.latency_hist.count());
+        // The first read happened during the warmup and is kept.
+        assert!(stats.ttfb.is_some());
+        let _ = std::fs::remove_dir(&dir);
+
+        let too_long = Config { warmup_secs: 2, ..cfg };
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_csv_results() {
+        let cfg = Config {
+            io_paths: vec!["/a".into(), "/b".into()],
+            io_read: true,
+            labels: vec![("env".into(), "ci".into()), ("rack".into(), "a,b".into())],
+            ..Config::default()
+        };
+        let worker = |id, path: &str| WorkerStats {
+            worker_id: id,
+            path: path.into(),
+            elapsed: Duration::from_secs(1),
+            ops: 10,
+            read_bytes: 10 * 1024 * 1024,
+            ..WorkerStats::default()
+        };
+        let workers = [worker(0, "/a"), worker(0, "/b")];
+        let rows = csv_rows(&cfg, 1700000000, None, None, &workers, None);
+        assert_eq!(rows.len(), 5);
+        assert!(rows.iter().all(|row| row.len() == CSV_HEADER.len()));
+        assert_eq!(rows[1][..8], ["1700000000", "io", "0", "/b", "R", "10.00", "10", "10485760"]);
+        assert_eq!(rows[2][2..7], ["total", "/a", "R", "10.00", "10"]);
+        assert_eq!(rows[4][2..7], ["total", "/a,/b", "R", "20.00", "20"]);
+        assert!(rows.iter().all(|row| row[11] == "env=ci rack=a,b"));
+
+        let path = std::env::temp_dir().join(format!("stressr_csv_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        append_csv(path, &rows).unwrap();
+        append_csv(path, &rows[4..]).unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        let _ = remove_file(path);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], format!("# stressr_result_version={}", RESULT_FORMAT_VERSION));
+        assert!(lines[1].starts_with("timestamp,subsystem,worker_id,path,"));
+        assert!(lines[7].contains(",total,\"/a,/b\",R,"));
+        assert!(lines[7].ends_with(",\"env=ci rack=a,b\""));
+    }
+
+    #[test]
+    fn test_repeat_spreads() {
+        let runs = vec![
+            vec![("io_mbps".to_string(), 100.0), ("io_latency_p99_ms".to_string(), 2.0)],
+            vec![("io_mbps".to_string(), 110.0)],
+            vec![("io_mbps".to_string(), 120.0), ("io_latency_p99_ms".to_string(), 4.0)],
+        ];
+        let spreads = repeat_spreads(&runs);
+        assert_eq!(spreads.len(), 2);
+        let (key, values, mbps) = &spreads[0];
+        assert_eq!((key.as_str(), values.len()), ("io_mbps", 3));
+        assert_eq!((mbps.min, mbps.max, mbps.mean), (100.0, 120.0, 110.0));
+        assert!((mbps.stddev - 10.0).abs() < 1e-9);
+        assert!((mbps.cv_percent() - 100.0 / 11.0).abs() < 1e-9);
+        assert_eq!(spreads[1].1, vec![2.0, 4.0]);
+
+        assert_eq!(Spread::of(&[5.0]).unwrap().stddev, 0.0);
+        assert!(Spread::of(&[]).is_none());
+        assert!(Config { repeat: 0, ..Config::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_io_rate_and_bandwidth_throttle() {
+        let dir = std::env::temp_dir().join("stressr_throttle");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+        let base = Config {
+            io_paths: vec![path.to_string()],
+            io_workers: 2,
+            io_size_bytes: 1024 * 1024,
+            chunk_size_bytes: 4096,
+            io_duration_secs: 1,
+            io_write: true,
+            ..Config::default()
+        };
+
+        // 200 ops/s shared by two workers is 100 ops/s each.
+        let cfg = Config { io_rate: Some(200), ..base.clone() };
+        let stats = disk_io_worker(path, 0, &cfg).unwrap();
+        assert!((85..=110).contains(&stats.ops), "{} ops", stats.ops);
+
+        // 0.8 MB/s shared by two workers at 4 KB per op is ~102 ops/s each.
+        let cfg = Config { io_bandwidth_mbps: Some(0.8), ..base };
+        let stats = disk_io_worker(path, 1, &cfg).unwrap();
+        let mbps = stats.total_mbps();
+        assert!((0.34..=0.44).contains(&mbps), "{:.3} MB/s", mbps);
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_target_iops_pacing() {
+        let cfg = Config {
+            io_paths: vec!["/a".into(), "/b".into()],
+            io_workers: 2,
+            io_target_iops: Some(400),
+            ..Config::default()
+        };
+        assert_eq!(target_op_interval(&cfg), Some(Duration::from_millis(10)));
+        assert_eq!(target_op_interval(&Config::default()), None);
+
+        let path = std::env::temp_dir();
+        let cfg = Config {
+            io_workers: 1,
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            io_write: true,
+            io_target_iops: Some(100),
+            ..Config::default()
+        };
+        let stats = disk_io_worker(path.to_str().unwrap(), 9, &cfg).unwrap();
+        assert!((95..=101).contains(&stats.ops), "{} ops", stats.ops);
+        assert_eq!(stats.latency_samples, stats.ops);
+    }
+
+    #[test]
+    fn test_parse_io_trace() {
+        let ops = parse_io_trace("# captured\nW 0 4096\n\nr 8192 512\n").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                TraceOp { write: true, offset: 0, size: 4096 },
+                TraceOp { write: false, offset: 8192, size: 512 },
+            ]
+        );
+        assert!(parse_io_trace("X 0 4096").is_err());
+        assert!(parse_io_trace("W 0").is_err());
+        assert!(parse_io_trace("R 0 0").is_err());
+        assert!(parse_io_trace("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn test_trace_worker_replays_once() {
+        let path = std::env::temp_dir();
+        let path = path.to_str().unwrap();
+        let ops = parse_io_trace("W 0 65536\nW 1048576 4096\nR 0 65536\nR 1048576 4096").unwrap();
+        let cfg = Config {
+            io_duration_secs: 10,
+            io_trace_once: true,
+            ..Config::default()
+        };
+
+        let stats = trace_worker(path, 7, &cfg, &ops).unwrap();
+        assert_eq!(stats.ops, 4);
+        assert_eq!(stats.write_bytes, 65536 + 4096);
+        assert_eq!(stats.read_bytes, 65536 + 4096);
+        assert!(stats.elapsed < Duration::from_secs(10));
+        assert!(!PathBuf::from(path).join("worker_7.tmp").exists());
+    }
+
+    #[test]
+    fn test_metadata_worker_cleans_up() {
+        let dir = std::env::temp_dir().join("stressr_meta_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.to_str().unwrap();
+
+        let cfg = Config {
+            io_duration_secs: 1,
+            io_metadata: true,
+            ..Config::default()
+        };
+
+        let stats = metadata_worker(path, 0, &cfg).unwrap();
+        assert!(stats.creates > 0);
+        assert_eq!(stats.creates, stats.deletes);
+        assert_eq!(stats.renames, 0);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let cfg = Config {
+            io_metadata_ops: true,
+            ..cfg
+        };
+        let stats = metadata_worker(path, 1, &cfg).unwrap();
+        assert_eq!(stats.creates, stats.deletes);
+        assert_eq!(stats.renames, stats.creates);
+        assert_eq!(stats.dirs, stats.creates);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir(&dir);
+    }
+
+    #[test]
+    fn test_worker_stats_wire_roundtrip() {
+        let mut latency_hist = LatencyHistogram::default();
+        latency_hist.record(Duration::from_micros(80));
+        latency_hist.record(Duration::from_millis(3));
+        let stats = WorkerStats {
+            worker_id: 3,
+            path: String::new(),
+            elapsed: Duration::from_millis(1500),
+            ops: 42,
+            read_ops: 30,
+            write_ops: 12,
+            read_bytes: 4096,
+            write_bytes: 8192,
+            paused: Duration::from_millis(250),
+            distinct_offsets: 7,
+            coverage: 0.5,
+            verify_errors: 0,
+            read_corrupt_bytes: 9,
+            corrupt_reads: 2,
+            first_corrupt_offset: Some(65536),
+            ttfb: Some(Duration::from_micros(830)),
+            max_latency: Duration::from_millis(12),
+            latency_violations: 2,
+            latency_sum: Duration::from_millis(40),
+            latency_samples: 84,
+            latency_hist,
+            fsyncs: 5,
+            fsync_time: Duration::from_millis(3),
+            files: 4,
+        };
+
+        let parsed = WorkerStats::from_wire(&stats.to_wire()).unwrap();
+        assert_eq!(parsed.worker_id, 3);
+        assert_eq!(parsed.ops, 42);
+        assert_eq!((parsed.read_ops, parsed.write_ops), (30, 12));
+        assert_eq!(parsed.read_bytes, 4096);
+        assert_eq!(parsed.write_bytes, 8192);
+        assert_eq!(parsed.elapsed, Duration::from_millis(1500));
+        assert_eq!(parsed.paused, Duration::from_millis(250));
+        assert_eq!(parsed.distinct_offsets, 7);
+        assert_eq!(parsed.coverage, 0.5);
+        assert_eq!(parsed.read_corrupt_bytes, 9);
+        assert_eq!(parsed.corrupt_reads, 2);
+        assert_eq!(parsed.first_corrupt_offset, Some(65536));
+        assert_eq!(parsed.ttfb, Some(Duration::from_micros(830)));
+        assert_eq!(parsed.max_latency, Duration::from_millis(12));
+        assert_eq!(parsed.latency_violations, 2);
+        assert_eq!(parsed.latency_sum, Duration::from_millis(40));
+        assert_eq!(parsed.latency_samples, 84);
+        assert_eq!(parsed.latency_hist, stats.latency_hist);
+        assert_eq!(parsed.fsyncs, 5);
+        assert_eq!(parsed.fsync_time, Duration::from_millis(3));
+        assert_eq!(parsed.files, 4);
+
+        let no_reads = WorkerStats::default();
+        let parsed = WorkerStats::from_wire(&no_reads.to_wire()).unwrap();
+        assert_eq!(parsed.ttfb, None);
+        assert_eq!(parsed.first_corrupt_offset, None);
+        assert_eq!(parsed.latency_hist.count(), 0);
+
+        assert!(WorkerStats::from_wire("[I/O Worker 0] 1.00 MB/s").is_none());
+
+        assert_eq!(progress_from_wire(&progress_to_wire(1 << 20, 16)), Some((1 << 20, 16)));
+        assert!(progress_from_wire(&no_reads.to_wire()).is_none());
+        assert!(WorkerStats::from_wire(&progress_to_wire(1, 1)).is_none());
+    }
+
+    #[test]
+    fn test_latency_histogram_percentiles() {
+        let mut stats = WorkerStats::default();
+        assert_eq!(stats.latency_percentile(0.5), None);
+
+        let cfg = Config::default();
+        for _ in 0..90 {
+            stats.record_latency(&cfg, "read", 0, Duration::from_micros(100));
+        }
+        for _ in 0..9 {
+            stats.record_latency(&cfg, "read", 0, Duration::from_millis(5));
+        }
+        stats.record_latency(&cfg, "read", 0, Duration::from_millis(40));
+
+        // Percentiles land on the upper edge of their bucket, within 26%.
+        let p50 = stats.latency_percentile(0.50).unwrap();
+        assert!(p50 >= Duration::from_micros(100) && p50 < Duration::from_micros(126), "{:?}", p50);
+        let p95 = stats.latency_percentile(0.95).unwrap();
+        assert!(p95 >= Duration::from_millis(5) && p95 < Duration::from_micros(6300), "{:?}", p95);
+        assert_eq!(stats.latency_percentile(1.0), Some(Duration::from_millis(40)));
+
+        let mut slow = LatencyHistogram::default();
+        slow.record(Duration::from_secs(60));
+        slow.record(Duration::from_nanos(10));
+        assert_eq!(slow.percentile(1.0), Some(Duration::from_secs(10)));
+        assert_eq!(slow.percentile(0.5), Some(Duration::from_micros(1)));
+        assert_eq!(format_latency(Duration::from_micros(85)), "85us");
+        assert_eq!(format_latency(Duration::from_micros(1250)), "1.25ms");
+    }
+
+    #[test]
+    fn test_fill_pattern_is_position_based() {
+        let mut whole = vec![0u8; 64];
+        fill_pattern(&mut whole, 7, 1000);
+
+        // Regenerating any unaligned sub-range must give the same bytes.
+        let mut part = vec![0u8; 13];
+        fill_pattern(&mut part, 7, 1021);
+        assert_eq!(&whole[21..34], &part[..]);
+
+        let mut other_seed = vec![0u8; 64];
+        fill_pattern(&mut other_seed, 8, 1000);
+        assert_ne!(whole, other_seed);
+    }
+
+    #[test]
+    fn test_io_pattern_payloads() {
+        let mut buf = vec![0u8; 24];
+        counter_pattern(&mut buf, 8);
+        assert_eq!(&buf[..8], &1u64.to_le_bytes());
+        assert_eq!(&buf[16..], &3u64.to_le_bytes());
+        let mut part = vec![0u8; 5];
+        counter_pattern(&mut part, 13);
+        assert_eq!(&buf[5..10], &part[..]);
+
+        let mut cfg = Config::default();
+        assert_eq!(Payload::of(&cfg), Payload::Random);
+        cfg.apply_args(&["--io-pattern".to_string(), "zero".to_string()]);
+        assert_eq!(Payload::of(&cfg), Payload::Fill(0));
+        cfg.io_pattern = IoPattern::Incrementing;
+        assert_eq!(Payload::of(&cfg), Payload::Counter);
+        cfg.io_fill = Some(0xAA);
+        assert_eq!(Payload::of(&cfg), Payload::Fill(0xAA));
+
+        let mut rng = Rng::new(1);
+        let mut written = [0xFFu8; 16];
+        Payload::Counter.refill(&mut written, 4096, &mut rng, None);
+        let mut expected = [0u8; 16];
+        assert_eq!(count_corrupt(&written, &mut expected, 0, 4096, Payload::Counter), 0);
+    }
+
+    #[test]
+    fn test_punch_hole_zeroes_region() {
+        let path = std::env::temp_dir().join("stressr_pretrim_test.tmp");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(&vec![0xAB; 64 * 1024]).unwrap();
+        file.sync_all().unwrap();
+
+        match punch_hole(&file, 64 * 1024) {
+            Ok(()) => {
+                let mut buf = Vec::new();
+                file.seek(SeekFrom::Start(0)).unwrap();
+                file.read_to_end(&mut buf).unwrap();
+                assert_eq!(buf.len(), 64 * 1024);
+                assert!(buf.iter().all(|&b| b == 0));
+            }
+            Err(e) => eprintln!("skipping: hole punching unsupported here ({})", e),
+        }
+        let _ = remove_file(&path);
+    }
+
+    #[test]
+    fn test_extent_count_and_fragmentation() {
+        assert_eq!(fragmentation_score(1, 1 << 30), 0.0);
+        assert_eq!(fragmentation_score(0, 0), 0.0);
+        assert_eq!(fragmentation_score(5, 5 * 4096), 100.0);
+        assert_eq!(fragmentation_score(2, 3 * 4096), 50.0);
+
+        let path = std::env::temp_dir().join("stressr_fiemap_test.tmp");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(&vec![0x5A; 256 * 1024]).unwrap();
+        match file_extent_count(&file) {
+            Ok(extents) => assert!(extents >= 1),
+            Err(e) => eprintln!("skipping: FIEMAP unsupported here ({})", e),
+        }
+        let _ = remove_file(&path);
+    }
+
+    #[test]
+    fn test_verify_pass_detects_corruption() {
+        let file_path = std::env::temp_dir().join("stressr_verify_test.tmp");
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .truncate(true)
+            .open(&file_path)
+            .unwrap();
+
+        prefill_pattern(&mut file, 1, Payload::Random, 3 * 4096).unwrap();
+        assert_eq!(verify_pattern(&mut file, 0, 1, Payload::Random, 3 * 4096).unwrap(), 0);
+
+        let mut byte = [0u8; 1];
+        file.seek(SeekFrom::Start(5000)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::Start(5000)).unwrap();
+        file.write_all(&[!byte[0]]).unwrap();
+        assert_eq!(verify_pattern(&mut file, 0, 1, Payload::Random, 3 * 4096).unwrap(), 1);
+
+        prefill_pattern(&mut file, 1, Payload::Fill(0xAA), 3 * 4096).unwrap();
+        assert_eq!(verify_pattern(&mut file, 0, 1, Payload::Fill(0xAA), 3 * 4096).unwrap(), 0);
+        let corrupt = verify_pattern(&mut file, 0, 1, Payload::Fill(0x55), 3 * 4096).unwrap();
+        assert_eq!(corrupt, 3 * 4096);
+
+        let _ = remove_file(&file_path);
+    }
+
+    #[test]
+    fn test_read_verify_counts_corrupt_bytes() {
+        let mut actual = vec![0u8; 4096];
+        let mut expected = vec![0u8; 4096];
+        fill_pattern(&mut actual, 9, 8192);
+        assert_eq!(count_corrupt(&actual, &mut expected, 9, 8192, Payload::Random), 0);
+        actual[10] ^= 0xFF;
+        actual[4000] ^= 0x01;
+        assert_eq!(count_corrupt(&actual, &mut expected, 9, 8192, Payload::Random), 2);
+        assert!(count_corrupt(&actual, &mut expected, 9, 0, Payload::Random) > 4000);
+
+        let cfg = Config {
+            io_size_bytes: 1024 * 1024,
+            io_duration_secs: 1,
+            io_random: true,
+            io_read: true,
+            io_write: true,
+            io_verify: true,
+            ..Config::default()
+        };
+        let path = std::env::temp_dir().join("stressr_read_verify");
+        std::fs::create_dir_all(&path).unwrap();
+        let stats = disk_io_worker(path.to_str().unwrap(), 0, &cfg).unwrap();
+        assert!(stats.read_bytes > 0);
+        assert_eq!(stats.corrupt_reads, 0);
+        assert_eq!(stats.first_corrupt_offset, None);
+        let _ = std::fs::remove_dir(&path);
+    }
+
+    #[test]
+    fn test_parse_fill_byte() {
+        assert_eq!(parse_fill_byte("FF"), Some(0xFF));
+        assert_eq!(parse_fill_byte("0xaa"), Some(0xAA));
+        assert_eq!(parse_fill_byte("5"), Some(0x05));
+        assert_eq!(parse_fill_byte("100"), None);
+        assert_eq!(parse_fill_byte("zz"), None);
+    }
+
+    #[test]
+    fn test_random_offsets_more_uniform_than_lcg() {
+        // The old generator: a plain LCG reduced with `% range`.
+        let mut lcg = 42u64;
+        let mut lcg_offset = |range: u64| {
+            lcg = lcg.wrapping_mul(6364136223846793005).wrapping_add(1);
+            lcg % range
+        };
+        let mut rng = Rng::for_stream(42, 0);
+        let (chunks, draws) = (64u64, 16_384);
+
+        let mut buckets = [0u32; 64];
+        let mut lcg_pairs = std::collections::HashSet::new();
+        let mut rng_pairs = std::collections::HashSet::new();
+        let (mut lcg_prev, mut rng_prev) = (0, 0);
+        for _ in 0..draws {
+            let mut cursor = 0;
+            let offset = next_offset(true, &mut rng, &mut cursor, 1, chunks + 1);
+            buckets[offset as usize] += 1;
+            rng_pairs.insert((rng_prev, offset));
+            rng_prev = offset;
+
+            let offset = lcg_offset(chunks);
+            lcg_pairs.insert((lcg_prev, offset));
+            lcg_prev = offset;
+        }
+        assert!(buckets.iter().all(|&n| (192..320).contains(&n)), "{:?}", buckets);
+        // The LCG's low bits cycle with period `chunks`, so each offset is
+        // always followed by the same one (plus the pair from the starting 0);
+        // a good generator visits most pairs.
+        assert!(lcg_pairs.len() <= chunks as usize + 1, "{} distinct pairs", lcg_pairs.len());
+        assert!(rng_pairs.len() > 3000, "{} distinct pairs", rng_pairs.len());
+    }
+
+    #[test]
+    fn test_coverage_tracking() {
+        let mut coverage = Coverage::new(16 * COVERAGE_PAGE);
+        coverage.record(0, COVERAGE_PAGE);
+        coverage.record(100, COVERAGE_PAGE); // straddles pages 0 and 1
+        coverage.record(8 * COVERAGE_PAGE, 4 * COVERAGE_PAGE);
+
+        assert_eq!(coverage.distinct_offsets(), 2);
+        assert_eq!(coverage.fraction(), 6.0 / 16.0);
+    }
+
+    #[test]
+    fn test_format_rate_units_and_precision() {
+        assert_eq!(format_rate(123.456, 2), "123.46 MB/s");
+        assert_eq!(format_rate(123.456, 0), "123 MB/s");
+        assert_eq!(format_rate(1.0, 3), "1.000 MB/s");
+        assert_eq!(format_rate(0.5, 2), "512.00 KB/s");
+        assert_eq!(format_rate(100.0 / 1024.0 / 1024.0, 1), "100.0 B/s");
+        assert_eq!(format_rate(0.0, 2), "0.00 MB/s");
+    }
+
+    #[test]
+    fn test_config_to_args() {
+        let text = "# sample\n\
+                    cpu_percent = 50\n\
+                    io = true\n\
+                    io_random = false\n\
+                    io_paths = \"/mnt/a,/mnt/b\"\n\
+                    label = drive=ssd\n\
+                    \n\
+                    label = rack=4\n";
+        let args = config_to_args(text).unwrap();
+        assert_eq!(
+            args,
+            vec![
+                "--cpu-percent", "50", "--io", "--io-paths", "/mnt/a,/mnt/b", "--label",
+                "drive=ssd", "--label", "rack=4",
+            ]
+        );
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&args);
+        assert_eq!(cfg.cpu_percent, 50);
+        assert!(cfg.io_enabled);
+        assert_eq!(cfg.io_paths, vec!["/mnt/a", "/mnt/b"]);
+        assert_eq!(cfg.labels.len(), 2);
+
+        assert!(config_to_args("cpu_percent 50").is_err());
+        assert!(config_to_args(" = 5").is_err());
+    }
+
+    #[test]
+    fn test_config_file_roundtrip() {
+        let path = std::env::temp_dir().join("stressr_config_roundtrip.conf");
+        std::fs::write(
+            &path,
+            "# nightly soak\n\
+             cpu_percent = 40\n\
+             duration = 5m\n\
+             io = true\n\
+             io_paths = /mnt/a,/mnt/b\n\
+             io_workers = 4\n\
+             io_size = 64M\n\
+             io_duration = 90s\n\
+             turbo_mode = on\n",
+        )
+        .unwrap();
+        let args = load_config(path.to_str().unwrap()).unwrap();
+        let _ = remove_file(&path);
+
+        let mut cfg = Config::default();
+        let unknown = cfg.apply_args(&args);
+        assert_eq!(unknown, vec!["--turbo-mode"]);
+        assert_eq!(cfg.cpu_percent, 40);
+        assert_eq!(cfg.duration_secs, 300);
+        assert!(cfg.io_enabled);
+        assert_eq!(cfg.io_paths, vec!["/mnt/a", "/mnt/b"]);
+        assert_eq!(cfg.io_workers, 4);
+        assert_eq!(cfg.io_size_bytes, 64 * 1024 * 1024);
+        assert_eq!(cfg.io_duration_secs, 90);
+
+        // Command-line flags are applied afterwards and win.
+        cfg.apply_args(&["--io-workers", "8"].map(String::from));
+        assert_eq!(cfg.io_workers, 8);
+    }
+
+    #[test]
+    fn test_env_to_args() {
+        let vars = [
+            ("STRESSR_IO_WORKERS", "4"),
+            ("PATH", "/usr/bin"),
+            ("STRESSR_CPU_PERCENT", "50"),
+            ("STRESSR_DURATION", "2m"),
+            ("STRESSR_IO_RANDOM", "true"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let args = env_to_args(vars);
+        assert_eq!(
+            args,
+            vec!["--cpu-percent", "50", "--duration", "2m", "--io-random", "--io-workers", "4"]
+        );
+
+        let mut cfg = Config::default();
+        assert!(cfg.apply_args(&args).is_empty());
+        cfg.apply_args(&["--io-workers", "8"].map(String::from));
+        assert_eq!(cfg.cpu_percent, 50);
+        assert_eq!(cfg.duration_secs, 120);
+        assert!(cfg.io_random);
+        assert_eq!(cfg.io_workers, 8);
+    }
+
+    #[test]
+    fn test_config_source() {
+        let args: Vec<String> = ["--io", "--config", "a.conf", "--config", "-"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(config_source(&args), Some("-"));
+        assert_eq!(config_source(&args[..1]), None);
+    }
+
+    #[test]
+    fn test_http_get_config() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (conn, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(conn);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(request_line.starts_with("GET /fleet.conf HTTP/1.0"));
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 0 && header != "\r\n" {
+                header.clear();
+            }
+            let mut conn = reader.into_inner();
+            conn.write_all(b"HTTP/1.0 200 OK\r\nContent-Length: 15\r\n\r\nio_workers = 4\n")
+                .unwrap();
+        });
+
+        let args = load_config(&format!("http://127.0.0.1:{}/fleet.conf", port)).unwrap();
+        assert_eq!(args, vec!["--io-workers", "4"]);
+        server.join().unwrap();
+
+        assert!(load_config("https://example.com/x").is_err());
+    }
+
+    #[test]
+    fn test_coordinated_barrier_and_results() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let coordinator = thread::spawn(move || run_coordinator(listener, 2));
+
+        let participants: Vec<_> = (0..2)
+            .map(|n| {
+                let addr = addr.clone();
+                thread::spawn(move || {
+                    let name = format!("host{}", n);
+                    let mut stream = wait_for_coordinator(&addr, &name).unwrap();
+                    let result = PeerResult {
+                        name,
+                        io_workers: 2,
+                        io_mbps: 100.0,
+                        io_ops: 10,
+                        io_bytes: 4096,
+                    };
+                    writeln!(stream, "{}", result.to_line()).unwrap();
+                })
+            })
+            .collect();
+
+        for p in participants {
+            p.join().unwrap();
+        }
+        let mut results = coordinator.join().unwrap();
+        results.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "host0");
+        assert_eq!(results[1].io_mbps, 100.0);
+        assert_eq!(results.iter().map(|r| r.io_ops).sum::<u64>(), 20);
+    }
+
+    #[test]
+    fn test_compare_metrics() {
+        let saved = format_metrics(&[("io_mbps".into(), 200.0), ("io_ops".into(), 1000.0)]);
+        assert!(saved.starts_with("# stressr_result_version=2\n"));
+        let previous = parse_metrics(&saved);
+        assert_eq!(previous.len(), 2);
+        let current = vec![
+            ("io_mbps".into(), 150.0),
+            ("io_ops".into(), 1020.0),
+            ("new_metric".into(), 1.0),
+        ];
+
+        let lines = compare_metrics(&previous, &current);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "io_mbps: 200.00 -> 150.00 (-25.0%)  REGRESSION");
+        assert_eq!(lines[1], "io_ops: 1000.00 -> 1020.00 (+2.0%)");
+    }
+
+    #[test]
+    fn test_preflight() {
+        let cfg = Config {
+            cpu_percent: 10,
+            memory_percent: 1,
+            io_enabled: true,
+            io_size_bytes: 1024 * 1024,
+            ..Config::default()
+        };
+        let results = run_preflight(&cfg);
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|(_, outcome)| outcome.is_ok()), "{:?}", results);
+
+        let cfg = Config {
+            io_paths: vec!["/nonexistent/stressr".into()],
+            ..cfg
+        };
+        let results = run_preflight(&cfg);
+        assert!(results[2].1.is_err());
+    }
+
+    #[test]
+    fn test_results_to_json() {
+        let cfg = Config {
+            labels: vec![("env".into(), "ci".into())],
+            ..Config::default()
+        };
+        let json = cfg.to_json().to_string();
+        assert!(json.contains(r#""io_workers":2"#), "{}", json);
+        assert!(json.contains(r#""labels":{"env":"ci"}"#), "{}", json);
+
+        let stats = WorkerStats {
+            worker_id: 1,
+            ops: 10,
+            ttfb: None,
+            ..WorkerStats::default()
+        };
+        let json = stats.to_json("WR").to_string();
+        assert!(json.starts_with(r#"{"worker_id":1,"mode":"WR""#), "{}", json);
+        assert!(json.contains(r#""ttfb_us":null"#), "{}", json);
+    }
+
+    #[test]
+    fn test_percent_validation() {
+        for (percent, ok) in [(0, true), (100, true), (101, false)] {
+            let cpu = Config { cpu_percent: percent, ..Config::default() };
+            assert_eq!(cpu.validate().is_ok(), ok, "--cpu-percent {}", percent);
+            let memory = Config { memory_percent: percent, ..Config::default() };
+            assert_eq!(memory.validate().is_ok(), ok, "--memory-percent {}", percent);
+        }
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--io-workers", "many", "--cpu-percent"].map(String::from));
+        assert_eq!(cfg.io_workers, 2);
+        assert_eq!(cfg.cpu_percent, 100);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_duration("10s"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("5x"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("-5"), None);
+        assert_eq!(parse_duration("+5s"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--duration", "2h", "--io-duration", "soon"].map(String::from));
+        assert_eq!(cfg.duration_secs, 7200);
+        assert_eq!(cfg.io_duration_secs, 30);
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512K", 1024), Some(512 * 1024));
+        assert_eq!(parse_size("100M", 1024), Some(100 * 1024 * 1024));
+        assert_eq!(parse_size("4G", 1024), Some(4 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("4g", 1024), parse_size("4G", 1024));
+        assert_eq!(parse_size("512k", 1), Some(512 * 1024));
+        assert_eq!(parse_size("100", 1024 * 1024), Some(100 * 1024 * 1024));
+        assert_eq!(parse_size("64", 1024), Some(64 * 1024));
+        assert_eq!(parse_size("10GB", 1024), None);
+        assert_eq!(parse_size("G", 1024), None);
+        assert_eq!(parse_size("", 1024), None);
+        assert_eq!(parse_size("-1M", 1024), None);
+        assert_eq!(parse_size("99999999999G", 1024), None);
+
+        let mut cfg = Config::default();
+        let args = ["--io-size", "4G", "--chunk-size", "1M", "--io-read-chunk", "8"];
+        cfg.apply_args(&args.map(String::from));
+        assert_eq!(cfg.io_size_bytes, 4 << 30);
+        assert_eq!(cfg.chunk_size_bytes, 1 << 20);
+        assert_eq!(cfg.read_chunk_bytes(), 8 << 10);
+        assert_eq!(cfg.write_chunk_bytes(), 1 << 20);
+    }
+
+    #[test]
+    fn test_parse_label() {
+        assert_eq!(
+            parse_label("drive=samsung980"),
+            Some(("drive".to_string(), "samsung980".to_string()))
+        );
+        assert_eq!(
+            parse_label("note=a=b"),
+            Some(("note".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(parse_label("empty="), Some(("empty".to_string(), String::new())));
+        assert_eq!(parse_label("=value"), None);
+        assert_eq!(parse_label("novalue"), None);
+    }
+
+    #[test]
+    fn test_benchmark_sweep_peak() {
+        // Scales to 8, then flattens: the step to 16 gains under 5%.
+        let curve = |n: usize| n.min(8) as f64 * 100.0 + n as f64;
+        let mut probed = Vec::new();
+        let points = sweep_points(1, 64, &[]);
+        let peak = sweep_peak(&points, |n| {
+            probed.push(n);
+            Some(curve(n))
+        });
+        assert_eq!(probed, vec![1, 2, 4, 8, 16]);
+        assert_eq!(peak, Some((16, curve(16))));
+        assert_eq!(sweep_peak(&points, |n| Some(100.0 / n as f64)), Some((1, 100.0)));
+        assert!(sweep_points(4, 2, &[]).is_empty());
+        // A failed probe ends the sweep and is not counted.
+        assert_eq!(sweep_peak(&points, |n| (n < 4).then_some(n as f64)), Some((2, 2.0)));
+
+        // The core count and its double are probed even off the doubling.
+        assert_eq!(sweep_points(1, 12, &[6, 12]), vec![1, 2, 4, 6, 8, 12]);
+        assert_eq!(sweep_points(1, 8, &[4, 8]), vec![1, 2, 4, 8]);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--benchmark".to_string()]);
+        assert!(cfg.benchmark);
+    }
+
+    #[test]
+    fn test_cpu_workload_parse() {
+        assert_eq!(CpuWorkload::parse("int"), Some(CpuWorkload::Int));
+        assert_eq!(CpuWorkload::parse("avx512"), Some(CpuWorkload::Avx512));
+        assert_eq!(CpuWorkload::parse("matrix"), Some(CpuWorkload::Matrix));
+        assert_eq!(CpuWorkload::parse("primes"), Some(CpuWorkload::Primes));
+        assert_eq!(CpuWorkload::parse("avx2"), None);
+        // pi(262144)
+        assert_eq!(primes_burst(), 23_000);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--cpu-method".to_string(), "sqrt".to_string()]);
+        assert_eq!(cfg.cpu_workload, CpuWorkload::Sqrt);
+        for workload in ["int", "float", "sqrt", "matrix", "primes"] {
+            run_cpu_kernel(CpuWorkload::parse(workload).unwrap(), &mut CacheChain::none());
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_windows_total_memory() {
+        let status = memory_status().expect("GlobalMemoryStatusEx failed");
+        assert!(status.ullTotalPhys >= 256 * 1024 * 1024, "{} bytes", status.ullTotalPhys);
+        assert!(status.ullAvailPhys <= status.ullTotalPhys);
+        assert_eq!(read_total_memory_kb(), status.ullTotalPhys / 1024);
+    }
+
+    #[test]
+    fn test_cgroup_cpu_quota() {
+        assert_eq!(parse_cgroup_cpu_max("200000 100000\n"), Some(2));
+        assert_eq!(parse_cgroup_cpu_max("150000 100000"), Some(2));
+        assert_eq!(parse_cgroup_cpu_max("50000 100000"), Some(1));
+        assert_eq!(parse_cgroup_cpu_max("400000"), Some(4));
+        assert_eq!(parse_cgroup_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cgroup_cpu_max(""), None);
+        assert_eq!(parse_cgroup_cfs_quota("300000\n", "100000\n"), Some(3));
+        assert_eq!(parse_cgroup_cfs_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cgroup_cfs_quota("25000", "0"), None);
+        assert_eq!(cpus_within_quota(Some(2), 8), 2);
+        assert_eq!(cpus_within_quota(Some(64), 8), 8);
+        assert_eq!(cpus_within_quota(None, 8), 8);
+        assert!(detect_cpu_count() >= 1);
+    }
+
+    #[test]
+    fn test_config_to_args_round_trip() {
+        assert!(Config::default().to_args().is_empty());
+        let cfg = Config {
+            cpu_percent: 40,
+            cpu_workload: CpuWorkload::Matrix,
+            cpu_affinity: vec![0, 2],
+            cpu_system_percent: Some(70),
+            memory_use: MemoryBase::Available,
+            memory_hold_secs: Some(5),
+            io_enabled: true,
+            io_paths: vec!["/a".into(), "/b".into()],
+            io_size_bytes: 6000,
+            io_processes: true,
+            io_rwmix: Some(30),
+            io_read: true,
+            io_write: true,
+            io_write_chunk_bytes: Some(4096),
+            io_fsync_every: 8,
+            io_sync_mode: IoSyncMode::Fdatasync,
+            io_pattern: IoPattern::Zero,
+            io_fill: Some(0xa5),
+            io_bandwidth_mbps: Some(12.5),
+            net_size_bytes: 3 << 20,
+            labels: vec![("env".into(), "ci=1".into())],
+            output: OutputFormat::Json,
+            log_level: LogLevel::Debug,
+            start_at: Some(3600 + 61),
+            grace_secs: Some(10),
+            ..Config::default()
+        };
+        let mut back = Config::default();
+        assert!(back.apply_args(&cfg.to_args()).is_empty());
+        assert_eq!(format!("{:?}", back), format!("{:?}", cfg));
+
+        // Only the count changes the sync mode; it stays off.
+        let every = Config { io_fsync_every: 4, ..Config::default() };
+        let mut back = Config::default();
+        back.apply_args(&every.to_args());
+        assert_eq!((back.io_sync_mode, back.io_fsync_every), (IoSyncMode::None, 4));
+    }
+
+    #[test]
+    fn test_from_args_help_and_version() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(matches!(Config::from_args(&[]), Ok(Invocation::Help)));
+        assert!(matches!(Config::from_args(&args(&["stressr", "-h"])), Ok(Invocation::Help)));
+        let version = Config::from_args(&args(&["stressr", "--cpu-percent", "10", "-V"]));
+        assert!(matches!(version, Ok(Invocation::Version)));
+        let run = Config::from_args(&args(&["stressr", "--cpu-percent", "10"]));
+        assert!(matches!(run, Ok(Invocation::Run(cfg)) if cfg.cpu_percent == 10));
+    }
+
+    #[test]
+    fn test_log_level_flags() {
+        let mut cfg = Config::default();
+        assert_eq!(cfg.log_level, LogLevel::Info);
+        cfg.apply_args(&["-q".to_string()]);
+        assert_eq!(cfg.log_level, LogLevel::Error);
+        cfg.apply_args(&["-vv".to_string()]);
+        assert_eq!(cfg.log_level, LogLevel::Debug);
+        assert!(cfg.to_json().to_string().contains("\"log_level\":\"debug\""));
+        assert_eq!(config_to_args("quiet = true").unwrap(), vec!["--quiet"]);
+        cfg.apply_args(&["--log-file".to_string(), "soak.log".to_string()]);
+        assert_eq!(cfg.log_file.as_deref(), Some("soak.log"));
+    }
+
+    #[test]
+    fn test_cache_chain_is_one_cycle() {
+        let lines = 1000;
+        let mut chain = CacheChain::new((lines * CACHE_LINE) as u64, 7, 0);
+        let mut seen = vec![false; lines];
+        let mut pos = 0;
+        for _ in 0..lines {
+            assert!(!seen[pos / CacheChain::STRIDE], "slot {} visited twice", pos);
+            seen[pos / CacheChain::STRIDE] = true;
+            pos = chain.slots[pos];
+        }
+        assert_eq!(pos, 0);
+        assert!(seen.iter().all(|&s| s));
+
+        let again = CacheChain::new((lines * CACHE_LINE) as u64, 7, 0);
+        assert_eq!(chain.slots, again.slots);
+        assert_ne!(chain.slots, CacheChain::new((lines * CACHE_LINE) as u64, 7, 1).slots);
+
+        run_cpu_kernel(CpuWorkload::Cache, &mut chain);
+        assert_eq!(chain.pos % CacheChain::STRIDE, 0);
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--cpu-method".into(), "cache".into(), "--cache-size".into(), "8".into()]);
+        assert_eq!((cfg.cpu_workload, cfg.cache_size_mb), (CpuWorkload::Cache, 8));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_cpu_achieved_percent() {
+        // Run alone in a child, so the other tests' threads do not compete
+        // with the stress thread for the cores.
+        if env::var_os("STRESSR_ACHIEVED_CHILD").is_none() {
+            let status = Command::new(env::current_exe().unwrap())
+                .args(["--exact", "tests::test_cpu_achieved_percent", "--test-threads=1"])
+                .env("STRESSR_ACHIEVED_CHILD", "1")
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let cfg = Config {
+            cpu_percent: 20,
+            cpu_threads: 1,
+            duration_secs: 2,
+            ..Config::default()
+        };
+        let stats = stress_cpu(&cfg);
+        let achieved = stats.achieved_percent.unwrap();
+        assert!((14.0..30.0).contains(&achieved), "achieved {:.1}%", achieved);
+    }
+
+    #[test]
+    fn test_cpu_ramp() {
+        let ramp = Duration::from_secs(10);
+        assert_eq!(ramp_fraction(Duration::ZERO, ramp), 0.0);
+        assert_eq!(ramp_fraction(Duration::from_secs(5), ramp), 0.5);
+        assert_eq!(ramp_fraction(Duration::from_secs(20), ramp), 1.0);
+        assert_eq!(ramp_fraction(Duration::from_secs(1), Duration::ZERO), 1.0);
+        assert_eq!(ramp_average(ramp, Duration::from_secs(20)), 0.75);
+        assert_eq!(ramp_average(Duration::ZERO, Duration::from_secs(20)), 1.0);
+
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--cpu-ramp".into(), "1m".into(), "--duration".into(), "30".into()]);
+        assert_eq!(cfg.cpu_ramp_secs, 60);
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn test_dry_run_plan() {
+        let mut cfg = Config {
+            cpu_percent: 50,
+            cpu_threads: 3,
+            cpu_workload: CpuWorkload::Cache,
+            cache_size_mb: 2,
+            io_enabled: true,
+            io_write: true,
+            io_paths: vec!["/a".to_string(), "/b".to_string()],
+            io_workers: 2,
+            io_size_bytes: 8 * 1024 * 1024,
+            io_bandwidth_mbps: Some(10.0),
+            io_duration_secs: 60,
+            ..Config::default()
+        };
+        cfg.apply_args(&["--dry-run".to_string()]);
+        assert!(cfg.dry_run);
+
+        let plan = RunPlan::of(&cfg);
+        assert_eq!(plan.cpu_threads, 3);
+        assert_eq!(plan.memory_mb, 0);
+        assert_eq!(plan.io_file_bytes(&cfg), 4 * 8 * 1024 * 1024);
+        assert_eq!(plan.io_write_limit_bytes, Some(600 * 1024 * 1024));
+        // Three 2 MB chains plus four workers with two 64 KB buffers each.
+        assert_eq!(plan.peak_memory_mb, 7);
+        let lines = plan.lines(&cfg);
+        assert_eq!(lines[0], "CPU: 3 threads @ 50% (cache workload) for 30s");
+        assert!(lines[1].starts_with("I/O: /a: 2 workers (W, sequential) for 60s, 2 x 8 MB"));
+        assert_eq!(
+            lines[3],
+            "I/O: 32 MB of worker files in total, at most 600 MB written (--io-bandwidth)"
+        );
+    }
+
+    #[test]
+    fn test_thread_names() {
+        assert_eq!(os_thread_name("stressr-cpu-12"), "stressr-cpu-12");
+        assert_eq!(os_thread_name("stressr-io-/tmp-3"), "io-/tmp-3");
+        assert_eq!(os_thread_name("stressr-io-/mnt/scratch/disk1-12"), "io-/mnt/scra-12");
+        let accented = os_thread_name("stressr-io-/dónnéés/ü-7");
+        assert!(accented.len() <= OS_THREAD_NAME_MAX && accented.ends_with("-7"), "{}", accented);
+
+        let name = spawn_named("stressr-mem-0".to_string(), || {
+            thread::current().name().map(str::to_string)
+        });
+        assert_eq!(name.join().unwrap().as_deref(), Some("stressr-mem-0"));
+        #[cfg(target_os = "linux")]
+        {
+            let comm = spawn_named("stressr-io-/mnt/scratch-4".to_string(), || {
+                std::fs::read_to_string("/proc/thread-self/comm").unwrap()
+            });
+            assert_eq!(comm.join().unwrap().trim_end(), "io-/mnt/scrat-4");
+        }
+    }
+
+    #[test]
+    fn test_cpu_total_spreads_load() {
+        let cycle = CPU_CYCLE.as_micros() as u64;
+        assert_eq!(total_load_duty_micros(30, 8, 8), cycle * 30 / 100);
+        assert_eq!(total_load_duty_micros(50, 8, 16), cycle / 4);
+        assert_eq!(total_load_duty_micros(50, 8, 4), cycle);
+        assert_eq!(total_load_duty_micros(100, 8, 2), cycle);
+        assert_eq!(total_load_duty_micros(0, 8, 8), 0);
+    }
+
+    #[test]
+    fn test_cpu_threads_override() {
+        let cfg = Config {
+            cpu_threads: 2,
+            duration_secs: 0,
+            ..Config::default()
+        };
+        let threads = cpu_thread_count(&cfg);
+        assert_eq!(threads, 2);
+        let counter = || Arc::new(AtomicU64::new(0));
+        let handles =
+            spawn_cpu_threads(&cfg, threads, CpuWorkload::Int, counter(), counter(), counter());
+        assert_eq!(handles.len(), 2);
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        let huge = Config { cpu_threads: 100_000, ..Config::default() };
+        assert_eq!(cpu_thread_count(&huge), MAX_CPU_THREADS);
+        assert!(cpu_thread_count(&Config::default()) >= 1);
+    }
+
+    #[test]
+    fn test_cpu_affinity() {
+        assert_eq!(parse_core_list("0, 2,4"), Some(vec![0, 2, 4]));
+        assert_eq!(parse_core_list("0,x"), None);
+        assert_eq!(parse_core_list(""), None);
+
+        #[cfg(target_os = "linux")]
+        {
+            assert!(pin_current_thread(0).is_ok());
+            assert!(pin_current_thread(4096).is_err());
+        }
+    }
+
+    #[test]
+    fn test_avx512_workload_smoke() {
+        // Falls back to the int kernel on CPUs without AVX-512.
+        let cfg = Config {
+            cpu_percent: 10,
+            cpu_workload: CpuWorkload::Avx512,
+            duration_secs: 1,
+            ..Config::default()
+        };
+        stress_cpu(&cfg);
+    }
+
+    #[test]
+    fn test_confirmation_reasons() {
+        let cfg = Config {
+            io_enabled: true,
+            ..Config::default()
+        };
+        assert_eq!(confirmation_reasons(&cfg), (vec![], false));
+
+        let big = Config {
+            io_enabled: true,
+            io_size_bytes: 8 * 1024 * 1024 * 1024,
+            io_workers: 2,
+            ..Config::default()
+        };
+        let (reasons, destructive) = confirmation_reasons(&big);
+        assert_eq!(reasons.len(), 1);
+        assert!(!destructive);
+
+        let raw = Config {
+            io_enabled: true,
+            io_paths: vec!["/dev/sdb".into()],
+            memory_percent: 95,
+            ..Config::default()
+        };
+        let (reasons, destructive) = confirmation_reasons(&raw);
+        assert_eq!(reasons.len(), 2);
+        assert!(destructive);
+    }
+
+    #[test]
+    fn test_resource_usage() {
+        if cfg!(unix) {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(50) {
+                std::hint::black_box(Rng::new(1).next_u64());
+            }
+            let usage = resource_usage(false).expect("getrusage should succeed");
+            assert!(usage.user + usage.system > Duration::ZERO);
+            assert!(usage.max_rss_kb > 0);
+            assert!(usage.summary().starts_with("user "));
+        }
+    }
+
+    #[test]
+    fn test_cpu_utilization() {
+        assert_eq!(cpu_utilization((100, 400), (150, 500)), 50.0);
+        assert_eq!(cpu_utilization((100, 400), (100, 400)), 0.0);
+        if cfg!(target_os = "linux") {
+            let (busy, total) = read_cpu_times().unwrap();
+            assert!(busy <= total);
+        }
+    }
+
+    #[test]
+    fn test_adjusted_cpu_percent() {
+        assert_eq!(adjusted_cpu_percent(50, true), 60);
+        assert_eq!(adjusted_cpu_percent(95, true), 100);
+        assert_eq!(adjusted_cpu_percent(50, false), 40);
+        assert_eq!(adjusted_cpu_percent(5, false), 0);
+        let mut cfg = Config::default();
+        cfg.apply_args(&["--signal-adjust".to_string()]);
+        assert!(cfg.validate().is_err());
+        assert!(Config { cpu_percent: 50, ..cfg }.validate().is_ok());
+    }
+
+    #[test]
+    fn test_next_throttle_level() {
+        assert_eq!(next_throttle_level(0, false), 1);
+        assert_eq!(next_throttle_level(1, false), 2);
+        assert_eq!(next_throttle_level(MAX_THROTTLE_LEVEL, false), MAX_THROTTLE_LEVEL);
+        assert_eq!(next_throttle_level(MAX_THROTTLE_LEVEL, true), 0);
+        assert_eq!(next_throttle_level(0, true), 0);
+    }
+
+    #[test]
+    fn test_cpu_trace_written() {
+        let trace_path = std::env::temp_dir().join("stressr_cpu_trace_test.csv");
+        let cfg = Config {
+            cpu_percent: 50,
+            duration_secs: 1,
+            cpu_trace: Some(trace_path.to_str().unwrap().to_string()),
+            ..Config::default()
+        };
+        stress_cpu(&cfg);
+
+        let trace = std::fs::read_to_string(&trace_path).unwrap();
+        let mut lines = trace.lines();
+        assert_eq!(lines.next(), Some("timestamp_ms,elapsed_ms,busy_fraction"));
+        let fractions: Vec<f64> = lines
+            .map(|l| l.rsplit(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert!(fractions.len() >= 5, "expected ~10 samples, got {}", fractions.len());
+        assert!(fractions.iter().all(|f| (0.0..=1.0).contains(f)));
+        assert!(fractions.iter().any(|&f| f > 0.0));
+
+        let _ = remove_file(&trace_path);
+    }
+
+    #[test]
+    fn test_cpu_stress_smoke() {
+        let cfg = Config {
+            cpu_percent: 10,
+            duration_secs: 1,
+            ..Config::default()
+        };
+        let stats = stress_cpu(&cfg);
+        assert!(stats.ops > 0);
+        assert!(stats.elapsed >= Duration::from_secs(1));
+        assert!(stats.mops() > 0.0);
+        let load = stats.load_percent();
+        assert!((5.0..20.0).contains(&load), "measured {:.1}% for a 10% target", load);
+        assert!(calibrate_kernel(CpuWorkload::Int, &mut CacheChain::none()) > 0.0);
+    }
+
+    #[test]
+    fn test_rapl_energy_wraparound() {
+        assert_eq!(energy_delta_uj(100, 300, 1000), 200);
+        assert_eq!(energy_delta_uj(900, 100, 1000), 200);
+        let joules = rapl_joules(&[(900, 1000), (0, 1000)], &[(100, 1000), (2_000, 1000)]);
+        assert_eq!(joules, 2_200.0 / 1e6);
+        if let Some(zones) = read_rapl_energy() {
+            assert!(zones.iter().all(|&(energy, max)| energy <= max));
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Leveled diagnostics on stderr. Results go to stdout through `report!`;
+//! everything else goes through `error!`, `warn!`, `info!` or `debug!`, and
+//! is dropped when it is below the level set by `--quiet` or `-v`. Both can
+//! be held back while `--tui` owns the screen. Diagnostics are also appended,
+//! timestamped, to the `--log-file` if there is one.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Severity of a diagnostic, from most to least important.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Drops messages less important than `level` from now on.
+pub(crate) fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Backs the macros; prints `args` to stderr when `level` is enabled.
+pub(crate) fn log(level: LogLevel, args: fmt::Arguments) {
+    if enabled(level) {
+        let line = args.to_string();
+        append(level.name(), &line);
+        emit(true, line);
+    }
+}
+
+/// The `--log-file`, when one is open.
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// Appends every diagnostic from now on to `path`, after what it holds.
+pub(crate) fn open_file(path: &str) -> std::io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    *FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+    Ok(())
+}
+
+/// Records `args` in the log file only, whatever the level, for the run's
+/// bookends.
+pub(crate) fn note(args: fmt::Arguments) {
+    append("note", &args.to_string());
+}
+
+/// Writes one timestamped record to the log file, if there is one. Each
+/// record is a single write so a killed run still leaves whole lines.
+fn append(tag: &str, line: &str) {
+    if let Some(file) = FILE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        let record = format!("{} [{}] {}\n", timestamp(SystemTime::now()), tag, line);
+        let _ = file.write_all(record.as_bytes());
+    }
+}
+
+/// `time` in UTC as RFC 3339 with milliseconds, e.g. 2026-10-16T08:05:09.042Z.
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    // Howard Hinnant's civil_from_days, for days since 1970-01-01.
+    let z = secs / 86_400 + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since.subsec_millis()
+    )
+}
+
+/// Lines held back while holding, each with whether it was bound for stderr.
+/// None when lines go straight out.
+static HELD: Mutex<Option<Vec<(bool, String)>>> = Mutex::new(None);
+
+fn held() -> std::sync::MutexGuard<'static, Option<Vec<(bool, String)>>> {
+    HELD.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Prints `line` to stdout, or to stderr when `stderr` is set, unless lines
+/// are being held.
+pub(crate) fn emit(stderr: bool, line: String) {
+    match held().as_mut() {
+        Some(lines) => lines.push((stderr, line)),
+        None if stderr => eprintln!("{}", line),
+        None => println!("{}", line),
+    }
+}
+
+/// Holds every line from now on until `release`.
+pub(crate) fn hold() {
+    held().get_or_insert_with(Vec::new);
+}
+
+/// Stops holding and prints the held lines in order, each where it was
+/// bound.
+pub(crate) fn release() {
+    let lines = held().take().unwrap_or_default();
+    for (stderr, line) in lines {
+        emit(stderr, line);
+    }
+}
+
+/// Stops holding and prints the held diagnostics, dropping the results.
+pub(crate) fn release_diagnostics() {
+    let lines = held().take().unwrap_or_default();
+    for (_, line) in lines.into_iter().filter(|(stderr, _)| *stderr) {
+        emit(true, line);
+    }
+}
+
+/// The last `n` held lines, oldest first.
+pub(crate) fn recent(n: usize) -> Vec<String> {
+    let held = held();
+    let lines = held.as_deref().unwrap_or_default();
+    lines[lines.len().saturating_sub(n)..].iter().map(|(_, line)| line.clone()).collect()
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Error, format_args!($($arg)*))
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Warn, format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Info, format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Debug, format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_filter() {
+        set_level(LogLevel::Warn);
+        assert!(enabled(LogLevel::Error));
+        assert!(enabled(LogLevel::Warn));
+        assert!(!enabled(LogLevel::Info));
+        set_level(LogLevel::Debug);
+        assert!(enabled(LogLevel::Debug));
+        set_level(LogLevel::Info);
+        assert!(!enabled(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let at = |secs: u64, millis: u64| {
+            timestamp(UNIX_EPOCH + std::time::Duration::from_millis(secs * 1000 + millis))
+        };
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(at(951_782_400, 5), "2000-02-29T00:00:00.005Z");
+        assert_eq!(at(1_792_137_909, 42), "2026-10-16T08:05:09.042Z");
+    }
+}
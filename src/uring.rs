@@ -0,0 +1,321 @@
+//! A minimal io_uring for `--io-queue-depth`: raw `io_uring_setup` and
+//! `io_uring_enter` syscalls, the three shared ring mappings, and read/write
+//! submissions. Needs Linux 5.6 or later for `IORING_OP_READ`/`WRITE`.
+
+use std::ffi::{c_int, c_long, c_void};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The io_uring syscalls share these numbers on every architecture.
+const SYS_IO_URING_SETUP: c_long = 425;
+const SYS_IO_URING_ENTER: c_long = 426;
+
+const IORING_OFF_SQ_RING: i64 = 0;
+const IORING_OFF_CQ_RING: i64 = 0x800_0000;
+const IORING_OFF_SQES: i64 = 0x1000_0000;
+
+const IORING_ENTER_GETEVENTS: u32 = 1;
+/// Added in 5.6 together with `IORING_OP_READ` and `IORING_OP_WRITE`.
+const IORING_FEAT_RW_CUR_POS: u32 = 1 << 3;
+const IORING_OP_READ: u8 = 22;
+const IORING_OP_WRITE: u8 = 23;
+
+const MAP_SHARED: c_int = 0x01;
+const MAP_POPULATE: c_int = 0x8000;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqRingOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqRingOffsets,
+    cq_off: CqRingOffsets,
+}
+
+/// A submission queue entry, laid out as `struct io_uring_sqe`.
+#[repr(C)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    addr3: u64,
+    pad: u64,
+}
+
+/// A completion queue entry, laid out as `struct io_uring_cqe`.
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+/// One shared mapping of the ring fd, unmapped on drop.
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: &OwnedFd, len: usize, offset: i64) -> io::Result<Self> {
+        // SAFETY: maps `len` bytes of the ring at one of its documented
+        // offsets; the result is checked against MAP_FAILED before use.
+        let ptr = unsafe {
+            super::mmap(
+                std::ptr::null_mut(),
+                len,
+                super::PROT_READ_WRITE,
+                MAP_SHARED | MAP_POPULATE,
+                fd.as_raw_fd(),
+                offset,
+            )
+        };
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr: ptr.cast(), len })
+    }
+
+    /// The ring field `offset` bytes in, which the kernel updates too.
+    fn atomic(&self, offset: u32) -> &AtomicU32 {
+        // SAFETY: the offsets come from the kernel and point at aligned u32
+        // ring fields inside the mapping, which lives as long as `self`.
+        unsafe { &*self.ptr.add(offset as usize).cast::<AtomicU32>() }
+    }
+
+    fn get(&self, offset: u32) -> u32 {
+        self.atomic(offset).load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` and `len` are what mmap returned and was asked for.
+        unsafe {
+            super::munmap(self.ptr.cast::<c_void>(), self.len);
+        }
+    }
+}
+
+/// An io_uring instance with room for `entries` operations in flight.
+pub(crate) struct Ring {
+    fd: OwnedFd,
+    sq: Mapping,
+    cq: Mapping,
+    sqes: Mapping,
+    params: Params,
+    /// Entries queued with `push` but not yet handed to the kernel.
+    pending: u32,
+}
+
+impl Ring {
+    pub(crate) fn new(entries: u32) -> io::Result<Self> {
+        let mut params = Params::default();
+        // SAFETY: io_uring_setup fills in `params`, a correctly laid out
+        // struct io_uring_params, and returns a new fd or -1.
+        let fd =
+            unsafe { super::syscall(SYS_IO_URING_SETUP, entries, &mut params as *mut Params) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the fd was just returned to us and nothing else owns it.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd as c_int) };
+        if params.features & IORING_FEAT_RW_CUR_POS == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "kernel io_uring lacks IORING_OP_READ/WRITE (needs Linux 5.6)",
+            ));
+        }
+
+        let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+        let cq_len = params.cq_off.cqes as usize + params.cq_entries as usize * size_of::<Cqe>();
+        let sqes_len = params.sq_entries as usize * size_of::<Sqe>();
+        Ok(Ring {
+            sq: Mapping::new(&fd, sq_len, IORING_OFF_SQ_RING)?,
+            cq: Mapping::new(&fd, cq_len, IORING_OFF_CQ_RING)?,
+            sqes: Mapping::new(&fd, sqes_len, IORING_OFF_SQES)?,
+            fd,
+            params,
+            pending: 0,
+        })
+    }
+
+    /// Queues a read (`write == false`) or write of `len` bytes at `buf` to
+    /// `offset` in `fd`, tagged with `user_data`. The caller keeps `buf`
+    /// alive and untouched until the matching completion is popped, and
+    /// never has more entries in flight than the ring was created for.
+    pub(crate) fn push(
+        &mut self,
+        fd: &impl AsRawFd,
+        write: bool,
+        buf: *mut u8,
+        len: u32,
+        offset: u64,
+        user_data: u64,
+    ) {
+        let off = &self.params.sq_off;
+        let tail = self.sq.get(off.tail);
+        let index = tail & self.sq.get(off.ring_mask);
+        let sqe = Sqe {
+            opcode: if write { IORING_OP_WRITE } else { IORING_OP_READ },
+            flags: 0,
+            ioprio: 0,
+            fd: fd.as_raw_fd(),
+            off: offset,
+            addr: buf as u64,
+            len,
+            rw_flags: 0,
+            user_data,
+            buf_index: 0,
+            personality: 0,
+            splice_fd_in: 0,
+            addr3: 0,
+            pad: 0,
+        };
+        // SAFETY: `index` is masked into the SQE array, and the kernel does
+        // not read that slot until the tail below moves past it.
+        unsafe {
+            self.sqes.ptr.cast::<Sqe>().add(index as usize).write(sqe);
+            let array = self.sq.ptr.add(off.array as usize).cast::<u32>();
+            array.add(index as usize).write(index);
+        }
+        self.sq.atomic(off.tail).store(tail.wrapping_add(1), Ordering::Release);
+        self.pending += 1;
+    }
+
+    /// Hands the queued entries to the kernel and waits until at least
+    /// `min_complete` completions are ready.
+    pub(crate) fn submit_and_wait(&mut self, min_complete: u32) -> io::Result<()> {
+        loop {
+            // SAFETY: io_uring_enter on our own ring fd with no signal mask.
+            let ret = unsafe {
+                super::syscall(
+                    SYS_IO_URING_ENTER,
+                    self.fd.as_raw_fd(),
+                    self.pending,
+                    min_complete,
+                    IORING_ENTER_GETEVENTS,
+                    std::ptr::null::<c_void>(),
+                    0usize,
+                )
+            };
+            if ret >= 0 {
+                self.pending -= ret as u32;
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    /// The next completion as (user_data, result), where the result is the
+    /// byte count or a negated errno.
+    pub(crate) fn pop(&mut self) -> Option<(u64, i32)> {
+        let off = &self.params.cq_off;
+        let head = self.cq.get(off.head);
+        if head == self.cq.atomic(off.tail).load(Ordering::Acquire) {
+            return None;
+        }
+        let index = head & self.cq.get(off.ring_mask);
+        // SAFETY: entries between head and tail are completions the kernel
+        // has finished writing, as the acquire load above guarantees.
+        let cqe = unsafe {
+            self.cq.ptr.add(off.cqes as usize).cast::<Cqe>().add(index as usize).read()
+        };
+        self.cq.atomic(off.head).store(head.wrapping_add(1), Ordering::Release);
+        Some((cqe.user_data, cqe.res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    #[test]
+    fn test_ring_layouts() {
+        assert_eq!(size_of::<Params>(), 120);
+        assert_eq!(size_of::<Sqe>(), 64);
+        assert_eq!(size_of::<Cqe>(), 16);
+    }
+
+    #[test]
+    fn test_ring_write_then_read() {
+        let mut ring = match Ring::new(4) {
+            Ok(ring) => ring,
+            Err(e) => return eprintln!("skipping: io_uring unavailable here ({})", e),
+        };
+        let path = std::env::temp_dir().join("stressr_uring_test.tmp");
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(&path)
+            .unwrap();
+
+        let mut data = *b"queued";
+        ring.push(&file, true, data.as_mut_ptr(), data.len() as u32, 4, 7);
+        ring.submit_and_wait(1).unwrap();
+        assert_eq!(ring.pop(), Some((7, data.len() as i32)));
+        assert_eq!(ring.pop(), None);
+        let mut contents = Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, b"\0\0\0\0queued");
+
+        file.write_all(b"!").unwrap();
+        let mut back = [0u8; 4];
+        ring.push(&file, false, back.as_mut_ptr(), 4, 0, 8);
+        ring.submit_and_wait(1).unwrap();
+        assert_eq!(ring.pop(), Some((8, 4)));
+        assert_eq!(&back, b"\0\0\0\0");
+        let _ = std::fs::remove_file(&path);
+    }
+}